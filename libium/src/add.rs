@@ -1,14 +1,26 @@
 use crate::{
     config::{
         filters::{Filter, ReleaseChannel},
-        structs::{ModIdentifier, ModLoader, Profile},
+        structs::{
+            Environment, Forge, ForgeRepository, MavenArtifact, ModIdentifier, ModLoader, Profile,
+            ProjectType,
+        },
     },
+    forge,
     iter_ext::IterExt as _,
-    upgrade::{check, Metadata},
+    lockfile::read_lockfile,
+    maven, search,
+    upgrade::{
+        check, from_forge_releases, from_gh_releases, from_maven_version, guess_compatibility,
+        url_filename, Hashes, Metadata,
+    },
     CURSEFORGE_API, GITHUB_API, MODRINTH_API,
 };
+use ferinth::structures::project::{Project, ProjectType as MRProjectType};
+use reqwest::StatusCode;
 use serde::Deserialize;
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr};
+use url::Url;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -24,12 +36,20 @@ pub enum Error {
     Incompatible(#[from] check::Error),
     #[error("The project does not exist")]
     DoesNotExist,
-    #[error("The project is not a mod")]
+    #[error("The project is not a mod, resource pack, shader pack, or datapack")]
     NotAMod,
+    #[error("The project is not used on the {} side", .0.to_string().to_lowercase())]
+    IncompatibleEnvironment(Environment),
+    #[error("{0} has declared that it is incompatible with this project")]
+    ConflictsWith(String),
     #[error("The specified version pin does not exist for this mod")]
     IncorrectVersionPin,
     #[error("The identifier provided is not in the correct format")]
     InvalidIdentifier,
+    #[error("Only HTTPS URLs can be added, unless they are of a Modrinth, CurseForge, or GitHub project")]
+    UnsupportedUrl,
+    #[error("Files from a URL need the hash they should have")]
+    MissingHash,
     #[error("GitHub: {0}")]
     GitHubError(String),
     #[error("GitHub: {0:#?}")]
@@ -38,6 +58,14 @@ pub enum Error {
     ModrinthError(#[from] ferinth::Error),
     #[error("CurseForge: {0}")]
     CurseForgeError(#[from] furse::Error),
+    #[error("Maven: {0}")]
+    MavenError(#[from] maven::Error),
+    #[error(transparent)]
+    ForgeError(#[from] forge::Error),
+    #[error("CurseForge: {0}")]
+    SearchError(#[from] search::Error),
+    #[error("Could not read the lockfile: {0}")]
+    LockfileError(#[from] std::io::Error),
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -88,55 +116,402 @@ struct ReleaseAsset {
     name: String,
 }
 
-pub fn parse_id(id: String) -> Result<ModIdentifier> {
-    let split = id.split(':').collect_vec();
-    let (id, pin) = match split.as_slice() {
-        [id, pin] => (id, Some(pin)),
-        [id] => (id, None),
-        _ => return Err(Error::InvalidIdentifier),
-    };
+/// Get the kind of the Modrinth `project`, returns `None` if it can't be added to a profile
+pub fn mr_project_type(project: &Project) -> Option<ProjectType> {
+    match project.project_type {
+        // Modrinth marks datapacks as mods that can only be loaded as a datapack
+        MRProjectType::Mod | MRProjectType::Project if project.loaders == ["datapack"] => {
+            Some(ProjectType::Datapack)
+        }
+        MRProjectType::Mod | MRProjectType::Project => Some(ProjectType::Mod),
+        MRProjectType::ResourcePack => Some(ProjectType::ResourcePack),
+        MRProjectType::Shader => Some(ProjectType::ShaderPack),
+        MRProjectType::Datapack => Some(ProjectType::Datapack),
+        MRProjectType::Plugin | MRProjectType::Modpack => None,
+    }
+}
 
-    if let Ok(id) = id.parse() {
-        Ok(ModIdentifier::CurseForgeProject(
-            id,
-            if let Some(pin) = pin {
-                Some(pin.parse().map_err(|_| Error::InvalidIdentifier)?)
-            } else {
-                None
+/// Get the kind of the CurseForge `project` from the category in its URL,
+/// returns `None` if it can't be added to a profile
+pub fn cf_project_type(project: &furse::structures::mod_structs::Mod) -> Option<ProjectType> {
+    let website_url = project.links.website_url.as_str();
+    if website_url.contains("mc-mods") {
+        Some(ProjectType::Mod)
+    } else if website_url.contains("texture-packs") {
+        Some(ProjectType::ResourcePack)
+    } else if website_url.contains("shaders") {
+        Some(ProjectType::ShaderPack)
+    } else if website_url.contains("data-packs") {
+        Some(ProjectType::Datapack)
+    } else {
+        None
+    }
+}
+
+/// The platforms that identifiers can be prefixed with, or that URLs can be from
+#[derive(Clone, Copy)]
+enum Platform {
+    Modrinth,
+    CurseForge,
+    GitHub,
+    Forge(Forge),
+}
+
+/// Parse `id` into a mod identifier
+///
+/// `id` can be a Modrinth, CurseForge, or GitHub URL of a project, or of one of its versions, files, or releases.
+/// Other HTTPS URLs are added as a direct download, whose hashes have to be set before the mod is added.
+/// Local `.jar` and `.zip` files, or any path prefixed with `file:`, are added by their absolute path.
+/// GitLab, Gitea, and Forgejo repositories are prefixed with `gitlab:`, `gitea:`, or `forgejo:`,
+/// followed by `<owner>/<repo>` on the forge's public instance (Forgejo's is Codeberg) or the URL of the repository on any instance,
+/// and can be followed by `:<asset ID>` to pin them. URLs of repositories on `gitlab.com`, `gitea.com`, and `codeberg.org` don't need the prefix.
+/// Maven artifacts are written as `maven:<repository>/<group>:<artifact>`, e.g. `maven:https://maven.fabricmc.net/net.fabricmc.fabric-api:fabric-api`.
+/// Otherwise, it can be prefixed with `mr:`, `cf:`, or `gh:` to set its platform, or else the platform is guessed:
+/// numbers are CurseForge projects, `owner/repo` are GitHub repositories, and anything else is a Modrinth project.
+/// It can be followed by `:<pin>` to pin it to a version, file, or asset.
+///
+/// URLs of versions and files are pinned to them, and URLs of GitHub releases are pinned to their asset that is compatible with `filters`.
+/// CurseForge slugs and Modrinth version numbers are resolved to their IDs.
+pub async fn parse_id(id: String, filters: &[Filter]) -> Result<ModIdentifier> {
+    if let Some(artifact) = id.strip_prefix("maven:") {
+        return parse_maven(artifact);
+    }
+    if let Some((forge, repository)) = id.split_once(':').and_then(|(prefix, repository)| {
+        Some((
+            match prefix {
+                "gitlab" => Forge::GitLab,
+                "gitea" => Forge::Gitea,
+                "forgejo" => Forge::Forgejo,
+                _ => return None,
             },
+            repository,
         ))
+    }) {
+        return parse_forge(forge, repository, filters).await;
+    }
+    if let Some(path) = local_path(&id) {
+        return Ok(ModIdentifier::LocalPath(
+            path.canonicalize().map_err(|_| Error::DoesNotExist)?,
+        ));
+    }
+
+    let url = id
+        .strip_prefix("https://")
+        .or_else(|| id.strip_prefix("http://"));
+    if let Some((platform, path)) = url_path(url.unwrap_or(&id)) {
+        return parse_url(platform, &path, filters).await;
+    } else if id.starts_with("https://") {
+        return Ok(ModIdentifier::DirectUrl(
+            Url::parse(&id).map_err(|_| Error::InvalidIdentifier)?,
+            Hashes::default(),
+        ));
+    } else if url.is_some() {
+        return Err(Error::UnsupportedUrl);
+    }
+
+    let (platform, id) = match id.split_once(':') {
+        Some(("mr" | "modrinth", id)) => (Some(Platform::Modrinth), id),
+        Some(("cf" | "curseforge", id)) => (Some(Platform::CurseForge), id),
+        Some(("gh" | "github", id)) => (Some(Platform::GitHub), id),
+        _ => (None, id.as_str()),
+    };
+    let (id, pin) = match id.split(':').collect_vec().as_slice() {
+        [id, pin] => (*id, Some(*pin)),
+        [id] => (*id, None),
+        _ => return Err(Error::InvalidIdentifier),
+    };
+    let platform = platform.unwrap_or(if id.parse::<i32>().is_ok() {
+        Platform::CurseForge
+    } else if id.split('/').count() == 2 {
+        Platform::GitHub
     } else {
-        let split = id.split('/').collect_vec();
-        if let [owner, repo] = split.as_slice() {
-            Ok(ModIdentifier::GitHubRepository(
+        Platform::Modrinth
+    });
+
+    match platform {
+        Platform::Modrinth => Ok(ModIdentifier::ModrinthProject(
+            id.to_owned(),
+            pin.map(ToOwned::to_owned),
+        )),
+        Platform::CurseForge => Ok(ModIdentifier::CurseForgeProject(
+            match id.parse() {
+                Ok(id) => id,
+                Err(_) => cf_slug_id(id, None).await?,
+            },
+            pin.map(|pin| pin.parse().map_err(|_| Error::InvalidIdentifier))
+                .transpose()?,
+        )),
+        Platform::GitHub => match id.split('/').collect_vec().as_slice() {
+            [owner, repo] => Ok(ModIdentifier::GitHubRepository(
                 (owner.to_string(), repo.to_string()),
-                if let Some(pin) = pin {
-                    Some(pin.parse().map_err(|_| Error::InvalidIdentifier)?)
-                } else {
-                    None
+                pin.map(ToOwned::to_owned),
+            )),
+            _ => Err(Error::InvalidIdentifier),
+        },
+        Platform::Forge(forge) => {
+            let path = id.split('/').collect_vec();
+            parse_forge_path(forge, forge.default_instance()?, &path, pin, filters).await
+        }
+    }
+}
+
+/// Parse a repository on `forge`, written as `<owner>/<repo>` on the forge's public instance or as the URL of the repository,
+/// optionally followed by `:<asset ID>` to pin it
+async fn parse_forge(forge: Forge, id: &str, filters: &[Filter]) -> Result<ModIdentifier> {
+    let (id, pin) = match id.rsplit_once(':') {
+        Some((id, pin)) if !pin.is_empty() && pin.chars().all(|c| c.is_ascii_digit()) => {
+            (id, Some(pin))
+        }
+        _ => (id, None),
+    };
+    let (instance, path) = if let Some(url) = id.strip_prefix("https://") {
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+        let (host, path) = url.split_once('/').ok_or(Error::InvalidIdentifier)?;
+        (
+            Url::parse(&format!("https://{host}/")).map_err(|_| Error::InvalidIdentifier)?,
+            path,
+        )
+    } else if id.starts_with("http://") {
+        return Err(Error::UnsupportedUrl);
+    } else {
+        (forge.default_instance()?, id)
+    };
+    let path = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect_vec();
+    parse_forge_path(forge, instance, &path, pin, filters).await
+}
+
+/// Parse the `path` of a repository on `forge`'s `instance`, or of one of its releases,
+/// which pins it to the release's asset that is compatible with `filters`
+async fn parse_forge_path(
+    forge: Forge,
+    instance: Url,
+    path: &[&str],
+    pin: Option<&str>,
+    filters: &[Filter],
+) -> Result<ModIdentifier> {
+    let (repository, tag) = match forge {
+        // e.g. gitlab.com/group/project/-/releases/v1.0, projects can be in nested groups
+        Forge::GitLab => match path.iter().position(|segment| *segment == "-") {
+            Some(i) => (
+                &path[..i],
+                match &path[i + 1..] {
+                    ["releases", tag @ ..] if !tag.is_empty() => Some(tag.join("/")),
+                    _ => None,
                 },
-            ))
-        } else {
+            ),
+            None => (path, None),
+        },
+        // e.g. codeberg.org/owner/repo/releases/tag/v1.0
+        Forge::Gitea | Forge::Forgejo => match path {
+            [_, _, rest @ ..] => (
+                &path[..2],
+                match rest {
+                    ["releases", "tag", tag @ ..] if !tag.is_empty() => Some(tag.join("/")),
+                    _ => None,
+                },
+            ),
+            _ => (path, None),
+        },
+    };
+    if repository.len() < 2 {
+        return Err(Error::InvalidIdentifier);
+    }
+    let repository = ForgeRepository {
+        forge,
+        instance,
+        path: repository.join("/").trim_end_matches(".git").to_owned(),
+    };
+    let pin = match tag {
+        Some(tag) => Some(forge_release_asset(&repository, &tag, filters).await?),
+        None => pin.map(ToOwned::to_owned),
+    };
+    Ok(ModIdentifier::ForgeRepository(repository, pin))
+}
+
+/// Parse a Maven artifact written as `<repository>/<group>:<artifact>`, optionally followed by `:<version>` to pin it
+/// and then `:<classifier>`, where the version can be left empty to not pin it
+fn parse_maven(id: &str) -> Result<ModIdentifier> {
+    let (repository, coordinates) = id.rsplit_once('/').ok_or(Error::InvalidIdentifier)?;
+    let repository = Url::parse(repository).map_err(|_| Error::InvalidIdentifier)?;
+    if repository.scheme() != "https" {
+        return Err(Error::UnsupportedUrl);
+    }
+    let (group, artifact, version, classifier) =
+        match coordinates.split(':').collect_vec().as_slice() {
+            [group, artifact] => (*group, *artifact, None, None),
+            [group, artifact, version] => (*group, *artifact, Some(*version), None),
+            [group, artifact, version, classifier] => (
+                *group,
+                *artifact,
+                Some(*version).filter(|version| !version.is_empty()),
+                Some(*classifier),
+            ),
+            _ => return Err(Error::InvalidIdentifier),
+        };
+    if [group, artifact].contains(&"") || version == Some("") || classifier == Some("") {
+        return Err(Error::InvalidIdentifier);
+    }
+    Ok(ModIdentifier::MavenArtifact(
+        MavenArtifact {
+            repository,
+            group: group.to_owned(),
+            artifact: artifact.to_owned(),
+            classifier: classifier.map(ToOwned::to_owned),
+        },
+        version.map(ToOwned::to_owned),
+    ))
+}
+
+/// The path in `id` if it's prefixed with `file:`, or if it's an existing `.jar` or `.zip` file
+fn local_path(id: &str) -> Option<&Path> {
+    if let Some(path) = id.strip_prefix("file:") {
+        return Some(Path::new(path));
+    }
+    let path = Path::new(id);
+    (path
+        .extension()
+        .is_some_and(|extension| extension == "jar" || extension == "zip")
+        && path.is_file())
+    .then_some(path)
+}
+
+/// The platform and path segments of `url` if it's on one of the platforms, `url` shouldn't have a scheme
+fn url_path(url: &str) -> Option<(Platform, Vec<&str>)> {
+    let url = url.strip_prefix("www.").unwrap_or(url);
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    let (host, path) = url.split_once('/')?;
+    let platform = match host {
+        "modrinth.com" => Platform::Modrinth,
+        "curseforge.com" => Platform::CurseForge,
+        "github.com" => Platform::GitHub,
+        "gitlab.com" => Platform::Forge(Forge::GitLab),
+        "gitea.com" => Platform::Forge(Forge::Gitea),
+        "codeberg.org" => Platform::Forge(Forge::Forgejo),
+        _ => return None,
+    };
+    Some((
+        platform,
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect(),
+    ))
+}
+
+async fn parse_url(platform: Platform, path: &[&str], filters: &[Filter]) -> Result<ModIdentifier> {
+    match (platform, path) {
+        // e.g. modrinth.com/mod/sodium/version/mc1.21-0.6.0-fabric
+        (Platform::Modrinth, [_, slug, "version", version, ..]) => {
+            let version = MODRINTH_API.version_get_from_number(slug, version).await?;
             Ok(ModIdentifier::ModrinthProject(
-                id.to_string(),
-                pin.map(ToString::to_string),
+                version.project_id,
+                Some(version.id),
+            ))
+        }
+        // e.g. modrinth.com/mod/sodium
+        (Platform::Modrinth, [_, slug, ..]) => {
+            Ok(ModIdentifier::ModrinthProject(slug.to_string(), None))
+        }
+        // e.g. curseforge.com/minecraft/mc-mods/jei/files/5846880
+        (Platform::CurseForge, ["minecraft", category, slug, rest @ ..]) => {
+            Ok(ModIdentifier::CurseForgeProject(
+                cf_slug_id(slug, Some(category)).await?,
+                match rest {
+                    ["files" | "download", file, ..] => {
+                        Some(file.parse().map_err(|_| Error::InvalidIdentifier)?)
+                    }
+                    _ => None,
+                },
             ))
         }
+        // e.g. github.com/CaffeineMC/sodium/releases/tag/mc1.21-0.6.0
+        (Platform::GitHub, [owner, repo, rest @ ..]) => {
+            let repo = (owner.to_string(), repo.trim_end_matches(".git").to_owned());
+            let pin = match rest {
+                ["releases", "tag", tag @ ..] if !tag.is_empty() => {
+                    Some(gh_release_asset(&repo, &tag.join("/"), filters).await?)
+                }
+                _ => None,
+            };
+            Ok(ModIdentifier::GitHubRepository(repo, pin))
+        }
+        (Platform::Forge(forge), path) => {
+            parse_forge_path(forge, forge.default_instance()?, path, None, filters).await
+        }
+        _ => Err(Error::InvalidIdentifier),
     }
 }
 
+/// Get the ID of the CurseForge project with `slug`, in the `category` of its URL (e.g. `mc-mods`) if provided
+async fn cf_slug_id(slug: &str, category: Option<&str>) -> Result<i32> {
+    search::curseforge_slug(slug)
+        .await?
+        .into_iter()
+        .find(|project| match category {
+            Some(category) => project
+                .links
+                .website_url
+                .path()
+                .contains(&format!("/{category}/")),
+            None => true,
+        })
+        .map(|project| project.id)
+        .ok_or(Error::DoesNotExist)
+}
+
+/// Get the ID of the asset of the GitHub release with `tag` that is compatible with `filters`
+async fn gh_release_asset(
+    repo: &(String, String),
+    tag: &str,
+    filters: &[Filter],
+) -> Result<String> {
+    let release = GITHUB_API
+        .repos(&repo.0, &repo.1)
+        .releases()
+        .get_by_tag(tag)
+        .await?;
+    let files = from_gh_releases(repo, [release.clone()]);
+    let index =
+        check::select_latest(files.iter().map(|(metadata, _)| metadata), filters.to_vec()).await?;
+    Ok(release.assets[index].node_id.clone())
+}
+
+/// Get the ID of the asset of `repository`'s release with `tag` that is compatible with `filters`
+async fn forge_release_asset(
+    repository: &ForgeRepository,
+    tag: &str,
+    filters: &[Filter],
+) -> Result<String> {
+    let release = forge::releases(repository)
+        .await?
+        .into_iter()
+        .find(|release| release.tag == tag)
+        .ok_or(Error::DoesNotExist)?;
+    let files = from_forge_releases(repository, [release.clone()]);
+    let index =
+        check::select_latest(files.iter().map(|(metadata, _)| metadata), filters.to_vec()).await?;
+    Ok(release.assets[index].id.clone())
+}
+
 /// Adds mods from `identifiers`, and returns successful mods with their names, and unsuccessful mods with an error.
 /// Currently does not batch requests when adding multiple pinned mods.
 ///
 /// Classifies the `identifiers` into the appropriate platforms, sends batch requests to get the necessary information,
 /// checks details about the projects, and adds them to `profile` if suitable.
 /// Performs checks on the mods to see whether they're compatible with the profile if `perform_checks` is true
+///
+/// `environment` overrides the environment the mods are used in, which Modrinth projects otherwise report themselves.
+/// If `ignore_conflicts` is false, mods that the profile's locked files have declared they are incompatible with are rejected.
 pub async fn add(
     profile: &mut Profile,
     identifiers: Vec<ModIdentifier>,
     perform_checks: bool,
     override_profile: bool,
     filters: Vec<Filter>,
+    environment: Option<Environment>,
+    ignore_conflicts: bool,
 ) -> Result<(Vec<(String, ModIdentifier)>, Vec<(String, Error)>)> {
     let mut mr_project_ids = Vec::new();
     let mut mr_version_ids = Vec::new();
@@ -147,8 +522,24 @@ pub async fn add(
     let mut gh_repo_ids = Vec::new();
     let mut gh_asset_ids = Vec::new();
 
+    let mut forge_repos = Vec::new();
+
+    let mut maven_artifacts = Vec::new();
+
+    let mut files = Vec::new();
+
     let mut errors = Vec::new();
 
+    let lockfile = read_lockfile(&profile.output_dir)?.unwrap_or_default();
+    // Get the name of the mod in the profile that has declared that it is incompatible with `identifier`
+    let conflicting = |profile: &Profile, identifier: &ModIdentifier| {
+        lockfile.mods.iter().find_map(|locked| {
+            let mod_ = profile.mods.iter().find(|mod_| locked.locks(mod_));
+            (locked.conflicts_with(identifier) && !mod_.is_some_and(|mod_| mod_.ignore_conflicts))
+                .then(|| mod_.map_or_else(|| locked.filename.clone(), |mod_| mod_.name.clone()))
+        })
+    };
+
     for id in identifiers {
         match id {
             ModIdentifier::CurseForgeProject(p, v) => {
@@ -163,6 +554,9 @@ pub async fn add(
                 gh_repo_ids.push(p);
                 gh_asset_ids.push(v);
             }
+            ModIdentifier::ForgeRepository(r, v) => forge_repos.push((r, v)),
+            ModIdentifier::MavenArtifact(a, v) => maven_artifacts.push((a, v)),
+            id @ (ModIdentifier::DirectUrl(..) | ModIdentifier::LocalPath(_)) => files.push(id),
         }
     }
 
@@ -260,9 +654,11 @@ pub async fn add(
         let res = 'cf_check: {
             let identifier = ModIdentifier::CurseForgeProject(project.id, pin);
 
+            // Dependencies that were added implicitly are replaced by the explicitly added mod
             if profile.mods.iter().any(|mod_| {
-                mod_.name.eq_ignore_ascii_case(&project.name)
-                    || mod_.identifier.is_same_as(&identifier)
+                !mod_.is_dependency()
+                    && (mod_.name.eq_ignore_ascii_case(&project.name)
+                        || mod_.identifier.is_same_as(&identifier))
             }) {
                 break 'cf_check Err(Error::AlreadyAdded);
             }
@@ -272,9 +668,16 @@ pub async fn add(
                 break 'cf_check Err(Error::DistributionDenied);
             }
 
-            // Check if the project is a Minecraft mod
-            if !project.links.website_url.as_str().contains("mc-mods") {
+            // Check if the project is a Minecraft mod, resource pack, shader pack, or datapack
+            let Some(project_type) = cf_project_type(&project) else {
                 break 'cf_check Err(Error::NotAMod);
+            };
+
+            // Check if a mod in the profile is incompatible with it
+            if perform_checks && !ignore_conflicts {
+                if let Some(name) = conflicting(profile, &identifier) {
+                    break 'cf_check Err(Error::ConflictsWith(name));
+                }
             }
 
             // Check if the mod is compatible
@@ -318,14 +721,13 @@ pub async fn add(
                         [profile.filters.clone(), filters.clone().clone()].concat()
                     }
                     .iter()
-                    .filter(|f| {
-                        matches!(
-                            f,
-                            Filter::GameVersionStrict(_)
-                                | Filter::GameVersionMinor(_)
-                                | Filter::ModLoaderAny(_)
-                                | Filter::ModLoaderPrefer(_)
-                        )
+                    .filter(|f| match f {
+                        Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_) => true,
+                        // Only mods are made for a mod loader
+                        Filter::ModLoaderAny(_) | Filter::ModLoaderPrefer(_) => {
+                            project_type.is_mod()
+                        }
+                        _ => false,
                     })
                     .cloned()
                     .collect_vec(),
@@ -333,13 +735,16 @@ pub async fn add(
                 .await?;
             }
 
-            profile.push_mod(
+            let mod_ = profile.push_mod(
                 project.name.trim().to_string(),
                 identifier.clone(),
                 project.slug.clone(),
                 override_profile,
                 filters.clone(),
+                environment,
             );
+            mod_.project_type = project_type;
+            mod_.ignore_conflicts = ignore_conflicts;
 
             Ok(identifier)
         };
@@ -365,15 +770,35 @@ pub async fn add(
         let res = 'mr_check: {
             let identifier = ModIdentifier::ModrinthProject(project.id.clone(), pin.clone());
 
+            // Dependencies that were added implicitly are replaced by the explicitly added mod
             if profile.mods.iter().any(|mod_| {
-                mod_.name.eq_ignore_ascii_case(&project.title)
-                    || mod_.identifier.is_same_as(&identifier)
+                !mod_.is_dependency()
+                    && (mod_.name.eq_ignore_ascii_case(&project.title)
+                        || mod_.identifier.is_same_as(&identifier))
             }) {
                 break 'mr_check Err(Error::AlreadyAdded);
             }
 
-            if project.project_type != ferinth::structures::project::ProjectType::Mod {
+            let Some(project_type) = mr_project_type(&project) else {
                 break 'mr_check Err(Error::NotAMod);
+            };
+
+            // Check if the mod is used in the profile's environment
+            if perform_checks
+                && !environment
+                    .unwrap_or_else(|| {
+                        Environment::from_sides(project.client_side, project.server_side)
+                    })
+                    .supports(profile.environment)
+            {
+                break 'mr_check Err(Error::IncompatibleEnvironment(profile.environment));
+            }
+
+            // Check if a mod in the profile is incompatible with it
+            if perform_checks && !ignore_conflicts {
+                if let Some(name) = conflicting(profile, &identifier) {
+                    break 'mr_check Err(Error::ConflictsWith(name));
+                }
             }
 
             if let Some(pin) = &pin {
@@ -401,14 +826,13 @@ pub async fn add(
                         [profile.filters.clone(), filters.clone().clone()].concat()
                     }
                     .iter()
-                    .filter(|f| {
-                        matches!(
-                            f,
-                            Filter::GameVersionStrict(_)
-                                | Filter::GameVersionMinor(_)
-                                | Filter::ModLoaderAny(_)
-                                | Filter::ModLoaderPrefer(_)
-                        )
+                    .filter(|f| match f {
+                        Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_) => true,
+                        // Only mods are made for a mod loader
+                        Filter::ModLoaderAny(_) | Filter::ModLoaderPrefer(_) => {
+                            project_type.is_mod()
+                        }
+                        _ => false,
                     })
                     .cloned()
                     .collect_vec(),
@@ -416,13 +840,16 @@ pub async fn add(
                 .await?;
             }
 
-            profile.push_mod(
+            let mod_ = profile.push_mod(
                 project.title.trim().to_owned(),
                 identifier.clone(),
                 project.slug.to_owned(),
                 override_profile,
                 filters.clone(),
+                environment,
             );
+            mod_.project_type = project_type;
+            mod_.ignore_conflicts = ignore_conflicts;
 
             Ok(identifier)
         };
@@ -438,86 +865,263 @@ pub async fn add(
     );
 
     for (((owner, repo), releases), pin) in gh_repos.into_iter().zip(gh_asset_ids) {
-        let res =
-            'gh_check: {
-                let identifier =
-                    ModIdentifier::GitHubRepository((owner.clone(), repo.clone()), pin.clone());
-
-                if profile.mods.iter().any(|mod_| {
-                    mod_.name.eq_ignore_ascii_case(repo.as_ref())
-                        || matches!(
-                            &mod_.identifier,
-                            ModIdentifier::GitHubRepository((o, r), _) if o == &owner && r == &repo,
-                        )
-                }) {
-                    break 'gh_check Err(Error::AlreadyAdded);
+        let res = 'gh_check: {
+            let identifier =
+                ModIdentifier::GitHubRepository((owner.clone(), repo.clone()), pin.clone());
+
+            if profile.mods.iter().any(|mod_| {
+                mod_.name.eq_ignore_ascii_case(repo.as_ref())
+                    || matches!(
+                        &mod_.identifier,
+                        ModIdentifier::GitHubRepository((o, r), _) if o == &owner && r == &repo,
+                    )
+            }) {
+                break 'gh_check Err(Error::AlreadyAdded);
+            }
+            if let Some(pin) = &pin {
+                if !releases
+                    .into_iter()
+                    .flat_map(|release| release.release_assets.nodes)
+                    .any(|asset| &asset.id == pin)
+                {
+                    break 'gh_check Err(Error::IncorrectVersionPin);
                 }
-                if let Some(pin) = &pin {
-                    if !releases
+            } else if perform_checks {
+                // Check if the repo is compatible
+                check::select_latest(
+                    releases
                         .into_iter()
-                        .flat_map(|release| release.release_assets.nodes)
-                        .any(|asset| &asset.id == pin)
-                    {
-                        break 'gh_check Err(Error::IncorrectVersionPin);
-                    }
-                } else if perform_checks {
-                    // Check if the repo is compatible
-                    check::select_latest(
-                        releases
-                            .into_iter()
-                            .flat_map(|release| {
-                                release.release_assets.nodes.into_iter().map(move |asset| {
-                                    Metadata {
-                                        title: release.name.clone(),
-                                        description: release.description.clone(),
-                                        channel: if release.is_prerelease {
-                                            ReleaseChannel::Beta
-                                        } else {
-                                            ReleaseChannel::Release
-                                        },
-                                        game_versions: asset
-                                            .name
-                                            .trim_end_matches(".jar")
-                                            .split(['-', '_', '+'])
-                                            .map(|s| s.trim_start_matches("mc"))
-                                            .map(ToOwned::to_owned)
-                                            .collect_vec(),
-                                        loaders: asset
-                                            .name
-                                            .trim_end_matches(".jar")
-                                            .split(['-', '_', '+'])
-                                            .filter_map(|s| ModLoader::from_str(s).ok())
-                                            .collect_vec(),
-                                        filename: asset.name,
-                                    }
-                                })
+                        .flat_map(|release| {
+                            release.release_assets.nodes.into_iter().map(move |asset| {
+                                let (game_versions, loaders) = guess_compatibility(&asset.name);
+                                Metadata {
+                                    title: release.name.clone(),
+                                    description: release.description.clone(),
+                                    channel: if release.is_prerelease {
+                                        ReleaseChannel::Beta
+                                    } else {
+                                        ReleaseChannel::Release
+                                    },
+                                    game_versions,
+                                    loaders,
+                                    filename: asset.name,
+                                }
                             })
-                            .collect_vec()
-                            .iter(),
-                        if override_profile {
-                            profile.filters.clone()
-                        } else {
-                            [profile.filters.clone(), filters.clone()].concat()
-                        },
-                    )
-                    .await?;
-                }
+                        })
+                        .collect_vec()
+                        .iter(),
+                    if override_profile {
+                        profile.filters.clone()
+                    } else {
+                        [profile.filters.clone(), filters.clone()].concat()
+                    },
+                )
+                .await?;
+            }
 
-                profile.push_mod(
+            profile
+                .push_mod(
                     repo.clone(),
                     identifier.clone(),
                     repo.clone(),
                     override_profile,
                     filters.clone(),
-                );
+                    environment,
+                )
+                .ignore_conflicts = ignore_conflicts;
 
-                Ok(identifier)
-            };
+            Ok(identifier)
+        };
         match res {
             Ok(id) => success_names.push((format!("{owner}/{repo}"), id)),
             Err(err) => errors.push((format!("{owner}/{repo}"), err)),
         }
     }
 
+    for (repository, pin) in forge_repos {
+        let name = forge::display_name(&repository);
+        let repo = repository
+            .path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        let res = 'forge_check: {
+            let identifier = ModIdentifier::ForgeRepository(repository.clone(), pin.clone());
+
+            if profile.mods.iter().any(|mod_| {
+                mod_.name.eq_ignore_ascii_case(&repo) || mod_.identifier.is_same_as(&identifier)
+            }) {
+                break 'forge_check Err(Error::AlreadyAdded);
+            }
+            let releases = match forge::releases(&repository).await {
+                Ok(releases) => releases,
+                Err(forge::Error::ReqwestError(err))
+                    if err.status() == Some(StatusCode::NOT_FOUND) =>
+                {
+                    break 'forge_check Err(Error::DoesNotExist);
+                }
+                Err(err) => break 'forge_check Err(err.into()),
+            };
+            if let Some(pin) = &pin {
+                if !releases
+                    .iter()
+                    .flat_map(|release| &release.assets)
+                    .any(|asset| &asset.id == pin)
+                {
+                    break 'forge_check Err(Error::IncorrectVersionPin);
+                }
+            } else if perform_checks {
+                // Check if the repo is compatible
+                if let Err(err) = check::select_latest(
+                    from_forge_releases(&repository, releases)
+                        .iter()
+                        .map(|(metadata, _)| metadata),
+                    if override_profile {
+                        profile.filters.clone()
+                    } else {
+                        [profile.filters.clone(), filters.clone()].concat()
+                    },
+                )
+                .await
+                {
+                    break 'forge_check Err(err.into());
+                }
+            }
+
+            profile
+                .push_mod(
+                    repo.clone(),
+                    identifier.clone(),
+                    repo.clone(),
+                    override_profile,
+                    filters.clone(),
+                    environment,
+                )
+                .ignore_conflicts = ignore_conflicts;
+
+            Ok(identifier)
+        };
+        match res {
+            Ok(id) => success_names.push((name, id)),
+            Err(err) => errors.push((name, err)),
+        }
+    }
+
+    for (artifact, pin) in maven_artifacts {
+        let name = maven::display_name(&artifact);
+        // Artifacts with a classifier are named after it too, since the artifact can be added with other classifiers
+        let mod_name = match &artifact.classifier {
+            Some(classifier) => format!("{}-{classifier}", artifact.artifact),
+            None => artifact.artifact.clone(),
+        };
+        let res = 'maven_check: {
+            let identifier = ModIdentifier::MavenArtifact(artifact.clone(), pin.clone());
+
+            if profile.mods.iter().any(|mod_| {
+                mod_.name.eq_ignore_ascii_case(&mod_name) || mod_.identifier.is_same_as(&identifier)
+            }) {
+                break 'maven_check Err(Error::AlreadyAdded);
+            }
+            let versions = match maven::versions(&artifact).await {
+                Ok(versions) if versions.is_empty() => break 'maven_check Err(Error::DoesNotExist),
+                Ok(versions) => versions,
+                Err(err) => break 'maven_check Err(err.into()),
+            };
+            if let Some(pin) = &pin {
+                if !versions.contains(pin) {
+                    break 'maven_check Err(Error::IncorrectVersionPin);
+                }
+            } else if perform_checks {
+                // Check if any version is compatible
+                let metadata = match versions
+                    .into_iter()
+                    .map(|version| {
+                        from_maven_version(&artifact, version).map(|(metadata, _)| metadata)
+                    })
+                    .collect::<maven::Result<Vec<_>>>()
+                {
+                    Ok(metadata) => metadata,
+                    Err(err) => break 'maven_check Err(err.into()),
+                };
+                if let Err(err) = check::select_latest(
+                    metadata.iter(),
+                    if override_profile {
+                        profile.filters.clone()
+                    } else {
+                        [profile.filters.clone(), filters.clone()].concat()
+                    },
+                )
+                .await
+                {
+                    break 'maven_check Err(err.into());
+                }
+            }
+
+            profile
+                .push_mod(
+                    mod_name.clone(),
+                    identifier.clone(),
+                    mod_name.clone(),
+                    override_profile,
+                    filters.clone(),
+                    environment,
+                )
+                .ignore_conflicts = ignore_conflicts;
+
+            Ok(identifier)
+        };
+        match res {
+            Ok(id) => success_names.push((name, id)),
+            Err(err) => errors.push((name, err)),
+        }
+    }
+
+    for identifier in files {
+        let name = match &identifier {
+            ModIdentifier::DirectUrl(url, _) => url_filename(url),
+            ModIdentifier::LocalPath(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            _ => unreachable!(),
+        };
+        let res = 'file_check: {
+            if profile.mods.iter().any(|mod_| {
+                mod_.name.eq_ignore_ascii_case(&name) || mod_.identifier.is_same_as(&identifier)
+            }) {
+                break 'file_check Err(Error::AlreadyAdded);
+            }
+            match &identifier {
+                ModIdentifier::DirectUrl(_, hashes) if hashes.is_empty() => {
+                    break 'file_check Err(Error::MissingHash);
+                }
+                ModIdentifier::LocalPath(path) if !path.is_file() => {
+                    break 'file_check Err(Error::DoesNotExist);
+                }
+                _ => (),
+            }
+
+            profile.push_mod(
+                name.clone(),
+                identifier.clone(),
+                Path::new(&name)
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                override_profile,
+                filters.clone(),
+                environment,
+            );
+            Ok(identifier)
+        };
+        match res {
+            Ok(id) => success_names.push((name, id)),
+            Err(err) => errors.push((name, err)),
+        }
+    }
+
     Ok((success_names, errors))
 }
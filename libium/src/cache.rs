@@ -0,0 +1,165 @@
+use crate::{upgrade::Hashes, PROJECT_DIRS};
+use sha1::{Digest as _, Sha1};
+use sha2::Sha512;
+use std::{
+    fs::{copy, create_dir_all, hard_link, read_dir, remove_file, File},
+    io::{self, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+/// The hash algorithms files in the store can be addressed by, in order of preference
+pub const ALGORITHMS: [&str; 2] = ["sha512", "sha1"];
+
+/// The directory of the store of downloaded files, which is shared across profiles and modpacks
+///
+/// Files are stored under their SHA-512 hash, or their SHA-1 hash if the platform doesn't provide a SHA-512 hash.
+/// Files without any known hashes are not stored.
+pub fn store_dir() -> PathBuf {
+    PROJECT_DIRS.cache_dir().join("store")
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// One of [`ALGORITHMS`]
+    pub algorithm: &'static str,
+    pub hash: String,
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+impl Entry {
+    /// Calculate the hash of the file and check whether it matches the hash it is stored under
+    pub fn verify(&self) -> Result<bool> {
+        let mut file = File::open(&self.path)?;
+        let actual = if self.algorithm == "sha1" {
+            let mut hasher = Sha1::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        } else {
+            let mut hasher = Sha512::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        };
+        Ok(actual == self.hash)
+    }
+
+    /// Whether this entry is the file with `hashes`
+    pub fn matches(&self, hashes: &Hashes) -> bool {
+        if self.algorithm == "sha1" {
+            &hashes.sha1
+        } else {
+            &hashes.sha512
+        }
+        .as_ref()
+        .is_some_and(|hash| hash.eq_ignore_ascii_case(&self.hash))
+    }
+
+    pub fn remove(self) -> Result<()> {
+        remove_file(self.path)
+    }
+}
+
+/// The algorithm and lowercase hash a file with `hashes` is stored under
+fn key(hashes: &Hashes) -> Option<(&'static str, String)> {
+    hashes
+        .sha512
+        .as_ref()
+        .map(|hash| ("sha512", hash.to_lowercase()))
+        .or_else(|| {
+            hashes
+                .sha1
+                .as_ref()
+                .map(|hash| ("sha1", hash.to_lowercase()))
+        })
+}
+
+/// The path a file with `hashes` is stored at, returns `None` if there are no hashes to address it by
+fn path_of(hashes: &Hashes) -> Option<PathBuf> {
+    key(hashes).map(|(algorithm, hash)| {
+        store_dir()
+            .join(algorithm)
+            .join(hash.get(..2).unwrap_or_default())
+            .join(hash)
+    })
+}
+
+/// Get the path of the stored file with `hashes`, if it is in the store
+pub fn get(hashes: &Hashes) -> Option<PathBuf> {
+    path_of(hashes).filter(|path| path.is_file())
+}
+
+/// Get the path of the stored file with `hashes`, if it is in the store and still has those hashes
+///
+/// Stored files are hardlinked to the files installed from them, so they change if an installed file is edited.
+/// A stored file that doesn't match its hashes anymore is removed from the store.
+pub fn get_verified(hashes: &Hashes) -> Result<Option<PathBuf>> {
+    let Some(path) = get(hashes) else {
+        return Ok(None);
+    };
+    if hashes.verify_file(&path).is_ok() {
+        Ok(Some(path))
+    } else {
+        remove_file(path)?;
+        Ok(None)
+    }
+}
+
+/// Add the file at `path`, which has `hashes`, to the store
+///
+/// Nothing is done if the file is already stored or if there are no hashes to address it by.
+pub fn insert(hashes: &Hashes, path: impl AsRef<Path>) -> Result<()> {
+    if let Some(stored) = path_of(hashes) {
+        if !stored.exists() {
+            if let Some(parent) = stored.parent() {
+                create_dir_all(parent)?;
+            }
+            link_or_copy(path, stored)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hardlink the file at `from` to `to`, copying it if a hardlink cannot be made (e.g. across filesystems)
+///
+/// If there is already a file at `to`, it is replaced.
+pub fn link_or_copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+    match remove_file(to.as_ref()) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+        _ => (),
+    }
+    if hard_link(from.as_ref(), to.as_ref()).is_err() {
+        copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// List all the files in the store
+pub fn entries() -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for algorithm in ALGORITHMS {
+        let algorithm_dir = store_dir().join(algorithm);
+        if !algorithm_dir.is_dir() {
+            continue;
+        }
+        for prefix in read_dir(algorithm_dir)? {
+            let prefix = prefix?;
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for file in read_dir(prefix.path())? {
+                let file = file?;
+                let metadata = file.metadata()?;
+                if metadata.is_file() {
+                    entries.push(Entry {
+                        path: file.path(),
+                        algorithm,
+                        hash: file.file_name().to_string_lossy().into_owned(),
+                        size: metadata.len(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
@@ -3,7 +3,7 @@ use crate::iter_ext::IterExt as _;
 use derive_more::derive::Display;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug, Display, Clone)]
+#[derive(Deserialize, Serialize, Debug, Display, Clone, PartialEq, Eq)]
 pub enum Filter {
     /// Prefers files in the order of the given loaders
     ///
@@ -1,7 +1,15 @@
 use super::filters::Filter;
+use crate::{iter_ext::IterExt as _, upgrade::Hashes};
+use clap::ValueEnum as _;
 use derive_more::derive::Display;
+use ferinth::structures::project::ProjectSupportRange;
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    mem::take,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use url::Url;
 
 #[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct Config {
@@ -53,6 +61,17 @@ pub struct Profile {
 
     pub mods: Vec<Mod>,
 
+    /// The environment the profile's mods are installed for,
+    /// mods that are not used in this environment are skipped
+    #[serde(skip_serializing_if = "is_both")]
+    #[serde(default)]
+    pub environment: Environment,
+
+    /// What to do with the optional dependencies of the profile's mods
+    #[serde(skip_serializing_if = "OptionalDependencies::is_ignore")]
+    #[serde(default)]
+    pub optional_dependencies: OptionalDependencies,
+
     // Kept for backwards compatibility reasons (i.e. migrating from a v4 config)
     #[serde(skip_serializing)]
     game_version: Option<String>,
@@ -79,6 +98,8 @@ impl Profile {
                 Filter::GameVersionStrict(game_versions),
             ],
             mods: vec![],
+            environment: Environment::Both,
+            optional_dependencies: OptionalDependencies::Ignore,
             game_version: None,
             mod_loader: None,
         }
@@ -104,6 +125,46 @@ impl Profile {
         }
     }
 
+    /// Remove the dependencies that were added implicitly and are no longer needed by the profile's other mods
+    ///
+    /// Returns the removed dependencies.
+    pub fn remove_unused_dependencies(&mut self) -> Vec<Mod> {
+        let mut needed = self
+            .mods
+            .iter()
+            .filter(|mod_| !mod_.is_dependency())
+            .map(|mod_| mod_.identifier.clone())
+            .collect_vec();
+        loop {
+            let newly_needed = self
+                .mods
+                .iter()
+                .filter(|mod_| {
+                    mod_.is_dependency()
+                        && !needed.contains(&mod_.identifier)
+                        && mod_
+                            .dependency_of
+                            .iter()
+                            .any(|parent| needed.iter().any(|id| id.is_same_as(parent)))
+                })
+                .map(|mod_| mod_.identifier.clone())
+                .collect_vec();
+            if newly_needed.is_empty() {
+                break;
+            }
+            needed.extend(newly_needed);
+        }
+
+        let (kept, removed) = take(&mut self.mods)
+            .into_iter()
+            .partition(|mod_| needed.contains(&mod_.identifier));
+        self.mods = kept;
+        removed
+    }
+
+    /// Add a mod to the profile, returns the added mod
+    ///
+    /// A dependency of the same project that was added implicitly is replaced.
     pub fn push_mod(
         &mut self,
         name: String,
@@ -111,16 +172,25 @@ impl Profile {
         slug: String,
         override_filters: bool,
         filters: Vec<Filter>,
-    ) {
+        environment: Option<Environment>,
+    ) -> &mut Mod {
+        self.mods
+            .retain(|mod_| !(mod_.is_dependency() && mod_.identifier.is_same_as(&identifier)));
         self.mods.push(Mod {
             name,
             slug: Some(slug),
             identifier,
+            project_type: ProjectType::Mod,
             filters,
             override_filters,
+            environment,
+            optional_dependencies: None,
+            ignore_conflicts: false,
+            dependency_of: Vec::new(),
             check_game_version: None,
             check_mod_loader: None,
-        })
+        });
+        self.mods.last_mut().unwrap()
     }
 }
 
@@ -134,6 +204,11 @@ pub struct Mod {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub slug: Option<String>,
 
+    /// The kind of project this is, which determines where it is installed
+    #[serde(skip_serializing_if = "ProjectType::is_mod")]
+    #[serde(default)]
+    pub project_type: ProjectType,
+
     /// Custom filters that apply only for this mod
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
@@ -144,6 +219,30 @@ pub struct Mod {
     #[serde(default)]
     pub override_filters: bool,
 
+    /// The environment this mod is used in,
+    /// which overrides the environment reported by the platform
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub environment: Option<Environment>,
+
+    /// What to do with this mod's optional dependencies, which overrides the profile's setting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub optional_dependencies: Option<OptionalDependencies>,
+
+    /// Whether to ignore incompatibilities declared by or against this mod, for known false positives
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub ignore_conflicts: bool,
+
+    /// The mods that need this mod as a dependency
+    ///
+    /// This is empty for mods that were added explicitly.
+    /// Dependencies are added implicitly when they are resolved, and removed when no mods need them anymore.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub dependency_of: Vec<ModIdentifier>,
+
     // Kept for backwards compatibility reasons
     #[serde(skip_serializing)]
     check_game_version: Option<bool>,
@@ -162,12 +261,122 @@ impl Mod {
             name,
             slug: None,
             identifier,
+            project_type: ProjectType::Mod,
             filters,
             override_filters,
+            environment: None,
+            optional_dependencies: None,
+            ignore_conflicts: false,
+            dependency_of: Vec::new(),
             check_game_version: None,
             check_mod_loader: None,
         }
     }
+
+    /// Whether this mod was added implicitly as a dependency of other mods
+    pub fn is_dependency(&self) -> bool {
+        !self.dependency_of.is_empty()
+    }
+}
+
+/// The side of the game something is used on
+#[derive(
+    Deserialize, Serialize, Debug, Display, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum,
+)]
+pub enum Environment {
+    Client,
+    Server,
+    #[default]
+    Both,
+}
+
+impl Environment {
+    /// Get the environment of a Modrinth project (or modpack file) from the support of its sides
+    pub fn from_sides(client: ProjectSupportRange, server: ProjectSupportRange) -> Self {
+        match (client, server) {
+            (_, ProjectSupportRange::Unsupported) => Self::Client,
+            (ProjectSupportRange::Unsupported, _) => Self::Server,
+            _ => Self::Both,
+        }
+    }
+
+    /// Whether something used in this environment should be installed in a `target` environment
+    pub fn supports(self, target: Self) -> bool {
+        self == Self::Both || target == Self::Both || self == target
+    }
+}
+
+/// What to do with optional dependencies, such as integrations with other mods
+#[derive(
+    Deserialize, Serialize, Debug, Display, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum,
+)]
+pub enum OptionalDependencies {
+    /// Don't do anything with them
+    #[default]
+    Ignore,
+    /// List the ones that aren't in the profile after upgrading
+    Suggest,
+    /// Install them like required dependencies
+    Install,
+}
+
+impl OptionalDependencies {
+    pub const fn is_ignore(&self) -> bool {
+        matches!(self, Self::Ignore)
+    }
+}
+
+/// The kind of project, which determines the directory it is installed to
+#[derive(
+    Deserialize, Serialize, Debug, Display, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum,
+)]
+pub enum ProjectType {
+    #[default]
+    Mod,
+    #[display("Resource Pack")]
+    ResourcePack,
+    #[display("Shader Pack")]
+    ShaderPack,
+    #[display("Datapack")]
+    Datapack,
+}
+
+impl ProjectType {
+    pub const fn is_mod(&self) -> bool {
+        matches!(self, Self::Mod)
+    }
+
+    /// The name of the instance directory that projects of this type are installed to
+    pub fn directory_name(self) -> &'static str {
+        match self {
+            Self::Mod => "mods",
+            Self::ResourcePack => "resourcepacks",
+            Self::ShaderPack => "shaderpacks",
+            Self::Datapack => "datapacks",
+        }
+    }
+
+    /// The project type that is installed to the instance directory `name`
+    pub fn from_directory_name(name: &str) -> Option<Self> {
+        Self::value_variants()
+            .iter()
+            .copied()
+            .find(|project_type| project_type.directory_name() == name)
+    }
+
+    /// The directory projects of this type are installed to, for a profile that outputs mods to `output_dir`
+    ///
+    /// Projects other than mods are installed to their directory next to `output_dir`, i.e. in the instance directory.
+    pub fn install_dir(self, output_dir: &Path) -> PathBuf {
+        match (self, output_dir.parent()) {
+            (Self::Mod, _) | (_, None) => output_dir.to_owned(),
+            (_, Some(instance_dir)) => instance_dir.join(self.directory_name()),
+        }
+    }
+}
+
+const fn is_both(environment: &Environment) -> bool {
+    matches!(environment, Environment::Both)
 }
 
 const fn is_false(b: &bool) -> bool {
@@ -179,18 +388,97 @@ pub enum ConfigModIdentifier {
     CurseForgeProject(i32),
     ModrinthProject(String),
     GitHubRepository(String, String),
+    MavenArtifact(Url, String, String, Option<String>),
+    ForgeRepository(Forge, Url, String),
 
     PinnedCurseForgeProject(i32, i32),
     PinnedModrinthProject(String, String),
     PinnedGitHubRepository((String, String), String),
+    PinnedMavenArtifact((Url, String, String, Option<String>), String),
+    PinnedForgeRepository((Forge, Url, String), String),
+
+    DirectUrl(Url, Hashes),
+    LocalPath(PathBuf),
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(from = "ConfigModIdentifier", into = "ConfigModIdentifier")]
 pub enum ModIdentifier {
     CurseForgeProject(i32, Option<i32>),
     ModrinthProject(String, Option<String>),
     GitHubRepository((String, String), Option<String>),
+    /// An artifact in a Maven repository, which can be pinned to a version
+    MavenArtifact(MavenArtifact, Option<String>),
+    /// A repository on a GitLab, Gitea, or Forgejo instance, which can be pinned to an asset of one of its releases
+    ForgeRepository(ForgeRepository, Option<String>),
+    /// A file downloaded from a URL, which has to match the hashes
+    DirectUrl(Url, Hashes),
+    /// A file on this computer, which is copied when it changes
+    LocalPath(PathBuf),
+}
+
+/// An artifact in a Maven repository
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MavenArtifact {
+    /// The URL of the repository, e.g. `https://maven.fabricmc.net`
+    pub repository: Url,
+    pub group: String,
+    /// The artifact ID
+    pub artifact: String,
+    /// The classifier of the jars to download, for artifacts that publish several jars for each version
+    pub classifier: Option<String>,
+}
+
+// The config stores Maven artifacts as tuples
+impl From<(Url, String, String, Option<String>)> for MavenArtifact {
+    fn from(
+        (repository, group, artifact, classifier): (Url, String, String, Option<String>),
+    ) -> Self {
+        Self {
+            repository,
+            group,
+            artifact,
+            classifier,
+        }
+    }
+}
+
+impl From<MavenArtifact> for (Url, String, String, Option<String>) {
+    fn from(artifact: MavenArtifact) -> Self {
+        (
+            artifact.repository,
+            artifact.group,
+            artifact.artifact,
+            artifact.classifier,
+        )
+    }
+}
+
+/// A repository on a GitLab, Gitea, or Forgejo instance
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForgeRepository {
+    pub forge: Forge,
+    /// The URL of the instance, e.g. `https://codeberg.org/`
+    pub instance: Url,
+    /// The path of the repository on the instance, e.g. `owner/repo`
+    pub path: String,
+}
+
+// The config stores forge repositories as tuples
+impl From<(Forge, Url, String)> for ForgeRepository {
+    fn from((forge, instance, path): (Forge, Url, String)) -> Self {
+        Self {
+            forge,
+            instance,
+            path,
+        }
+    }
+}
+
+impl From<ForgeRepository> for (Forge, Url, String) {
+    fn from(repository: ForgeRepository) -> Self {
+        (repository.forge, repository.instance, repository.path)
+    }
 }
 
 impl From<ConfigModIdentifier> for ModIdentifier {
@@ -201,6 +489,12 @@ impl From<ConfigModIdentifier> for ModIdentifier {
             ConfigModIdentifier::GitHubRepository(o, r) => {
                 ModIdentifier::GitHubRepository((o, r), None)
             }
+            ConfigModIdentifier::MavenArtifact(u, g, a, c) => {
+                ModIdentifier::MavenArtifact((u, g, a, c).into(), None)
+            }
+            ConfigModIdentifier::ForgeRepository(f, u, r) => {
+                ModIdentifier::ForgeRepository((f, u, r).into(), None)
+            }
             ConfigModIdentifier::PinnedCurseForgeProject(p, v) => {
                 ModIdentifier::CurseForgeProject(p, Some(v))
             }
@@ -210,6 +504,14 @@ impl From<ConfigModIdentifier> for ModIdentifier {
             ConfigModIdentifier::PinnedGitHubRepository(p, v) => {
                 ModIdentifier::GitHubRepository(p, Some(v))
             }
+            ConfigModIdentifier::PinnedMavenArtifact(p, v) => {
+                ModIdentifier::MavenArtifact(p.into(), Some(v))
+            }
+            ConfigModIdentifier::PinnedForgeRepository(p, v) => {
+                ModIdentifier::ForgeRepository(p.into(), Some(v))
+            }
+            ConfigModIdentifier::DirectUrl(u, h) => ModIdentifier::DirectUrl(u, h),
+            ConfigModIdentifier::LocalPath(p) => ModIdentifier::LocalPath(p),
         }
     }
 }
@@ -222,6 +524,23 @@ impl From<ModIdentifier> for ConfigModIdentifier {
             ModIdentifier::GitHubRepository((o, r), None) => {
                 ConfigModIdentifier::GitHubRepository(o, r)
             }
+            ModIdentifier::MavenArtifact(
+                MavenArtifact {
+                    repository,
+                    group,
+                    artifact,
+                    classifier,
+                },
+                None,
+            ) => ConfigModIdentifier::MavenArtifact(repository, group, artifact, classifier),
+            ModIdentifier::ForgeRepository(
+                ForgeRepository {
+                    forge,
+                    instance,
+                    path,
+                },
+                None,
+            ) => ConfigModIdentifier::ForgeRepository(forge, instance, path),
             ModIdentifier::CurseForgeProject(p, Some(v)) => {
                 ConfigModIdentifier::PinnedCurseForgeProject(p, v)
             }
@@ -231,11 +550,31 @@ impl From<ModIdentifier> for ConfigModIdentifier {
             ModIdentifier::GitHubRepository(p, Some(v)) => {
                 ConfigModIdentifier::PinnedGitHubRepository(p, v)
             }
+            ModIdentifier::MavenArtifact(p, Some(v)) => {
+                ConfigModIdentifier::PinnedMavenArtifact(p.into(), v)
+            }
+            ModIdentifier::ForgeRepository(p, Some(v)) => {
+                ConfigModIdentifier::PinnedForgeRepository(p.into(), v)
+            }
+            ModIdentifier::DirectUrl(u, h) => ConfigModIdentifier::DirectUrl(u, h),
+            ModIdentifier::LocalPath(p) => ConfigModIdentifier::LocalPath(p),
         }
     }
 }
 
 impl ModIdentifier {
+    /// Get the identifier of the project without any pin
+    pub fn unpinned(&self) -> Self {
+        match self {
+            Self::CurseForgeProject(id, _) => Self::CurseForgeProject(*id, None),
+            Self::ModrinthProject(id, _) => Self::ModrinthProject(id.clone(), None),
+            Self::GitHubRepository(id, _) => Self::GitHubRepository(id.clone(), None),
+            Self::MavenArtifact(id, _) => Self::MavenArtifact(id.clone(), None),
+            Self::ForgeRepository(id, _) => Self::ForgeRepository(id.clone(), None),
+            Self::DirectUrl(..) | Self::LocalPath(_) => self.clone(),
+        }
+    }
+
     /// Checks if `self` and `other` refer to the same project,
     /// ignoring any differences in pinning.
     pub fn is_same_as(&self, other: &Self) -> bool {
@@ -243,11 +582,36 @@ impl ModIdentifier {
             (Self::CurseForgeProject(l0, _), Self::CurseForgeProject(r0, _)) => l0 == r0,
             (Self::ModrinthProject(l0, _), Self::ModrinthProject(r0, _)) => l0 == r0,
             (Self::GitHubRepository(l0, _), Self::GitHubRepository(r0, _)) => l0 == r0,
+            (Self::MavenArtifact(l0, _), Self::MavenArtifact(r0, _)) => l0 == r0,
+            (Self::ForgeRepository(l0, _), Self::ForgeRepository(r0, _)) => l0 == r0,
+            (Self::DirectUrl(l0, _), Self::DirectUrl(r0, _)) => l0 == r0,
+            (Self::LocalPath(l0), Self::LocalPath(r0)) => l0 == r0,
             _ => false,
         }
     }
 }
 
+/// A software forge other than GitHub that hosts repositories with releases
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Forge {
+    #[display("GitLab")]
+    GitLab,
+    /// Gitea and Forgejo have the same API
+    Gitea,
+    Forgejo,
+}
+
+impl Forge {
+    /// The public instance used when a repository is given without one
+    pub fn default_instance(self) -> crate::forge::Result<Url> {
+        Ok(Url::parse(match self {
+            Self::GitLab => "https://gitlab.com/",
+            Self::Gitea => "https://gitea.com/",
+            Self::Forgejo => "https://codeberg.org/",
+        })?)
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum ModLoader {
     Quilt,
@@ -0,0 +1,212 @@
+//! Releases of repositories on GitLab, Gitea, and Forgejo instances, such as `gitlab.com` and `codeberg.org`
+
+use crate::{
+    config::structs::{Forge, ForgeRepository},
+    iter_ext::IterExt as _,
+};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub enum Error {
+    ReqwestError(#[from] reqwest::Error),
+    JSONError(#[from] serde_json::Error),
+    UrlParseError(#[from] url::ParseError),
+    #[error("{0} is not the HTTPS URL of a forge instance")]
+    InvalidInstance(Url),
+}
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A release of a repository, with the details that every forge provides
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub tag: String,
+    pub name: String,
+    pub description: String,
+    pub prerelease: bool,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Asset {
+    /// The ID that mods are pinned to
+    pub id: String,
+    pub name: String,
+    pub download_url: Url,
+    /// The length of the file in bytes, or 0 on GitLab which doesn't provide it
+    pub size: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitLabRelease {
+    tag_name: String,
+    name: Option<String>,
+    description: Option<String>,
+    assets: GitLabAssets,
+}
+#[derive(Deserialize, Debug)]
+struct GitLabAssets {
+    links: Vec<GitLabLink>,
+}
+#[derive(Deserialize, Debug)]
+struct GitLabLink {
+    id: u64,
+    name: String,
+    url: Url,
+    direct_asset_url: Option<Url>,
+}
+
+impl From<GitLabRelease> for Release {
+    fn from(release: GitLabRelease) -> Self {
+        Self {
+            name: release.name.unwrap_or_else(|| release.tag_name.clone()),
+            tag: release.tag_name,
+            description: release.description.unwrap_or_default(),
+            // GitLab doesn't mark releases as prereleases
+            prerelease: false,
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| Asset {
+                    id: link.id.to_string(),
+                    name: link.name,
+                    download_url: link.direct_asset_url.unwrap_or(link.url),
+                    size: 0,
+                })
+                .collect_vec(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct GiteaRelease {
+    tag_name: String,
+    name: String,
+    body: Option<String>,
+    prerelease: bool,
+    draft: bool,
+    assets: Vec<GiteaAsset>,
+}
+#[derive(Deserialize, Debug)]
+struct GiteaAsset {
+    id: u64,
+    name: String,
+    size: usize,
+    browser_download_url: Url,
+}
+
+impl From<GiteaRelease> for Release {
+    fn from(release: GiteaRelease) -> Self {
+        Self {
+            tag: release.tag_name,
+            name: release.name,
+            description: release.body.unwrap_or_default(),
+            prerelease: release.prerelease,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| Asset {
+                    id: asset.id.to_string(),
+                    name: asset.name,
+                    download_url: asset.browser_download_url,
+                    size: asset.size,
+                })
+                .collect_vec(),
+        }
+    }
+}
+
+/// The access token for the forge instance at `host`, if one is set in the `FORGE_TOKENS` environment variable
+///
+/// The variable holds comma separated `<host>=<token>` pairs, e.g. `gitlab.com=glpat-...,codeberg.org=...`.
+pub fn token(host: &str) -> Option<String> {
+    std::env::var("FORGE_TOKENS")
+        .ok()?
+        .split(',')
+        .find_map(|pair| {
+            let (token_host, token) = pair.split_once('=')?;
+            (token_host.trim() == host).then(|| token.trim().to_owned())
+        })
+}
+
+/// Add the access token for `url`'s host to `request` if one is set,
+/// which GitLab, Gitea, and Forgejo all accept as a bearer token
+pub fn authenticate(request: RequestBuilder, url: &Url) -> RequestBuilder {
+    match url.host_str().and_then(token) {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+/// The host and path of `repository`, e.g. `codeberg.org/owner/repo`
+pub fn display_name(repository: &ForgeRepository) -> String {
+    format!(
+        "{}/{}",
+        repository.instance.host_str().unwrap_or_default(),
+        repository.path
+    )
+}
+
+/// The URL of `segments` on `repository`'s instance
+///
+/// The instance's URL comes from the config, so it is checked here too.
+fn instance_url<'a>(
+    repository: &ForgeRepository,
+    segments: impl IntoIterator<Item = &'a str>,
+) -> Result<Url> {
+    let mut url = repository.instance.clone();
+    if url.scheme() != "https" {
+        return Err(Error::InvalidInstance(repository.instance.clone()));
+    }
+    url.path_segments_mut()
+        .map_err(|()| Error::InvalidInstance(repository.instance.clone()))?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+/// The web page of `repository`
+pub fn repository_url(repository: &ForgeRepository) -> Result<Url> {
+    instance_url(repository, repository.path.split('/'))
+}
+
+/// Get the releases of `repository`, latest first
+pub async fn releases(repository: &ForgeRepository) -> Result<Vec<Release>> {
+    let ForgeRepository { forge, path, .. } = repository;
+    let url = match forge {
+        // GitLab projects can be in nested groups, so the whole path is one segment
+        Forge::GitLab => instance_url(repository, ["api", "v4", "projects", path, "releases"])?,
+        Forge::Gitea | Forge::Forgejo => instance_url(
+            repository,
+            ["api", "v1", "repos"]
+                .into_iter()
+                .chain(path.split('/'))
+                .chain(["releases"]),
+        )?,
+    };
+    let page_size = match forge {
+        Forge::GitLab => ("per_page", "100"),
+        Forge::Gitea | Forge::Forgejo => ("limit", "50"),
+    };
+    let response = authenticate(Client::new().get(url.clone()), &url)
+        .query(&[page_size])
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(match forge {
+        Forge::GitLab => serde_json::from_str::<Vec<GitLabRelease>>(&response)?
+            .into_iter()
+            .map(Into::into)
+            .collect_vec(),
+        Forge::Gitea | Forge::Forgejo => serde_json::from_str::<Vec<GiteaRelease>>(&response)?
+            .into_iter()
+            .filter(|release| !release.draft)
+            .map(Into::into)
+            .collect_vec(),
+    })
+}
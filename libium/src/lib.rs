@@ -1,8 +1,14 @@
 pub mod add;
+pub mod cache;
 pub mod config;
+pub mod forge;
 pub mod iter_ext;
+pub mod lockfile;
+pub mod maven;
 pub mod modpack;
 pub mod scan;
+pub mod search;
+pub mod snapshot;
 pub mod upgrade;
 pub mod version_ext;
 
@@ -20,11 +26,15 @@ pub static GITHUB_API: LazyLock<octocrab::Octocrab> = LazyLock::new(|| {
     github.build().expect("Could not build GitHub client")
 });
 
-pub static CURSEFORGE_API: LazyLock<furse::Furse> = LazyLock::new(|| {
-    furse::Furse::new(std::env::var("CURSEFORGE_API_KEY").unwrap_or(String::from(
+/// The CurseForge API key set in the `CURSEFORGE_API_KEY` environment variable, or else ferium's API key
+fn curseforge_api_key() -> String {
+    std::env::var("CURSEFORGE_API_KEY").unwrap_or(String::from(
         "$2a$10$sI.yRk4h4R49XYF94IIijOrO4i3W3dAFZ4ssOlNE10GYrDhc2j8K.",
-    )))
-});
+    ))
+}
+
+pub static CURSEFORGE_API: LazyLock<furse::Furse> =
+    LazyLock::new(|| furse::Furse::new(curseforge_api_key()));
 
 pub static MODRINTH_API: LazyLock<ferinth::Ferinth<()>> = LazyLock::new(|| {
     ferinth::Ferinth::<()>::new(
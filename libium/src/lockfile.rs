@@ -0,0 +1,334 @@
+use crate::{
+    config::{
+        filters::Filter,
+        structs::{Environment, Mod, ModIdentifier, Profile, ProjectType},
+    },
+    iter_ext::IterExt as _,
+    upgrade::{DownloadData, Hashes},
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufReader, ErrorKind, Result},
+    mem::take,
+    path::Path,
+};
+
+/// The name of the lockfile, which is stored in the profile's output directory
+pub const LOCKFILE_NAME: &str = "ferium-lock.json";
+
+/// The exact files that were resolved for a profile
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct Lockfile {
+    /// The environment of the profile when the files were resolved
+    #[serde(default)]
+    pub environment: Environment,
+    /// The filters of the profile when the files were resolved
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub mods: Vec<LockedMod>,
+    /// The mods in the profile that are not used in the environment,
+    /// which are skipped instead of being resolved again
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub unused: Vec<ModIdentifier>,
+    /// The files that were locked before the lockfile was rewritten,
+    /// which are still installed until the next upgrade
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub replaced: Vec<LockedMod>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LockedMod {
+    /// The identifier of the mod, pinned to the resolved file
+    pub identifier: ModIdentifier,
+    #[serde(skip_serializing_if = "ProjectType::is_mod")]
+    #[serde(default)]
+    pub project_type: ProjectType,
+    pub filename: String,
+    pub download_url: Url,
+    /// The length of the file in bytes
+    pub length: usize,
+    #[serde(skip_serializing_if = "Hashes::is_empty")]
+    #[serde(default)]
+    pub hashes: Hashes,
+    /// The filters of the mod when the file was resolved
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub override_filters: bool,
+    /// The optional dependencies of the locked file
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub optional_dependencies: Vec<ModIdentifier>,
+    /// The mods the locked file has declared that it is incompatible with
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub conflicts: Vec<ModIdentifier>,
+    /// The mods that need this mod as a dependency
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub dependency_of: Vec<ModIdentifier>,
+}
+
+impl LockedMod {
+    /// Lock the file of `project` in `download_data`, returns `None` if the file is not from a mod platform
+    pub fn new(
+        download_data: DownloadData,
+        project: &Mod,
+        dependency_of: Vec<ModIdentifier>,
+    ) -> Option<Self> {
+        Some(Self {
+            filename: download_data.filename(),
+            identifier: download_data.identifier?,
+            project_type: project.project_type,
+            download_url: download_data.download_url,
+            length: download_data.length,
+            hashes: download_data.hashes,
+            filters: project.filters.clone(),
+            override_filters: project.override_filters,
+            optional_dependencies: download_data.optional_dependencies,
+            conflicts: download_data.conflicts,
+            dependency_of,
+        })
+    }
+
+    /// Whether this entry is the locked file of `mod_`, respecting any pin `mod_` has
+    pub fn locks(&self, mod_: &Mod) -> bool {
+        self.identifier.is_same_as(&mod_.identifier)
+            && match &mod_.identifier {
+                ModIdentifier::CurseForgeProject(_, Some(_))
+                | ModIdentifier::ModrinthProject(_, Some(_))
+                | ModIdentifier::GitHubRepository(_, Some(_))
+                | ModIdentifier::MavenArtifact(_, Some(_))
+                | ModIdentifier::ForgeRepository(_, Some(_)) => self.identifier == mod_.identifier,
+                _ => true,
+            }
+    }
+
+    /// Whether the file was resolved with the current filters of `mod_`
+    pub fn resolved_with(&self, mod_: &Mod) -> bool {
+        self.filters == mod_.filters && self.override_filters == mod_.override_filters
+    }
+
+    /// Whether the locked file has declared that it is incompatible with `identifier`
+    ///
+    /// Incompatibilities with a specific version only apply if `identifier` is pinned to that version.
+    pub fn conflicts_with(&self, identifier: &ModIdentifier) -> bool {
+        self.conflicts.iter().any(|conflict| match conflict {
+            ModIdentifier::CurseForgeProject(_, Some(_))
+            | ModIdentifier::ModrinthProject(_, Some(_))
+            | ModIdentifier::GitHubRepository(_, Some(_))
+            | ModIdentifier::MavenArtifact(_, Some(_))
+            | ModIdentifier::ForgeRepository(_, Some(_)) => conflict == identifier,
+            _ => conflict.is_same_as(identifier),
+        })
+    }
+}
+
+impl From<LockedMod> for DownloadData {
+    fn from(locked: LockedMod) -> Self {
+        Self {
+            identifier: Some(locked.identifier),
+            download_url: locked.download_url,
+            output: locked.filename.into(),
+            length: locked.length,
+            hashes: locked.hashes,
+            channel: None,
+            dependencies: Vec::new(),
+            optional_dependencies: locked.optional_dependencies,
+            conflicts: locked.conflicts,
+        }
+    }
+}
+
+impl Lockfile {
+    /// Remove entries that are no longer needed by the mods in `profile`
+    ///
+    /// Returns the mods in `profile` that do not have a locked file and are not unused,
+    /// except the dependencies that were added implicitly.
+    /// If the profile's environment or filters have changed, every entry is removed.
+    pub fn retain_for(&mut self, profile: &Profile) -> Vec<Mod> {
+        if self.environment != profile.environment || self.filters != profile.filters {
+            self.environment = profile.environment;
+            self.filters.clone_from(&profile.filters);
+            self.mods.clear();
+            self.unused.clear();
+        }
+
+        // Mods that are now set to be used in the environment have to be resolved
+        self.unused.retain(|identifier| {
+            profile.mods.iter().any(|mod_| {
+                &mod_.identifier == identifier
+                    && mod_
+                        .environment
+                        .is_none_or(|environment| !environment.supports(profile.environment))
+            })
+        });
+
+        // Local files that have changed since they were locked have to be copied again
+        self.mods.retain(|locked| match &locked.identifier {
+            ModIdentifier::LocalPath(path) => {
+                Hashes::of_file(path).is_ok_and(|hashes| hashes == locked.hashes)
+            }
+            _ => true,
+        });
+
+        // Files resolved with different filters than their mod in the profile has now,
+        // files of mods that are now set to not be used in the environment,
+        // and dependencies that don't match the pin of their mod in the profile, have to be resolved again,
+        // along with the mods that need them
+        let mut stale = self
+            .mods
+            .iter()
+            .filter(|locked| {
+                profile.mods.iter().any(|mod_| {
+                    locked.identifier.is_same_as(&mod_.identifier)
+                        && (!locked.resolved_with(mod_)
+                            || mod_.environment.is_some_and(|environment| {
+                                !environment.supports(profile.environment)
+                            })
+                            || (mod_.is_dependency() && !locked.locks(mod_)))
+                })
+            })
+            .map(|locked| locked.identifier.clone())
+            .collect_vec();
+        loop {
+            let dependents = self
+                .mods
+                .iter()
+                .filter(|locked| {
+                    !stale.contains(&locked.identifier)
+                        && self.mods.iter().any(|dependency| {
+                            stale.contains(&dependency.identifier)
+                                && dependency
+                                    .dependency_of
+                                    .iter()
+                                    .any(|parent| parent.is_same_as(&locked.identifier))
+                        })
+                })
+                .map(|locked| locked.identifier.clone())
+                .collect_vec();
+            if dependents.is_empty() {
+                break;
+            }
+            stale.extend(dependents);
+        }
+        self.mods
+            .retain(|locked| !stale.contains(&locked.identifier));
+
+        let mut unlocked = Vec::new();
+        let mut kept = Vec::new();
+        // Dependencies are kept below if the mods that need them are kept
+        for mod_ in profile.mods.iter().filter(|mod_| !mod_.is_dependency()) {
+            if let Some(locked) = self.mods.iter().find(|locked| locked.locks(mod_)) {
+                kept.push(locked.identifier.clone());
+            } else if !self.unused.contains(&mod_.identifier) {
+                unlocked.push(mod_.clone());
+            }
+        }
+
+        // Keep dependencies of kept mods, and then the dependencies of those
+        let mut dependencies = self
+            .mods
+            .iter()
+            .filter(|locked| !kept.contains(&locked.identifier))
+            .cloned()
+            .collect_vec();
+        loop {
+            let (needed, rest) = dependencies.into_iter().partition::<Vec<_>, _>(|locked| {
+                locked
+                    .dependency_of
+                    .iter()
+                    .any(|parent| kept.iter().any(|id| id.is_same_as(parent)))
+            });
+            dependencies = rest;
+            if needed.is_empty() {
+                break;
+            }
+            kept.extend(needed.into_iter().map(|locked| locked.identifier));
+        }
+
+        self.mods.retain(|locked| kept.contains(&locked.identifier));
+        unlocked
+    }
+
+    /// Get the pairs of locked files where the first has declared that it is incompatible with the second
+    ///
+    /// Files of mods in `profile` that ignore conflicts are skipped.
+    pub fn conflicts<'a>(&'a self, profile: &Profile) -> Vec<(&'a LockedMod, &'a LockedMod)> {
+        let checked = self
+            .mods
+            .iter()
+            .filter(|locked| {
+                !profile
+                    .mods
+                    .iter()
+                    .any(|mod_| mod_.ignore_conflicts && locked.locks(mod_))
+            })
+            .collect_vec();
+        let mut conflicts = Vec::new();
+        for locked in &checked {
+            for other in &checked {
+                if locked.conflicts_with(&other.identifier) {
+                    conflicts.push((*locked, *other));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Whether the file called `filename` in the directory of `project_type` is locked or was [replaced](Self::replaced)
+    pub fn owns(&self, project_type: ProjectType, filename: &str) -> bool {
+        self.mods
+            .iter()
+            .chain(&self.replaced)
+            .any(|locked| locked.project_type == project_type && locked.filename == filename)
+    }
+
+    /// Add `locked` to the lockfile
+    ///
+    /// If the project is already locked, its file is replaced and the dependents are merged.
+    pub fn insert(&mut self, mut locked: LockedMod) {
+        if let Some(existing) = self
+            .mods
+            .iter_mut()
+            .find(|existing| existing.identifier.is_same_as(&locked.identifier))
+        {
+            for parent in take(&mut existing.dependency_of) {
+                if !locked.dependency_of.contains(&parent) {
+                    locked.dependency_of.push(parent);
+                }
+            }
+            *existing = locked;
+        } else {
+            self.mods.push(locked);
+        }
+    }
+}
+
+const fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Read the lockfile in `directory`, returns `None` if it does not exist
+pub fn read_lockfile(directory: impl AsRef<Path>) -> Result<Option<Lockfile>> {
+    match File::open(directory.as_ref().join(LOCKFILE_NAME)) {
+        Ok(file) => Ok(Some(serde_json::from_reader(BufReader::new(file))?)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Serialise `lockfile` and write it to the lockfile in `directory`
+pub fn write_lockfile(directory: impl AsRef<Path>, lockfile: &Lockfile) -> Result<()> {
+    create_dir_all(directory.as_ref())?;
+    let lockfile_file = File::create(directory.as_ref().join(LOCKFILE_NAME))?;
+    serde_json::to_writer_pretty(lockfile_file, lockfile)?;
+    Ok(())
+}
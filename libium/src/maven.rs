@@ -0,0 +1,146 @@
+//! Artifacts published to Maven repositories, such as `maven.fabricmc.net`
+
+use crate::{config::structs::MavenArtifact, iter_ext::IterExt as _, upgrade::Hashes};
+use reqwest::{Client, StatusCode};
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub enum Error {
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0:?} is not a SHA-1 hash")]
+    InvalidChecksum(String),
+    #[error("{0} is not the HTTPS URL of a Maven repository")]
+    InvalidRepository(Url),
+    #[error("The maven-metadata.xml is invalid, {0}")]
+    InvalidMetadata(#[from] roxmltree::Error),
+}
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The URL of `path` in the directory of `artifact`
+///
+/// The repository's URL comes from the config, so it is checked here too.
+pub fn artifact_url(artifact: &MavenArtifact, path: &[&str]) -> Result<Url> {
+    let mut url = artifact.repository.clone();
+    if url.scheme() != "https" {
+        return Err(Error::InvalidRepository(artifact.repository.clone()));
+    }
+    url.path_segments_mut()
+        .map_err(|()| Error::InvalidRepository(artifact.repository.clone()))?
+        .pop_if_empty()
+        .extend(artifact.group.split('.'))
+        .push(&artifact.artifact)
+        .extend(path);
+    Ok(url)
+}
+
+/// The group and artifact ID of `artifact`, followed by its classifier if it has one, e.g. `net.fabricmc:fabric-loader`
+pub fn display_name(artifact: &MavenArtifact) -> String {
+    let MavenArtifact {
+        group,
+        artifact,
+        classifier,
+        ..
+    } = artifact;
+    match classifier {
+        Some(classifier) => format!("{group}:{artifact} ({classifier})"),
+        None => format!("{group}:{artifact}"),
+    }
+}
+
+/// The name of the jar of `version` of `artifact`
+pub fn filename(artifact: &MavenArtifact, version: &str) -> String {
+    match &artifact.classifier {
+        Some(classifier) => format!("{}-{version}-{classifier}.jar", artifact.artifact),
+        None => format!("{}-{version}.jar", artifact.artifact),
+    }
+}
+
+/// Get the versions of `artifact` listed in its `maven-metadata.xml`, latest first
+///
+/// An artifact that isn't in the repository has no versions.
+pub async fn versions(artifact: &MavenArtifact) -> Result<Vec<String>> {
+    let response = Client::new()
+        .get(artifact_url(artifact, &["maven-metadata.xml"])?)
+        .send()
+        .await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    parse_versions(&response.error_for_status()?.text().await?)
+}
+
+/// The versions in the `<versions>` list of a `maven-metadata.xml`, which is oldest first
+fn parse_versions(metadata: &str) -> Result<Vec<String>> {
+    let metadata = roxmltree::Document::parse(metadata)?;
+    let mut versions = metadata
+        .descendants()
+        .filter(|node| node.has_tag_name("versions"))
+        .flat_map(|versions| versions.children())
+        .filter(|node| node.has_tag_name("version"))
+        .map(|version| {
+            // Includes the text in CDATA sections, and skips comments
+            version
+                .descendants()
+                .filter_map(|node| node.is_text().then(|| node.text()).flatten())
+                .collect::<String>()
+                .trim()
+                .to_owned()
+        })
+        .filter(|version| !version.is_empty())
+        .collect_vec();
+    versions.reverse();
+    Ok(versions)
+}
+
+/// Get the SHA-1 hash of the file at `url` from the `.sha1` file next to it
+pub async fn checksum(url: &Url) -> Result<Hashes> {
+    let mut checksum_url = url.clone();
+    checksum_url.set_path(&format!("{}.sha1", url.path()));
+    let checksum = Client::new()
+        .get(checksum_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    // Some repositories put the filename after the hash
+    let checksum = checksum.split_whitespace().next().unwrap_or_default();
+    Hashes::from_hex(checksum)
+        .filter(|hashes| hashes.sha1.is_some())
+        .ok_or_else(|| Error::InvalidChecksum(checksum.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_versions;
+
+    #[test]
+    fn versions_with_whitespace_comments_and_cdata() {
+        let metadata = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>net.fabricmc</groupId>
+  <artifactId>fabric-loader</artifactId>
+  <versioning>
+    <latest>0.16.10</latest>
+    <versions>
+      <!-- <version>0.1.0</version> -->
+      <version>
+        0.15.11
+      </version>
+      <version><![CDATA[0.16.9]]></version>
+      <version>0.16.10<!-- latest --></version>
+    </versions>
+  </versioning>
+</metadata>"#;
+        assert_eq!(
+            parse_versions(metadata).unwrap(),
+            ["0.16.10", "0.16.9", "0.15.11"]
+        );
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        assert!(parse_versions("<metadata><versions>").is_err());
+    }
+}
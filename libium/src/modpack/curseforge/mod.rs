@@ -1 +1,53 @@
 pub mod structs;
+
+use std::{
+    fs::{read_dir, File},
+    io::{copy, Write},
+    path::{Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+use zip_extensions::zip_writer_extensions::ZipWriterExtensions as _;
+
+/// Create a CurseForge modpack at `output` using the provided `manifest` and optional `overrides`
+///
+/// The contents of `overrides` are added to the root of the modpack,
+/// and the files in `additional_mods` are added to the `mods` folder of the manifest's overrides.
+pub fn create(
+    output: &Path,
+    manifest: &structs::Manifest,
+    overrides: Option<&Path>,
+    additional_mods: Option<&Path>,
+) -> zip::result::ZipResult<()> {
+    let mut writer = ZipWriter::new(File::create(output)?);
+    let options = SimpleFileOptions::default();
+
+    // Add the manifest to the zip file
+    writer.start_file("manifest.json", options)?;
+    writer.write_all(
+        serde_json::to_string_pretty(manifest)
+            .map_err(std::io::Error::from)?
+            .as_bytes(),
+    )?;
+
+    let mods_dir = PathBuf::from(&manifest.overrides).join("mods");
+    writer.add_directory(mods_dir.to_string_lossy(), options)?;
+
+    // Add additional (non-CurseForge) mods to the zip file
+    if let Some(path) = additional_mods {
+        for entry in read_dir(path)?
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|e| e.is_file()))
+        {
+            writer.start_file(mods_dir.join(entry.file_name()).to_string_lossy(), options)?;
+            copy(&mut File::open(entry.path())?, &mut writer)?;
+        }
+    }
+
+    // Add the overrides to the zip file
+    if let Some(overrides) = overrides {
+        writer.create_from_directory(&overrides.to_owned())?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
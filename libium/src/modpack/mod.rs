@@ -1,11 +1,18 @@
 pub mod add;
 pub mod curseforge;
 pub mod modrinth;
+pub mod packwiz;
 
 pub use zip_extensions::zip_extract::zip_extract;
 
-use crate::read_wrapper;
-use std::io::{Read, Seek};
+use crate::{config::structs::ModLoader, read_wrapper};
+use reqwest::Client;
+use serde_json::Value;
+use std::{
+    fs::{create_dir_all, File},
+    io::{copy, Read, Seek},
+    path::Path,
+};
 use zip::{result::ZipResult, ZipArchive};
 
 /// Returns the contents of the `file_name` from the provided `input` zip file if it exists
@@ -19,3 +26,93 @@ pub fn read_file_from_zip(input: impl Read + Seek, file_name: &str) -> ZipResult
     };
     ret
 }
+
+/// Extract the `overrides` folder of the modpack zip file `input`
+///
+/// Files in the overrides' `mods` folder are extracted to `mods_dir`, and all other files are extracted to `others_dir`.
+pub fn extract_overrides(
+    input: impl Read + Seek,
+    overrides: &str,
+    mods_dir: &Path,
+    others_dir: &Path,
+) -> ZipResult<()> {
+    let mut zip_file = ZipArchive::new(input)?;
+    for i in 0..zip_file.len() {
+        let mut entry = zip_file.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let Ok(path) = path.strip_prefix(overrides) else {
+            continue;
+        };
+        let output = match path.strip_prefix("mods") {
+            Ok(path) => mods_dir.join(path),
+            Err(_) => others_dir.join(path),
+        };
+        if entry.is_dir() {
+            create_dir_all(output)?;
+        } else {
+            if let Some(parent) = output.parent() {
+                create_dir_all(parent)?;
+            }
+            copy(&mut entry, &mut File::create(output)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the latest version of `loader` that supports `game_version`, using the loader's metadata API
+///
+/// Returns `None` if the loader has no versions for `game_version`.
+pub async fn latest_loader_version(
+    loader: ModLoader,
+    game_version: &str,
+) -> reqwest::Result<Option<String>> {
+    let client = Client::new();
+    Ok(match loader {
+        ModLoader::Fabric | ModLoader::Quilt => {
+            let url = if loader == ModLoader::Fabric {
+                format!("https://meta.fabricmc.net/v2/versions/loader/{game_version}")
+            } else {
+                format!("https://meta.quiltmc.org/v3/versions/loader/{game_version}")
+            };
+            let versions: Value = client.get(url).send().await?.json().await?;
+            versions[0]["loader"]["version"]
+                .as_str()
+                .map(ToOwned::to_owned)
+        }
+        ModLoader::Forge => {
+            let promotions: Value = client
+                .get("https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json")
+                .send()
+                .await?
+                .json()
+                .await?;
+            promotions["promos"][format!("{game_version}-recommended")]
+                .as_str()
+                .or(promotions["promos"][format!("{game_version}-latest")].as_str())
+                .map(ToOwned::to_owned)
+        }
+        ModLoader::NeoForge => {
+            // NeoForge versions drop the leading `1.` of the game version, e.g. `21.1.x` is for `1.21.1`
+            let mut prefix = game_version.trim_start_matches("1.").to_owned();
+            if !prefix.contains('.') {
+                prefix.push_str(".0");
+            }
+            prefix.push('.');
+            let versions: Value = client
+                .get("https://maven.neoforged.net/api/maven/versions/releases/net/neoforged/neoforge")
+                .send()
+                .await?
+                .json()
+                .await?;
+            versions["versions"].as_array().and_then(|versions| {
+                versions
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .rfind(|version| version.starts_with(&prefix))
+                    .map(ToOwned::to_owned)
+            })
+        }
+    })
+}
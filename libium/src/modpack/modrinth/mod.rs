@@ -33,7 +33,7 @@ pub fn create(
             writer.start_file(
                 PathBuf::from("overrides")
                     .join("mods")
-                    .with_file_name(entry.file_name().unwrap())
+                    .join(entry.file_name().unwrap())
                     .to_string_lossy(),
                 options,
             )?;
@@ -1,3 +1,4 @@
+use crate::config::structs::{Environment, ModLoader};
 use ferinth::structures::{project::ProjectSupportRange, version::Hash, Int};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
@@ -32,6 +33,30 @@ pub enum DependencyID {
     QuiltLoader,
 }
 
+impl From<ModLoader> for DependencyID {
+    fn from(loader: ModLoader) -> Self {
+        match loader {
+            ModLoader::Quilt => Self::QuiltLoader,
+            ModLoader::Fabric => Self::FabricLoader,
+            ModLoader::Forge => Self::Forge,
+            ModLoader::NeoForge => Self::Neoforge,
+        }
+    }
+}
+
+impl DependencyID {
+    /// The mod loader this dependency is, if it is a mod loader
+    pub fn mod_loader(&self) -> Option<ModLoader> {
+        match self {
+            Self::Minecraft => None,
+            Self::Forge => Some(ModLoader::Forge),
+            Self::Neoforge => Some(ModLoader::NeoForge),
+            Self::FabricLoader => Some(ModLoader::Fabric),
+            Self::QuiltLoader => Some(ModLoader::Quilt),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ModpackFile {
@@ -50,8 +75,30 @@ pub struct ModpackFile {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ModpackFileEnvironment {
-    client: ProjectSupportRange,
-    server: ProjectSupportRange,
+    pub client: ProjectSupportRange,
+    pub server: ProjectSupportRange,
+}
+
+impl From<Environment> for ModpackFileEnvironment {
+    fn from(environment: Environment) -> Self {
+        let side = |used| {
+            if used {
+                ProjectSupportRange::Required
+            } else {
+                ProjectSupportRange::Unsupported
+            }
+        };
+        Self {
+            client: side(environment != Environment::Server),
+            server: side(environment != Environment::Client),
+        }
+    }
+}
+
+impl From<ModpackFileEnvironment> for Environment {
+    fn from(env: ModpackFileEnvironment) -> Self {
+        Self::from_sides(env.client, env.server)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
@@ -0,0 +1,119 @@
+pub mod structs;
+
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs::{create_dir_all, read_dir, read_to_string, write, File},
+    io,
+    path::{Path, PathBuf},
+};
+use structs::{Index, IndexFile, MetaFile, Pack};
+
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub enum Error {
+    IOError(#[from] io::Error),
+    DeserialiseError(#[from] toml::de::Error),
+    SerialiseError(#[from] toml::ser::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The name of the file at the root of a packwiz pack
+pub const PACK_FILE: &str = "pack.toml";
+/// The name of the index file that is written
+pub const INDEX_FILE: &str = "index.toml";
+
+/// Calculate the SHA-256 hash of the file at `path`, which is the hash format used for packwiz indices
+pub fn sha256_file(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Read the pack at `pack_file` and its index
+///
+/// Returns the pack, the index, and the directory the paths in the index are relative to.
+pub fn read(pack_file: &Path) -> Result<(Pack, Index, PathBuf)> {
+    let pack: Pack = toml::from_str(&read_to_string(pack_file)?)?;
+    let index_path = pack_file
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(&pack.index.file);
+    let index = toml::from_str(&read_to_string(&index_path)?)?;
+    Ok((
+        pack,
+        index,
+        index_path.parent().unwrap_or(Path::new(".")).to_owned(),
+    ))
+}
+
+/// Read the metadata file at `path`
+pub fn read_metafile(path: &Path) -> Result<MetaFile> {
+    Ok(toml::from_str(&read_to_string(path)?)?)
+}
+
+/// Write a packwiz pack to `directory`
+///
+/// The `metafiles` are written at their paths relative to `directory`,
+/// and then every file in `directory` (except hidden files) is added to the index.
+/// The index of `pack` is replaced with the written index.
+pub fn write_pack(
+    directory: &Path,
+    mut pack: Pack,
+    metafiles: impl IntoIterator<Item = (PathBuf, MetaFile)>,
+) -> Result<()> {
+    for (path, metafile) in metafiles {
+        let path = directory.join(path);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(path, toml::to_string(&metafile)?)?;
+    }
+
+    let mut index = Index {
+        hash_format: "sha256".to_owned(),
+        files: Vec::new(),
+    };
+    for path in files_in(directory)? {
+        let relative = path
+            .strip_prefix(directory)
+            .unwrap_or(&path)
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative == PACK_FILE || relative == INDEX_FILE {
+            continue;
+        }
+        index.files.push(IndexFile {
+            metafile: relative.ends_with(".pw.toml"),
+            hash: sha256_file(&path)?,
+            file: relative,
+        });
+    }
+    index.files.sort_by(|a, b| a.file.cmp(&b.file));
+
+    let index_path = directory.join(INDEX_FILE);
+    write(&index_path, toml::to_string(&index)?)?;
+    pack.index.file = INDEX_FILE.to_owned();
+    pack.index.hash_format = "sha256".to_owned();
+    pack.index.hash = sha256_file(&index_path)?;
+    write(directory.join(PACK_FILE), toml::to_string(&pack)?)?;
+    Ok(())
+}
+
+/// Recursively list the files in `directory`, ignoring hidden files such as `.git`
+fn files_in(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in read_dir(directory)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            files.extend(files_in(&entry.path())?);
+        } else {
+            files.push(entry.path());
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,130 @@
+use crate::config::structs::Environment;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// The format version of packwiz packs that are written
+pub const PACK_FORMAT: &str = "packwiz:1.1.0";
+
+/// The `pack.toml` file at the root of a packwiz pack
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Pack {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack_format: Option<String>,
+    pub index: IndexReference,
+    /// The versions of Minecraft and the mod loader, keyed by `minecraft`, `fabric`, `quilt`, `forge`, or `neoforge`
+    pub versions: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexReference {
+    /// The path to the index file, relative to `pack.toml`
+    pub file: String,
+    pub hash_format: String,
+    pub hash: String,
+}
+
+/// The index file listing all the files in the pack
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Index {
+    pub hash_format: String,
+    #[serde(default)]
+    pub files: Vec<IndexFile>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexFile {
+    /// The path to the file, relative to the index file, separated by forward slashes
+    pub file: String,
+    pub hash: String,
+    /// Whether this file is a metadata file (`.pw.toml`) that describes a file to download
+    #[serde(default)]
+    pub metafile: bool,
+}
+
+/// A metadata (`.pw.toml`) file describing a file to download
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetaFile {
+    pub name: String,
+    pub filename: String,
+    #[serde(default)]
+    pub side: Side,
+    pub download: Download,
+    #[serde(default)]
+    pub update: Update,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Download {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    pub hash_format: String,
+    pub hash: String,
+    /// How the file should be downloaded, e.g. `metadata:curseforge` if the URL has to be obtained from CurseForge
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Update {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<ModrinthUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<CurseForgeUpdate>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModrinthUpdate {
+    pub mod_id: String,
+    /// The ID of the version
+    pub version: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct CurseForgeUpdate {
+    pub project_id: i32,
+    pub file_id: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Client,
+    Server,
+    #[default]
+    Both,
+}
+
+impl From<Side> for Environment {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Client => Self::Client,
+            Side::Server => Self::Server,
+            Side::Both => Self::Both,
+        }
+    }
+}
+
+impl From<Environment> for Side {
+    fn from(environment: Environment) -> Self {
+        match environment {
+            Environment::Client => Self::Client,
+            Environment::Server => Self::Server,
+            Environment::Both => Self::Both,
+        }
+    }
+}
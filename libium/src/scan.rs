@@ -1,8 +1,10 @@
 use crate::{CURSEFORGE_API, MODRINTH_API};
+use ferinth::structures::version::Version as MRVersion;
+use furse::structures::file_structs::File as CFFile;
 use futures_util::{try_join, TryFutureExt};
 use sha1::{Digest, Sha1};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{read, read_dir},
     path::Path,
 };
@@ -16,72 +18,112 @@ pub enum Error {
 }
 type Result<T> = std::result::Result<T, Error>;
 
-/// Scans `dir_path` and return the filename, Modrinth project ID, and CurseForge mod ID for each JAR file
+/// A file with the hashes it can be looked up by on Modrinth and CurseForge
+#[derive(Debug, Clone)]
+pub struct HashedFile {
+    pub filename: String,
+    /// The SHA-1 hash, which Modrinth versions can be looked up by
+    pub sha1: String,
+    /// The fingerprint CurseForge files can be looked up by
+    pub fingerprint: usize,
+}
+
+/// A file with the Modrinth version and CurseForge file it was found to be
+#[derive(Debug, Clone)]
+pub struct Identified {
+    pub file: HashedFile,
+    pub modrinth: Option<MRVersion>,
+    pub curseforge: Option<CFFile>,
+}
+
+/// Hash the files in `dir_path` that have one of the `extensions`
 ///
-/// Calls `hashing_complete` after reading and hashing files is done.
-pub async fn scan(
-    dir_path: impl AsRef<Path>,
-    hashing_complete: impl Fn(),
-) -> Result<Vec<(String, Option<String>, Option<i32>)>> {
-    let mut filenames = HashMap::new();
-    let mut mr_hashes = vec![];
-    let mut cf_hashes = vec![];
+/// Files with the same contents as a file that was already hashed are skipped.
+pub fn hash_files(dir_path: impl AsRef<Path>, extensions: &[&str]) -> Result<Vec<HashedFile>> {
+    let mut files = Vec::new();
+    let mut fingerprints = HashSet::new();
 
     for entry in read_dir(dir_path)? {
         let path = entry?.path();
         if path.is_file()
-            && path
-                .extension()
-                .is_some_and(|ext| ext.eq_ignore_ascii_case("jar"))
+            && path.extension().is_some_and(|ext| {
+                extensions
+                    .iter()
+                    .any(|extension| ext.eq_ignore_ascii_case(extension))
+            })
         {
             let bytes = read(&path)?;
 
-            let mr_hash = format!("{:x}", Sha1::digest(&bytes));
-            let cf_hash = furse::cf_fingerprint(&bytes);
+            let sha1 = format!("{:x}", Sha1::digest(&bytes));
+            let fingerprint = furse::cf_fingerprint(&bytes);
 
             if let Some(filename) = path.file_name() {
-                // Only add the hashes if this file wasn't already hashed
-                if filenames.insert(cf_hash, filename.to_owned()).is_none() {
-                    mr_hashes.push(mr_hash);
-                    cf_hashes.push(cf_hash);
+                // Only add the file if this file wasn't already hashed
+                if fingerprints.insert(fingerprint) {
+                    files.push(HashedFile {
+                        filename: filename.to_string_lossy().into_owned(),
+                        sha1,
+                        fingerprint,
+                    });
                 }
             }
         }
     }
+    Ok(files)
+}
 
-    hashing_complete();
+/// Look up the `files` on Modrinth and CurseForge using their hashes
+pub async fn identify(files: Vec<HashedFile>) -> Result<Vec<Identified>> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let (mr_results, cf_results) = try_join!(
+    let (mut mr_results, cf_results) = try_join!(
         MODRINTH_API
-            .version_get_from_multiple_hashes(mr_hashes.clone())
+            .version_get_from_multiple_hashes(files.iter().map(|file| file.sha1.clone()).collect())
             .map_err(Error::from),
         CURSEFORGE_API
-            .get_fingerprint_matches(cf_hashes.clone())
+            .get_fingerprint_matches(files.iter().map(|file| file.fingerprint).collect())
             .map_err(Error::from),
     )?;
 
     // Elide explicit type parameters when https://github.com/rust-lang/rust/issues/90879 is resolved.
-    let mut mr_results =
-        HashMap::<_, _>::from_iter(mr_results.into_iter().map(|(k, v)| (k, v.project_id)));
     let mut cf_results = HashMap::<_, _>::from_iter(
         cf_results
             .exact_fingerprints
             .into_iter()
-            .zip(cf_results.exact_matches.into_iter().map(|m| m.id)),
+            .zip(cf_results.exact_matches.into_iter().map(|m| m.file)),
     );
 
-    Ok(mr_hashes
-        .iter()
-        .zip(&cf_hashes)
-        .map(|(mr, cf)| {
+    Ok(files
+        .into_iter()
+        .map(|file| Identified {
+            modrinth: mr_results.remove(&file.sha1),
+            curseforge: cf_results.remove(&(file.fingerprint as i64)),
+            file,
+        })
+        .collect())
+}
+
+/// Scans `dir_path` and return the filename, Modrinth project ID, and CurseForge mod ID for each JAR file
+///
+/// Calls `hashing_complete` after reading and hashing files is done.
+pub async fn scan(
+    dir_path: impl AsRef<Path>,
+    hashing_complete: impl Fn(),
+) -> Result<Vec<(String, Option<String>, Option<i32>)>> {
+    let files = hash_files(dir_path, &["jar"])?;
+
+    hashing_complete();
+
+    Ok(identify(files)
+        .await?
+        .into_iter()
+        .map(|identified| {
             (
-                filenames
-                    .remove(cf)
-                    .expect("Missing filename in hashmap")
-                    .to_string_lossy()
-                    .into_owned(),
-                mr_results.remove(mr),
-                cf_results.remove(&(*cf as i64)),
+                identified.file.filename,
+                identified.modrinth.map(|version| version.project_id),
+                identified.curseforge.map(|file| file.mod_id),
             )
         })
         .collect())
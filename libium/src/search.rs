@@ -0,0 +1,189 @@
+use crate::{
+    config::{
+        filters::Filter,
+        structs::{ModIdentifier, ModLoader},
+    },
+    curseforge_api_key,
+    upgrade::check::get_version_groups,
+    MODRINTH_API,
+};
+use ferinth::structures::{
+    project::ProjectType,
+    search::{Facet, Sort},
+};
+use furse::structures::mod_structs::Mod as CFMod;
+use reqwest::Client;
+use serde::Deserialize;
+
+#[derive(thiserror::Error, Debug)]
+#[error(transparent)]
+pub enum Error {
+    ModrinthError(#[from] ferinth::Error),
+    ReqwestError(#[from] reqwest::Error),
+    VersionGrouping(#[from] crate::upgrade::check::Error),
+    JSONError(#[from] serde_json::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The number of results that are requested from each platform
+pub const RESULT_LIMIT: usize = 20;
+
+const CURSEFORGE_SEARCH_URL: &str = "https://api.curseforge.com/v1/mods/search";
+/// The ID of Minecraft on CurseForge
+const CURSEFORGE_MINECRAFT: i32 = 432;
+/// The ID of the mods class on CurseForge
+const CURSEFORGE_MODS: i32 = 6;
+/// CurseForge's search accepts at most this many game versions
+const CURSEFORGE_MAX_GAME_VERSIONS: usize = 4;
+
+/// A project found by searching Modrinth or CurseForge
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub identifier: ModIdentifier,
+    pub name: String,
+    pub slug: String,
+    pub summary: String,
+    pub authors: Vec<String>,
+    pub downloads: usize,
+}
+
+#[derive(Deserialize)]
+struct CFSearchResponse {
+    data: Vec<CFMod>,
+}
+
+async fn curseforge_request(params: &[(&str, String)]) -> Result<Vec<CFMod>> {
+    let response: CFSearchResponse = Client::new()
+        .get(CURSEFORGE_SEARCH_URL)
+        .header("x-api-key", curseforge_api_key())
+        .query(params)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response.data)
+}
+
+/// The game versions and mod loaders the projects have to support to be compatible with `filters`
+async fn compatible(filters: &[Filter]) -> Result<(Vec<String>, Vec<ModLoader>)> {
+    let mut game_versions = Vec::new();
+    let mut mod_loaders = Vec::new();
+    for filter in filters {
+        match filter {
+            Filter::GameVersionStrict(versions) => game_versions.extend(versions.iter().cloned()),
+            Filter::GameVersionMinor(versions) => {
+                for group in get_version_groups().await? {
+                    if group.iter().any(|version| versions.contains(version)) {
+                        game_versions.extend(group.iter().cloned());
+                    }
+                }
+            }
+            Filter::ModLoaderPrefer(loaders) | Filter::ModLoaderAny(loaders) => {
+                mod_loaders.extend(loaders.iter().copied());
+            }
+            _ => (),
+        }
+    }
+    Ok((game_versions, mod_loaders))
+}
+
+/// Search Modrinth for mods matching `query`, that support the game versions and mod loaders of `filters`
+pub async fn modrinth(query: &str, filters: &[Filter]) -> Result<Vec<SearchResult>> {
+    let (game_versions, mod_loaders) = compatible(filters).await?;
+    // Facets in the same list are combined with OR, and the lists are combined with AND
+    let facets = vec![
+        vec![Facet::ProjectType(ProjectType::Mod)],
+        game_versions.into_iter().map(Facet::Versions).collect(),
+        mod_loaders
+            .iter()
+            .map(|loader| Facet::Categories(loader.to_string().to_lowercase()))
+            .collect(),
+    ];
+    Ok(MODRINTH_API
+        .search_paged(query, Sort::Relevance, RESULT_LIMIT, 0, facets)
+        .await?
+        .hits
+        .into_iter()
+        .map(|hit| SearchResult {
+            identifier: ModIdentifier::ModrinthProject(hit.project_id.clone(), None),
+            slug: hit.slug.unwrap_or(hit.project_id),
+            name: hit.title,
+            summary: hit.description,
+            authors: vec![hit.author],
+            downloads: hit.downloads,
+        })
+        .collect())
+}
+
+/// Search CurseForge for mods matching `query`, that support the game versions and mod loaders of `filters`
+///
+/// Only the latest few game versions are used, since CurseForge doesn't accept more.
+pub async fn curseforge(query: &str, filters: &[Filter]) -> Result<Vec<SearchResult>> {
+    let (game_versions, mod_loaders) = compatible(filters).await?;
+    let mut params = vec![
+        ("gameId", CURSEFORGE_MINECRAFT.to_string()),
+        ("classId", CURSEFORGE_MODS.to_string()),
+        ("searchFilter", query.to_owned()),
+        // Sort by popularity, which is the closest to relevance
+        ("sortField", "2".to_owned()),
+        ("sortOrder", "desc".to_owned()),
+        ("pageSize", RESULT_LIMIT.to_string()),
+    ];
+    if !game_versions.is_empty() {
+        params.push((
+            "gameVersions",
+            serde_json::to_string(
+                &game_versions
+                    .iter()
+                    .take(CURSEFORGE_MAX_GAME_VERSIONS)
+                    .collect::<Vec<_>>(),
+            )?,
+        ));
+    }
+    if !mod_loaders.is_empty() {
+        params.push((
+            "modLoaderTypes",
+            serde_json::to_string(
+                &mod_loaders
+                    .iter()
+                    .map(|loader| match loader {
+                        ModLoader::Forge => 1,
+                        ModLoader::Fabric => 4,
+                        ModLoader::Quilt => 5,
+                        ModLoader::NeoForge => 6,
+                    })
+                    .collect::<Vec<_>>(),
+            )?,
+        ));
+    }
+
+    Ok(curseforge_request(&params)
+        .await?
+        .into_iter()
+        .map(|project| SearchResult {
+            identifier: ModIdentifier::CurseForgeProject(project.id, None),
+            name: project.name,
+            slug: project.slug,
+            summary: project.summary.trim().to_owned(),
+            authors: project
+                .authors
+                .into_iter()
+                .map(|author| author.name)
+                .collect(),
+            downloads: project.download_count.try_into().unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Get the CurseForge projects with `slug`, which can be one of each kind of project
+pub async fn curseforge_slug(slug: &str) -> Result<Vec<CFMod>> {
+    Ok(curseforge_request(&[
+        ("gameId", CURSEFORGE_MINECRAFT.to_string()),
+        ("slug", slug.to_owned()),
+    ])
+    .await?
+    .into_iter()
+    .filter(|project| project.slug == slug)
+    .collect())
+}
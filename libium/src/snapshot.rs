@@ -0,0 +1,241 @@
+use crate::{
+    cache::link_or_copy,
+    config::structs::ProjectType,
+    lockfile::{read_lockfile, write_lockfile, Lockfile, LOCKFILE_NAME},
+    upgrade::Hashes,
+};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, read_dir, remove_dir_all, remove_file, File},
+    io::{BufReader, ErrorKind, Result},
+    mem::take as take_all,
+    path::{Path, PathBuf},
+};
+
+/// The name of the directory snapshots are stored in, which is in the profile's output directory
+pub const SNAPSHOTS_DIR: &str = ".snapshots";
+/// The number of snapshots that are kept by [`prune`]
+pub const MAX_SNAPSHOTS: usize = 10;
+const SNAPSHOT_FILE: &str = "snapshot.json";
+/// The start of the reason of snapshots taken before rolling back, which is followed by the ID of the snapshot restored
+pub const ROLLBACK_REASON: &str = "Before rolling back to ";
+
+/// The files that were in a profile's install directories, and the profile's lockfile, at some point in time
+///
+/// The files themselves are stored with the snapshot, hardlinked if possible.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Snapshot {
+    /// The name of the snapshot's directory, which is the local time it was taken at
+    #[serde(skip)]
+    pub id: String,
+    pub created: DateTime<Utc>,
+    /// Why the snapshot was taken, e.g. before upgrading
+    pub reason: String,
+    /// The profile's lockfile, if it had one, with only the files in this snapshot
+    pub lockfile: Option<Lockfile>,
+    pub directories: Vec<SnapshotDirectory>,
+}
+
+/// The files in the install directory of a project type
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SnapshotDirectory {
+    pub project_type: ProjectType,
+    pub files: Vec<String>,
+}
+
+impl Snapshot {
+    /// The number of files in this snapshot
+    pub fn len(&self) -> usize {
+        self.directories
+            .iter()
+            .map(|directory| directory.files.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The ID of the snapshot that was restored, if this snapshot was taken before rolling back
+    pub fn rolled_back_to(&self) -> Option<&str> {
+        self.reason.strip_prefix(ROLLBACK_REASON)
+    }
+}
+
+fn snapshots_dir(output_dir: &Path) -> PathBuf {
+    output_dir.join(SNAPSHOTS_DIR)
+}
+
+/// The files in `directory` that snapshots record, which excludes the lockfile and partial downloads
+fn recorded_files(directory: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    if directory.is_dir() {
+        for entry in read_dir(directory)? {
+            let entry = entry?;
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_file()
+                && filename != LOCKFILE_NAME
+                && !filename.ends_with(".part")
+            {
+                files.push((filename, entry.path()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Save the files in the install directories of `project_types`, and the lockfile, of the profile with `output_dir`
+pub fn take(
+    output_dir: &Path,
+    project_types: &[ProjectType],
+    reason: impl Into<String>,
+) -> Result<Snapshot> {
+    let now = Local::now();
+    let mut id = now.format("%Y-%m-%d_%H-%M-%S").to_string();
+    // Snapshots taken in the same second get a number
+    let mut number = 1;
+    while snapshots_dir(output_dir).join(&id).exists() {
+        number += 1;
+        id = format!("{}_{number}", now.format("%Y-%m-%d_%H-%M-%S"));
+    }
+    let snapshot_dir = snapshots_dir(output_dir).join(&id);
+
+    let mut directories = Vec::new();
+    for &project_type in project_types {
+        let files_dir = snapshot_dir.join(project_type.directory_name());
+        create_dir_all(&files_dir)?;
+        let mut files = Vec::new();
+        for (filename, path) in recorded_files(&project_type.install_dir(output_dir))? {
+            link_or_copy(path, files_dir.join(&filename))?;
+            files.push(filename);
+        }
+        directories.push(SnapshotDirectory {
+            project_type,
+            files,
+        });
+    }
+
+    let mut lockfile = read_lockfile(output_dir)?;
+    // Only keep the locked files that are in the snapshot, the lockfile may have been updated since they were installed,
+    // in which case the installed files are the replaced ones
+    if let Some(lockfile) = &mut lockfile {
+        let replaced = take_all(&mut lockfile.replaced);
+        lockfile.mods.extend(replaced);
+        lockfile.mods.retain(|locked| {
+            directories.iter().any(|directory| {
+                directory.project_type == locked.project_type
+                    && directory.files.contains(&locked.filename)
+            })
+        });
+    }
+
+    let snapshot = Snapshot {
+        id,
+        created: now.to_utc(),
+        reason: reason.into(),
+        lockfile,
+        directories,
+    };
+    serde_json::to_writer_pretty(File::create(snapshot_dir.join(SNAPSHOT_FILE))?, &snapshot)?;
+    Ok(snapshot)
+}
+
+/// List the snapshots of the profile with `output_dir`, oldest first
+pub fn list(output_dir: &Path) -> Result<Vec<Snapshot>> {
+    let mut snapshots = Vec::new();
+    match read_dir(snapshots_dir(output_dir)) {
+        Ok(entries) => {
+            for entry in entries {
+                let entry = entry?;
+                let file = match File::open(entry.path().join(SNAPSHOT_FILE)) {
+                    Ok(file) => file,
+                    // Skip snapshots that weren't completely taken
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err),
+                };
+                let mut snapshot: Snapshot = serde_json::from_reader(BufReader::new(file))?;
+                snapshot.id = entry.file_name().to_string_lossy().into_owned();
+                snapshots.push(snapshot);
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => return Err(err),
+    }
+    snapshots.sort_unstable_by_key(|snapshot| snapshot.created);
+    Ok(snapshots)
+}
+
+/// Delete all but the latest [`MAX_SNAPSHOTS`] snapshots of the profile with `output_dir`
+pub fn prune(output_dir: &Path) -> Result<()> {
+    let snapshots = list(output_dir)?;
+    for snapshot in snapshots
+        .iter()
+        .take(snapshots.len().saturating_sub(MAX_SNAPSHOTS))
+    {
+        remove_dir_all(snapshots_dir(output_dir).join(&snapshot.id))?;
+    }
+    Ok(())
+}
+
+/// Whether the file at `path` is one of the files of `project_type` locked in `lockfile`, going by its hashes
+fn known_by(lockfile: &Lockfile, project_type: ProjectType, path: &Path) -> bool {
+    let Ok(hashes) = Hashes::of_file(path) else {
+        return false;
+    };
+    let same = |a: &Option<String>, b: &Option<String>| {
+        a.as_ref()
+            .zip(b.as_ref())
+            .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b))
+    };
+    lockfile.mods.iter().any(|locked| {
+        locked.project_type == project_type
+            && (same(&locked.hashes.sha1, &hashes.sha1)
+                || same(&locked.hashes.sha512, &hashes.sha512))
+    })
+}
+
+/// Restore the install directories and the lockfile of the profile with `output_dir` to how they were in `snapshot`
+///
+/// Files that aren't in the snapshot are deleted if ferium installed them,
+/// i.e. if the current lockfile owns them or the snapshot's lockfile has their hashes.
+/// Other files, such as packs the user added, are kept.
+/// A snapshot of the current files should be [taken](take) first.
+pub fn restore(output_dir: &Path, snapshot: &Snapshot) -> Result<()> {
+    let snapshot_dir = snapshots_dir(output_dir).join(&snapshot.id);
+    let current = read_lockfile(output_dir)?;
+    for directory in &snapshot.directories {
+        let project_type = directory.project_type;
+        let install_dir = project_type.install_dir(output_dir);
+        create_dir_all(&install_dir)?;
+        for (filename, path) in recorded_files(&install_dir)? {
+            if !directory.files.contains(&filename)
+                && (current
+                    .as_ref()
+                    .is_some_and(|current| current.owns(project_type, &filename))
+                    || snapshot
+                        .lockfile
+                        .as_ref()
+                        .is_some_and(|lockfile| known_by(lockfile, project_type, &path)))
+            {
+                remove_file(path)?;
+            }
+        }
+        for filename in &directory.files {
+            link_or_copy(
+                snapshot_dir
+                    .join(directory.project_type.directory_name())
+                    .join(filename),
+                install_dir.join(filename),
+            )?;
+        }
+    }
+
+    match &snapshot.lockfile {
+        Some(lockfile) => write_lockfile(output_dir, lockfile),
+        None => match remove_file(output_dir.join(LOCKFILE_NAME)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        },
+    }
+}
@@ -122,19 +122,34 @@ impl Filter {
     }
 }
 
+/// Get the index of the most preferred file in `download_files` that passes the `filters`
+///
 /// Assumes that the provided `download_files` are sorted in the order of preference (e.g. chronological)
 pub async fn select_latest(
     download_files: impl Iterator<Item = &Metadata> + Clone,
     filters: Vec<Filter>,
 ) -> Result<usize> {
+    Ok(select_candidates(download_files, filters).await?[0])
+}
+
+/// Get the indices of the files in `download_files` that pass the `filters`, in the order of preference
+///
+/// Assumes that the provided `download_files` are sorted in the order of preference (e.g. chronological).
+/// Files are ordered by the mod loaders they are compatible with first, if the filters prefer some mod loaders.
+/// This fails if no files pass the filters, so the returned indices are never empty.
+pub async fn select_candidates(
+    download_files: impl Iterator<Item = &Metadata> + Clone,
+    filters: Vec<Filter>,
+) -> Result<Vec<usize>> {
     let mut filter_results = vec![];
     let mut run_last = vec![];
 
     for filter in &filters {
-        if let Filter::ModLoaderPrefer(_) = filter {
-            // ModLoaderPrefer has to be run last
+        if let Filter::ModLoaderPrefer(loaders) = filter {
+            // ModLoaderPrefer is used to order the files that pass the other filters
             run_last.push((
                 filter,
+                loaders,
                 filter.filter(download_files.clone().enumerate()).await?,
             ));
         } else {
@@ -147,7 +162,12 @@ pub async fn select_latest(
 
     let empty_filtrations = filter_results
         .iter()
-        .chain(run_last.iter())
+        .map(|(filter, indices)| (filter, indices))
+        .chain(
+            run_last
+                .iter()
+                .map(|(filter, _, indices)| (filter, indices)),
+        )
         .filter_map(|(filter, indices)| {
             if indices.is_empty() {
                 Some(filter.to_string())
@@ -166,40 +186,30 @@ pub async fn select_latest(
     // Intersect all the index_sets by folding the HashSet::intersection method
     // Ref: https://www.reddit.com/r/rust/comments/5v35l6/intersection_of_more_than_two_sets
     // Here we're getting the non-ModLoaderPrefer indices first
-    let final_indices = filter_results
-        .next()
-        .map(|set_1| {
-            filter_results.fold(set_1, |set_a, set_b| {
-                set_a.intersection(&set_b).copied().collect_hashset()
-            })
+    let final_indices = match filter_results.next() {
+        Some(set_1) => filter_results.fold(set_1, |set_a, set_b| {
+            set_a.intersection(&set_b).copied().collect_hashset()
+        }),
+        None => (0..download_files.clone().count()).collect_hashset(),
+    };
+
+    // Order the files by the position of the first preferred mod loader they are compatible with,
+    // leaving out the files that aren't compatible with any of them
+    let mut candidates = download_files
+        .enumerate()
+        .filter(|(i, _)| final_indices.contains(i))
+        .filter_map(|(i, file)| {
+            let rank = run_last
+                .iter()
+                .map(|(_, loaders, _)| loaders.iter().position(|l| file.loaders.contains(l)))
+                .collect::<Option<Vec<_>>>()?;
+            Some((rank, i))
         })
-        .unwrap_or_default();
-
-    let download_files = download_files.into_iter().enumerate().filter_map(|(i, f)| {
-        if final_indices.contains(&i) {
-            Some((i, f))
-        } else {
-            None
-        }
-    });
-
-    let mut filter_results = vec![];
-    for (filter, _) in run_last {
-        filter_results.push(filter.filter(download_files.clone()).await?)
+        .collect_vec();
+    if candidates.is_empty() {
+        return Err(Error::IntersectFailure);
     }
-    let mut filter_results = filter_results.into_iter();
-
-    let final_index = filter_results
-        .next()
-        .and_then(|set_1| {
-            filter_results
-                .fold(set_1, |set_a, set_b| {
-                    set_a.intersection(&set_b).copied().collect_hashset()
-                })
-                .into_iter()
-                .min()
-        })
-        .ok_or(Error::IntersectFailure)?;
+    candidates.sort_unstable();
 
-    Ok(final_index)
+    Ok(candidates.into_iter().map(|(_, i)| i).collect_vec())
 }
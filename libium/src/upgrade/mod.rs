@@ -1,12 +1,14 @@
 pub mod check;
 pub mod mod_downloadable;
 pub mod modpack_downloadable;
+pub mod resolve;
 
 use crate::{
     config::{
         filters::ReleaseChannel,
-        structs::{ModIdentifier, ModLoader},
+        structs::{ForgeRepository, MavenArtifact, ModIdentifier, ModLoader},
     },
+    forge::{self, Asset as ForgeAsset, Release as ForgeRelease},
     iter_ext::IterExt as _,
     modpack::modrinth::structs::ModpackFile as ModpackModFile,
     version_ext::VersionExt,
@@ -15,13 +17,22 @@ use ferinth::structures::version::{
     DependencyType as MRDependencyType, Version as MRVersion, VersionType,
 };
 use furse::structures::file_structs::{
-    File as CFFile, FileRelationType as CFFileRelationType, FileReleaseType,
+    File as CFFile, FileRelationType as CFFileRelationType, FileReleaseType, HashAlgo as CFHashAlgo,
 };
 use octocrab::models::repos::{Asset as GHAsset, Release as GHRelease};
-use reqwest::{Client, Url};
+use reqwest::{
+    header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    Client, Response, StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest as _, Sha1};
+use sha2::Sha512;
 use std::{
-    fs::{create_dir_all, rename, OpenOptions},
-    io::{BufWriter, Write},
+    fs::{
+        copy, create_dir_all, metadata, read_to_string, remove_file, rename, write, File,
+        OpenOptions,
+    },
+    io::{BufWriter, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -31,6 +42,15 @@ use std::{
 pub enum Error {
     ReqwestError(#[from] reqwest::Error),
     IOError(#[from] std::io::Error),
+    #[error("{} is not an absolute path", .0.display())]
+    RelativePath(PathBuf),
+    #[error("The {algorithm} hash of {filename} is {actual}, but {expected} was expected")]
+    HashMismatch {
+        filename: String,
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
 }
 type Result<T> = std::result::Result<T, Error>;
 
@@ -48,8 +68,159 @@ pub struct Metadata {
     pub loaders: Vec<ModLoader>,
 }
 
+/// Hashes of a file, as provided by the platform it is downloaded from
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Hashes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub sha1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub sha512: Option<String>,
+}
+
+impl Hashes {
+    pub fn is_empty(&self) -> bool {
+        self.sha1.is_none() && self.sha512.is_none()
+    }
+
+    /// Parse a SHA-1 or SHA-512 hash written in hexadecimal, which are told apart by their length
+    pub fn from_hex(hash: &str) -> Option<Self> {
+        if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let hash = hash.to_lowercase();
+        match hash.len() {
+            40 => Some(Self {
+                sha1: Some(hash),
+                sha512: None,
+            }),
+            128 => Some(Self {
+                sha1: None,
+                sha512: Some(hash),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Calculate the SHA-1 and SHA-512 hashes of the file at `path`
+    pub fn of_file(path: impl AsRef<Path>) -> Result<Self> {
+        let mut hasher = Hasher {
+            sha1: Some(Sha1::new()),
+            sha512: Some(Sha512::new()),
+        };
+        hasher.update_from(File::open(path)?)?;
+        Ok(Self {
+            sha1: hasher.sha1.map(|h| format!("{:x}", h.finalize())),
+            sha512: hasher.sha512.map(|h| format!("{:x}", h.finalize())),
+        })
+    }
+
+    /// Check that the file at `path` has these hashes, returns `Error::HashMismatch` if it doesn't
+    pub fn verify_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut hasher = Hasher::new(self);
+        hasher.update_from(File::open(path.as_ref())?)?;
+        hasher.verify(
+            self,
+            &path
+                .as_ref()
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+        )
+    }
+}
+
+/// Calculates the hashes of a file as it is being written, for the algorithms that have an expected hash
+struct Hasher {
+    sha1: Option<Sha1>,
+    sha512: Option<Sha512>,
+}
+
+impl Hasher {
+    fn new(expected: &Hashes) -> Self {
+        Self {
+            sha1: expected.sha1.as_ref().map(|_| Sha1::new()),
+            sha512: expected.sha512.as_ref().map(|_| Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        if let Some(sha1) = &mut self.sha1 {
+            sha1.update(data);
+        }
+        if let Some(sha512) = &mut self.sha512 {
+            sha512.update(data);
+        }
+    }
+
+    /// Add the contents of `reader`
+    fn update_from(&mut self, mut reader: impl Read) -> Result<()> {
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            match reader.read(&mut buf)? {
+                0 => return Ok(()),
+                n => self.update(&buf[..n]),
+            }
+        }
+    }
+
+    /// Compare the calculated hashes to the `expected` ones
+    fn verify(self, expected: &Hashes, filename: &str) -> Result<()> {
+        let calculated = [
+            (
+                "SHA-1",
+                self.sha1.map(|h| format!("{:x}", h.finalize())),
+                &expected.sha1,
+            ),
+            (
+                "SHA-512",
+                self.sha512.map(|h| format!("{:x}", h.finalize())),
+                &expected.sha512,
+            ),
+        ];
+        for (algorithm, actual, expected) in calculated {
+            if let (Some(actual), Some(expected)) = (actual, expected) {
+                if !actual.eq_ignore_ascii_case(expected) {
+                    return Err(Error::HashMismatch {
+                        filename: filename.to_owned(),
+                        algorithm,
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<VersionType> for ReleaseChannel {
+    fn from(version_type: VersionType) -> Self {
+        match version_type {
+            VersionType::Release => Self::Release,
+            VersionType::Beta => Self::Beta,
+            VersionType::Alpha => Self::Alpha,
+        }
+    }
+}
+
+impl From<FileReleaseType> for ReleaseChannel {
+    fn from(release_type: FileReleaseType) -> Self {
+        match release_type {
+            FileReleaseType::Release => Self::Release,
+            FileReleaseType::Beta => Self::Beta,
+            FileReleaseType::Alpha => Self::Alpha,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DownloadData {
+    /// The identifier of the project this file belongs to, pinned to this file
+    ///
+    /// This is `None` for files that don't come from a mod platform, such as modpack files.
+    pub identifier: Option<ModIdentifier>,
     pub download_url: Url,
     /// The path of the downloaded file relative to the output directory
     ///
@@ -57,8 +228,13 @@ pub struct DownloadData {
     pub output: PathBuf,
     /// The length of the file in bytes
     pub length: usize,
+    pub hashes: Hashes,
+    /// The release channel of the file, if the platform provides one
+    pub channel: Option<ReleaseChannel>,
     /// The dependencies this file has
     pub dependencies: Vec<ModIdentifier>,
+    /// The optional dependencies this file has, such as integrations with other mods
+    pub optional_dependencies: Vec<ModIdentifier>,
     /// Other mods this file is incompatible with
     pub conflicts: Vec<ModIdentifier>,
 }
@@ -71,16 +247,13 @@ pub struct DistributionDeniedError(pub i32, pub i32);
 pub fn try_from_cf_file(
     file: CFFile,
 ) -> std::result::Result<(Metadata, DownloadData), DistributionDeniedError> {
+    let channel = file.release_type.into();
     Ok((
         Metadata {
             title: file.display_name,
             description: String::new(), // Changelog requires a separate request
             filename: file.file_name.clone(),
-            channel: match file.release_type {
-                FileReleaseType::Release => ReleaseChannel::Release,
-                FileReleaseType::Beta => ReleaseChannel::Beta,
-                FileReleaseType::Alpha => ReleaseChannel::Alpha,
-            },
+            channel,
             loaders: file
                 .game_versions
                 .iter()
@@ -89,11 +262,21 @@ pub fn try_from_cf_file(
             game_versions: file.game_versions,
         },
         DownloadData {
+            identifier: Some(ModIdentifier::CurseForgeProject(file.mod_id, Some(file.id))),
             download_url: file
                 .download_url
                 .ok_or(DistributionDeniedError(file.mod_id, file.id))?,
             output: file.file_name.into(),
             length: file.file_length as usize,
+            hashes: Hashes {
+                sha1: file
+                    .hashes
+                    .iter()
+                    .find(|h| h.algo == CFHashAlgo::Sha1)
+                    .map(|h| h.value.clone()),
+                sha512: None,
+            },
+            channel: Some(channel),
             dependencies: file
                 .dependencies
                 .iter()
@@ -105,6 +288,17 @@ pub fn try_from_cf_file(
                     }
                 })
                 .collect_vec(),
+            optional_dependencies: file
+                .dependencies
+                .iter()
+                .filter_map(|d| {
+                    if d.relation_type == CFFileRelationType::OptionalDependency {
+                        Some(ModIdentifier::CurseForgeProject(d.mod_id, None))
+                    } else {
+                        None
+                    }
+                })
+                .collect_vec(),
             conflicts: file
                 .dependencies
                 .iter()
@@ -120,17 +314,33 @@ pub fn try_from_cf_file(
     ))
 }
 
+/// Get the projects that `version` has a dependency of `dependency_type` on
+fn mr_dependencies(version: &MRVersion, dependency_type: MRDependencyType) -> Vec<ModIdentifier> {
+    version
+        .dependencies
+        .iter()
+        .filter(|d| d.dependency_type == dependency_type)
+        .filter_map(|d| match (&d.project_id, &d.version_id) {
+            (Some(proj_id), ver_id) => Some(ModIdentifier::ModrinthProject(
+                proj_id.clone(),
+                ver_id.clone(),
+            )),
+            _ => {
+                eprintln!("Project ID not available");
+                None
+            }
+        })
+        .collect_vec()
+}
+
 pub fn from_mr_version(version: MRVersion) -> (Metadata, DownloadData) {
+    let channel = version.version_type.into();
     (
         Metadata {
             title: version.name.clone(),
             description: version.changelog.as_ref().cloned().unwrap_or_default(),
             filename: version.get_version_file().filename.clone(),
-            channel: match version.version_type {
-                VersionType::Release => ReleaseChannel::Release,
-                VersionType::Beta => ReleaseChannel::Beta,
-                VersionType::Alpha => ReleaseChannel::Alpha,
-            },
+            channel,
             loaders: version
                 .loaders
                 .iter()
@@ -140,60 +350,28 @@ pub fn from_mr_version(version: MRVersion) -> (Metadata, DownloadData) {
             game_versions: version.game_versions.clone(),
         },
         DownloadData {
+            identifier: Some(ModIdentifier::ModrinthProject(
+                version.project_id.clone(),
+                Some(version.id.clone()),
+            )),
             download_url: version.get_version_file().url.clone(),
             output: version.get_version_file().filename.as_str().into(),
             length: version.get_version_file().size,
-            dependencies: version
-                .dependencies
-                .clone()
-                .into_iter()
-                .filter_map(|d| {
-                    if d.dependency_type == MRDependencyType::Required {
-                        match (d.project_id, d.version_id) {
-                            (Some(proj_id), Some(ver_id)) => {
-                                Some(ModIdentifier::ModrinthProject(proj_id, Some(ver_id)))
-                            }
-                            (Some(proj_id), None) => {
-                                Some(ModIdentifier::ModrinthProject(proj_id, None))
-                            }
-                            _ => {
-                                eprintln!("Project ID not available");
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect_vec(),
-            conflicts: version
-                .dependencies
-                .into_iter()
-                .filter_map(|d| {
-                    if d.dependency_type == MRDependencyType::Incompatible {
-                        match (d.project_id, d.version_id) {
-                            (Some(proj_id), Some(ver_id)) => {
-                                Some(ModIdentifier::ModrinthProject(proj_id, Some(ver_id)))
-                            }
-                            (Some(proj_id), None) => {
-                                Some(ModIdentifier::ModrinthProject(proj_id, None))
-                            }
-                            _ => {
-                                eprintln!("Project ID not available");
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect_vec(),
+            hashes: Hashes {
+                sha1: Some(version.get_version_file().hashes.sha1.clone()),
+                sha512: Some(version.get_version_file().hashes.sha512.clone()),
+            },
+            channel: Some(channel),
+            dependencies: mr_dependencies(&version, MRDependencyType::Required),
+            optional_dependencies: mr_dependencies(&version, MRDependencyType::Optional),
+            conflicts: mr_dependencies(&version, MRDependencyType::Incompatible),
         },
     )
 }
 
 pub fn from_modpack_file(file: ModpackModFile) -> DownloadData {
     DownloadData {
+        identifier: None,
         download_url: file
             .downloads
             .first()
@@ -201,47 +379,202 @@ pub fn from_modpack_file(file: ModpackModFile) -> DownloadData {
             .clone(),
         output: file.path,
         length: file.file_size,
+        hashes: Hashes {
+            sha1: Some(file.hashes.sha1),
+            sha512: Some(file.hashes.sha512),
+        },
+        channel: None,
         dependencies: Vec::new(),
+        optional_dependencies: Vec::new(),
         conflicts: Vec::new(),
     }
 }
 
+/// The metadata of a file that isn't from a mod platform, which doesn't say what it's compatible with
+fn file_metadata(filename: String) -> Metadata {
+    Metadata {
+        title: filename.clone(),
+        description: String::new(),
+        filename,
+        channel: ReleaseChannel::Release,
+        game_versions: Vec::new(),
+        loaders: Vec::new(),
+    }
+}
+
+/// The last segment of `url`'s path, or the whole URL if it doesn't have one
+pub fn url_filename(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or(url.as_str())
+        .to_owned()
+}
+
+/// The length of the file at `url` as reported by the server, or 0 if it doesn't provide it
+pub async fn content_length(url: &Url) -> reqwest::Result<usize> {
+    let response = Client::new().head(url.clone()).send().await?;
+    Ok(if response.status().is_success() {
+        response.content_length().unwrap_or_default() as usize
+    } else {
+        0
+    })
+}
+
+/// Get the file at `url`, which has to match `hashes`
+///
+/// The length of the file is taken from the server if it provides it.
+pub async fn from_direct_url(url: &Url, hashes: &Hashes) -> Result<(Metadata, DownloadData)> {
+    let length = content_length(url).await?;
+    let filename = url_filename(url);
+    Ok((
+        file_metadata(filename.clone()),
+        DownloadData {
+            identifier: Some(ModIdentifier::DirectUrl(url.clone(), hashes.clone())),
+            download_url: url.clone(),
+            output: filename.into(),
+            length,
+            hashes: hashes.clone(),
+            channel: None,
+            dependencies: Vec::new(),
+            optional_dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        },
+    ))
+}
+
+/// Get the file at the absolute `path`, with its current hashes so that it's copied again when it changes
+pub fn from_local_path(path: &Path) -> Result<(Metadata, DownloadData)> {
+    let download_url =
+        Url::from_file_path(path).map_err(|()| Error::RelativePath(path.to_owned()))?;
+    let filename = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    Ok((
+        file_metadata(filename.clone()),
+        DownloadData {
+            identifier: Some(ModIdentifier::LocalPath(path.to_owned())),
+            download_url,
+            output: filename.into(),
+            length: metadata(path)?.len() as usize,
+            hashes: Hashes::of_file(path)?,
+            channel: None,
+            dependencies: Vec::new(),
+            optional_dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        },
+    ))
+}
+
+/// Get the jar of `version` of the Maven `artifact`
+///
+/// Its hashes and length aren't listed with the versions, so they're left empty to be fetched separately.
+/// Returns an error if the artifact's repository doesn't have an HTTPS URL.
+pub fn from_maven_version(
+    artifact: &MavenArtifact,
+    version: String,
+) -> crate::maven::Result<(Metadata, DownloadData)> {
+    let filename = crate::maven::filename(artifact, &version);
+    let (game_versions, loaders) = guess_compatibility(&filename);
+    let channel = version
+        .to_lowercase()
+        .split(['-', '.', '+', '_'])
+        .find_map(|part| {
+            if part.starts_with("alpha") || part.starts_with("snapshot") {
+                Some(ReleaseChannel::Alpha)
+            } else if ["beta", "pre", "rc"].iter().any(|p| part.starts_with(p)) {
+                Some(ReleaseChannel::Beta)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(ReleaseChannel::Release);
+    let download_url = crate::maven::artifact_url(artifact, &[&version, &filename])?;
+    Ok((
+        Metadata {
+            title: match &artifact.classifier {
+                Some(classifier) => format!("{version} ({classifier})"),
+                None => version.clone(),
+            },
+            description: String::new(),
+            filename: filename.clone(),
+            channel,
+            game_versions,
+            loaders,
+        },
+        DownloadData {
+            download_url,
+            identifier: Some(ModIdentifier::MavenArtifact(
+                artifact.clone(),
+                Some(version),
+            )),
+            output: filename.into(),
+            length: 0,
+            hashes: Hashes::default(),
+            channel: Some(channel),
+            dependencies: Vec::new(),
+            optional_dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        },
+    ))
+}
+
+/// Guess the game versions and mod loaders that a file is compatible with from its `filename`,
+/// for platforms that don't say what their files are compatible with
+pub fn guess_compatibility(filename: &str) -> (Vec<String>, Vec<ModLoader>) {
+    let parts = filename
+        .trim_end_matches(".jar")
+        .split(['-', '_', '+'])
+        .collect_vec();
+    (
+        parts
+            .iter()
+            .map(|s| s.trim_start_matches("mc").to_owned())
+            .collect_vec(),
+        parts
+            .iter()
+            .filter_map(|s| ModLoader::from_str(s).ok())
+            .collect_vec(),
+    )
+}
+
 pub fn from_gh_releases(
+    repo: &(String, String),
     releases: impl IntoIterator<Item = GHRelease>,
 ) -> Vec<(Metadata, DownloadData)> {
     releases
         .into_iter()
         .flat_map(|release| {
+            let channel = if release.prerelease {
+                ReleaseChannel::Beta
+            } else {
+                ReleaseChannel::Release
+            };
             release.assets.into_iter().map(move |asset| {
+                let (game_versions, loaders) = guess_compatibility(&asset.name);
                 (
                     Metadata {
                         title: release.name.clone().unwrap_or_default(),
                         description: release.body.clone().unwrap_or_default(),
-                        channel: if release.prerelease {
-                            ReleaseChannel::Beta
-                        } else {
-                            ReleaseChannel::Release
-                        },
-                        game_versions: asset
-                            .name
-                            .trim_end_matches(".jar")
-                            .split(['-', '_', '+'])
-                            .map(|s| s.trim_start_matches("mc"))
-                            .map(ToOwned::to_owned)
-                            .collect_vec(),
-                        loaders: asset
-                            .name
-                            .trim_end_matches(".jar")
-                            .split(['-', '_', '+'])
-                            .filter_map(|s| ModLoader::from_str(s).ok())
-                            .collect_vec(),
+                        channel,
+                        game_versions,
+                        loaders,
                         filename: asset.name.clone(),
                     },
                     DownloadData {
+                        identifier: Some(ModIdentifier::GitHubRepository(
+                            repo.clone(),
+                            Some(asset.node_id),
+                        )),
                         download_url: asset.browser_download_url,
                         output: asset.name.into(),
                         length: asset.size as usize,
+                        hashes: Hashes::default(),
+                        channel: Some(channel),
                         dependencies: Vec::new(),
+                        optional_dependencies: Vec::new(),
                         conflicts: Vec::new(),
                     },
                 )
@@ -250,20 +583,120 @@ pub fn from_gh_releases(
         .collect_vec()
 }
 
-pub fn from_gh_asset(asset: GHAsset) -> DownloadData {
+pub fn from_forge_releases(
+    repository: &ForgeRepository,
+    releases: impl IntoIterator<Item = ForgeRelease>,
+) -> Vec<(Metadata, DownloadData)> {
+    releases
+        .into_iter()
+        .flat_map(|release| {
+            let channel = if release.prerelease {
+                ReleaseChannel::Beta
+            } else {
+                ReleaseChannel::Release
+            };
+            release.assets.into_iter().map(move |asset| {
+                let (game_versions, loaders) = guess_compatibility(&asset.name);
+                (
+                    Metadata {
+                        title: release.name.clone(),
+                        description: release.description.clone(),
+                        channel,
+                        game_versions,
+                        loaders,
+                        filename: asset.name.clone(),
+                    },
+                    DownloadData {
+                        channel: Some(channel),
+                        ..from_forge_asset(repository, asset)
+                    },
+                )
+            })
+        })
+        .collect_vec()
+}
+
+pub fn from_forge_asset(repository: &ForgeRepository, asset: ForgeAsset) -> DownloadData {
+    DownloadData {
+        identifier: Some(ModIdentifier::ForgeRepository(
+            repository.clone(),
+            Some(asset.id),
+        )),
+        download_url: asset.download_url,
+        output: asset.name.into(),
+        length: asset.size,
+        hashes: Hashes::default(),
+        channel: None,
+        dependencies: Vec::new(),
+        optional_dependencies: Vec::new(),
+        conflicts: Vec::new(),
+    }
+}
+
+pub fn from_gh_asset(repo: &(String, String), asset: GHAsset) -> DownloadData {
     DownloadData {
+        identifier: Some(ModIdentifier::GitHubRepository(
+            repo.clone(),
+            Some(asset.node_id),
+        )),
         download_url: asset.browser_download_url,
         output: asset.name.into(),
         length: asset.size as usize,
+        hashes: Hashes::default(),
+        channel: None,
         dependencies: Vec::new(),
+        optional_dependencies: Vec::new(),
         conflicts: Vec::new(),
     }
 }
 
+/// The path the validator of a partial download of `out_file_path` is saved to while downloading
+///
+/// It ends with `.part` like the partial download, so that it is cleaned up with it.
+pub fn validator_path(out_file_path: &Path) -> PathBuf {
+    out_file_path.with_extension("validator.part")
+}
+
+/// The strong ETag or the last modified date of the file in `response`, which can be used in an `If-Range` header
+fn response_validator(response: &Response) -> Option<&str> {
+    response
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        // Weak ETags can't be used to resume downloads
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| {
+            response
+                .headers()
+                .get(LAST_MODIFIED)
+                .and_then(|date| date.to_str().ok())
+        })
+}
+
+/// The position of the first byte in the `Content-Range` of `response`
+fn range_start(response: &Response) -> Option<usize> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .trim()
+        .parse()
+        .ok()
+}
+
 impl DownloadData {
     /// Consumes `self` and downloads the file to the `output_dir`
     ///
     /// The `update` closure is called with the chunk length whenever a chunk is downloaded and written.
+    /// If a partially downloaded file from an earlier attempt exists, the download is resumed from where it stopped
+    /// when the server supports it and the file hasn't changed since, and the length of the existing part is passed to `update`.
+    /// Files with a `file` URL, which are on this computer, are copied instead.
+    /// The file is only moved into the `output_dir` if its hashes match the expected hashes,
+    /// otherwise the partially downloaded file is deleted and `Error::HashMismatch` is returned.
     ///
     /// Returns the total size of the file and the filename.
     pub async fn download(
@@ -275,28 +708,116 @@ impl DownloadData {
         let (filename, url, size) = (self.filename(), self.download_url, self.length);
         let out_file_path = output_dir.as_ref().join(&self.output);
         let temp_file_path = out_file_path.with_extension("part");
+        let validator_path = validator_path(&out_file_path);
         if let Some(up_dir) = out_file_path.parent() {
             create_dir_all(up_dir)?;
         }
 
-        let mut temp_file = BufWriter::with_capacity(
-            size,
+        let mut hasher = Hasher::new(&self.hashes);
+
+        if url.scheme() == "file" {
+            // Local files are copied instead
+            let path = url
+                .to_file_path()
+                .map_err(|()| Error::RelativePath(url.path().into()))?;
+            copy(path, &temp_file_path)?;
+            hasher.update_from(File::open(&temp_file_path)?)?;
+            update(size);
+        } else {
+            Self::fetch(
+                client,
+                url,
+                size,
+                &temp_file_path,
+                &validator_path,
+                &mut hasher,
+                &update,
+            )
+            .await?;
+            if validator_path.exists() {
+                remove_file(validator_path)?;
+            }
+        }
+        if let Err(err) = hasher.verify(&self.hashes, &filename) {
+            remove_file(temp_file_path)?;
+            return Err(err);
+        }
+        rename(temp_file_path, out_file_path)?;
+        Ok((size, filename))
+    }
+
+    /// Download the file at `url` to `temp_file_path`,
+    /// resuming from the end of an existing partial download if the server supports it
+    ///
+    /// The ETag or last modified date of the file is saved to `validator_path` while downloading,
+    /// and the download is only resumed if the server confirms that the file still has it.
+    async fn fetch(
+        client: Client,
+        url: Url,
+        size: usize,
+        temp_file_path: &Path,
+        validator_path: &Path,
+        hasher: &mut Hasher,
+        update: &(impl Fn(usize) + Send),
+    ) -> Result<()> {
+        let existing = metadata(temp_file_path).map_or(0, |m| m.len() as usize);
+        let validator = read_to_string(validator_path)
+            .ok()
+            .filter(|_| 0 < existing && existing < size);
+        let mut response = Self::request(
+            &client,
+            &url,
+            validator.as_deref().map(|validator| (existing, validator)),
+        )
+        .await?;
+
+        // The server sends the whole file if it doesn't support ranges or if the file changed
+        let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+        let resuming = validator.is_some() && partial && range_start(&response) == Some(existing);
+        if partial && !resuming {
+            // The server sent a different part of the file than the one requested
+            response = Self::request(&client, &url, None).await?;
+        }
+        if resuming {
+            hasher.update_from(File::open(temp_file_path)?)?;
+            update(existing);
+        } else {
+            match response_validator(&response) {
+                Some(validator) => write(validator_path, validator)?,
+                None if validator_path.exists() => remove_file(validator_path)?,
+                None => (),
+            }
+        }
+        let mut temp_file = BufWriter::new(
             OpenOptions::new()
-                .append(true)
+                .append(resuming)
+                .write(true)
+                .truncate(!resuming)
                 .create(true)
-                .open(&temp_file_path)?,
+                .open(temp_file_path)?,
         );
 
-        let mut response = client.get(url).send().await?;
-        response.error_for_status_ref()?;
-
         while let Some(chunk) = response.chunk().await? {
             temp_file.write_all(&chunk)?;
+            hasher.update(&chunk);
             update(chunk.len());
         }
         temp_file.flush()?;
-        rename(temp_file_path, out_file_path)?;
-        Ok((size, filename))
+        Ok(())
+    }
+
+    /// Request the file at `url`, or only the part after the first `range.0` bytes if it still has the validator `range.1`
+    async fn request(client: &Client, url: &Url, range: Option<(usize, &str)>) -> Result<Response> {
+        // Assets of private repositories on other forges need their access token
+        let mut request = forge::authenticate(client.get(url.clone()), url);
+        if let Some((start, validator)) = range {
+            request = request
+                .header(RANGE, format!("bytes={start}-"))
+                .header(IF_RANGE, validator);
+        }
+        let response = request.send().await?;
+        response.error_for_status_ref()?;
+        Ok(response)
     }
 
     pub fn filename(&self) -> String {
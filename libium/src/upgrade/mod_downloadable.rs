@@ -1,15 +1,18 @@
 use super::{
-    from_gh_asset, from_gh_releases, from_mr_version, try_from_cf_file, DistributionDeniedError,
-    DownloadData,
+    content_length, from_direct_url, from_forge_asset, from_forge_releases, from_gh_asset,
+    from_gh_releases, from_local_path, from_maven_version, from_mr_version, try_from_cf_file,
+    DistributionDeniedError, DownloadData, Metadata,
 };
 use crate::{
     config::{
         filters::Filter,
-        structs::{Mod, ModIdentifier},
+        structs::{Environment, Mod, ModIdentifier},
     },
+    forge,
     iter_ext::IterExt as _,
-    CURSEFORGE_API, GITHUB_API, MODRINTH_API,
+    maven, CURSEFORGE_API, GITHUB_API, MODRINTH_API,
 };
+use futures_util::future::{join, try_join_all};
 use std::cmp::Reverse;
 
 #[derive(Debug, thiserror::Error)]
@@ -25,19 +28,55 @@ pub enum Error {
     CurseForgeError(#[from] furse::Error),
     #[error("GitHub: {0:#?}")]
     GitHubError(#[from] octocrab::Error),
+    #[error("Maven: {0}")]
+    MavenError(#[from] maven::Error),
+    ForgeError(#[from] forge::Error),
+    #[error("Maven: {0}")]
+    MavenRequestError(#[from] reqwest::Error),
+    FileError(#[from] super::Error),
 }
 type Result<T> = std::result::Result<T, Error>;
 
 impl Mod {
-    pub async fn fetch_download_file(&self, profile_filters: Vec<Filter>) -> Result<DownloadData> {
-        match &self.identifier {
+    /// Get the latest compatible file of this mod,
+    /// or `None` if this mod is not used in the profile's `environment`
+    pub async fn fetch_download_file(
+        &self,
+        profile_filters: Vec<Filter>,
+        environment: Environment,
+    ) -> Result<Option<DownloadData>> {
+        Ok(self
+            .fetch_candidates(profile_filters, environment)
+            .await?
+            .map(|mut candidates| candidates.swap_remove(0)))
+    }
+
+    /// Get the compatible files of this mod in the order of preference, latest first,
+    /// or `None` if this mod is not used in the profile's `environment`
+    ///
+    /// A pinned mod only has its pinned file. The returned files are never empty.
+    /// Mods without an environment are used in every environment, see [`fetch_environments`].
+    pub async fn fetch_candidates(
+        &self,
+        profile_filters: Vec<Filter>,
+        environment: Environment,
+    ) -> Result<Option<Vec<DownloadData>>> {
+        if !self
+            .environment
+            .is_none_or(|mod_environment| mod_environment.supports(environment))
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(match &self.identifier {
             ModIdentifier::CurseForgeProject(mod_id, Some(pin)) => {
-                Ok(try_from_cf_file(CURSEFORGE_API.get_mod_file(*mod_id, *pin).await?)?.1)
+                vec![try_from_cf_file(CURSEFORGE_API.get_mod_file(*mod_id, *pin).await?)?.1]
             }
             ModIdentifier::ModrinthProject(_, Some(pin)) => {
-                Ok(from_mr_version(MODRINTH_API.version_get(pin).await?).1)
+                vec![from_mr_version(MODRINTH_API.version_get(pin).await?).1]
             }
-            ModIdentifier::GitHubRepository((owner, repo), Some(pin)) => Ok(from_gh_asset(
+            ModIdentifier::GitHubRepository(id @ (owner, repo), Some(pin)) => vec![from_gh_asset(
+                id,
                 GITHUB_API
                     .repos(owner, repo)
                     .releases()
@@ -49,44 +88,245 @@ impl Mod {
                     .flat_map(|release| release.assets)
                     .find(|asset| &asset.node_id == pin)
                     .ok_or(Error::InvalidPinID)?,
-            )),
-            id => {
-                let download_files = match &id {
-                    ModIdentifier::CurseForgeProject(id, None) => {
-                        let mut files = CURSEFORGE_API.get_mod_files(*id).await?;
-                        files.sort_unstable_by_key(|f| Reverse(f.file_date));
-                        files
-                            .into_iter()
-                            .map(|f| try_from_cf_file(f).map_err(Into::into))
-                            .collect::<Result<Vec<_>>>()?
+            )],
+            ModIdentifier::ForgeRepository(repository, Some(pin)) => vec![from_forge_asset(
+                repository,
+                forge::releases(repository)
+                    .await?
+                    .into_iter()
+                    .flat_map(|release| release.assets)
+                    .find(|asset| &asset.id == pin)
+                    .ok_or(Error::InvalidPinID)?,
+            )],
+            ModIdentifier::MavenArtifact(artifact, Some(version)) => {
+                vec![with_maven_checksum(from_maven_version(artifact, version.clone())?.1).await?]
+            }
+            _ => {
+                let (files, indices) = self.fetch_files(profile_filters).await?;
+                let mut files = files.into_iter().map(Some).collect_vec();
+                let mut candidates = indices
+                    .into_iter()
+                    .filter_map(|i| files[i].take())
+                    .map(|(_, download_data)| download_data)
+                    .collect_vec();
+                if let ModIdentifier::MavenArtifact(..) = self.identifier {
+                    // Maven artifacts don't declare dependencies that could rule out the latest file,
+                    // so only its hash is fetched instead of one for every version
+                    vec![with_maven_checksum(candidates.swap_remove(0)).await?]
+                } else {
+                    candidates
+                }
+            }
+        }))
+    }
+
+    /// Get every file of this mod's project, latest first, and the indices of the compatible ones in the order of preference
+    ///
+    /// The mod's pin is ignored.
+    /// Mods from a URL or a local path only have one file, which is always considered compatible.
+    async fn fetch_files(
+        &self,
+        profile_filters: Vec<Filter>,
+    ) -> Result<(Vec<(Metadata, DownloadData)>, Vec<usize>)> {
+        let download_files = match &self.identifier {
+            ModIdentifier::DirectUrl(url, hashes) => {
+                return Ok((vec![from_direct_url(url, hashes).await?], vec![0]));
+            }
+            ModIdentifier::LocalPath(path) => return Ok((vec![from_local_path(path)?], vec![0])),
+            ModIdentifier::CurseForgeProject(id, _) => {
+                let mut files = CURSEFORGE_API.get_mod_files(*id).await?;
+                files.sort_unstable_by_key(|f| Reverse(f.file_date));
+                files
+                    .into_iter()
+                    .map(|f| try_from_cf_file(f).map_err(Into::into))
+                    .collect::<Result<Vec<_>>>()?
+            }
+            ModIdentifier::ModrinthProject(id, _) => MODRINTH_API
+                .version_list(id)
+                .await?
+                .into_iter()
+                .map(from_mr_version)
+                .collect_vec(),
+            ModIdentifier::ForgeRepository(repository, _) => {
+                from_forge_releases(repository, forge::releases(repository).await?)
+            }
+            ModIdentifier::MavenArtifact(artifact, _) => maven::versions(artifact)
+                .await?
+                .into_iter()
+                .map(|version| from_maven_version(artifact, version))
+                .collect::<maven::Result<Vec<_>>>()?,
+            ModIdentifier::GitHubRepository(id @ (owner, repo), _) => GITHUB_API
+                .repos(owner, repo)
+                .releases()
+                .list()
+                .send()
+                .await
+                .map(|r| from_gh_releases(id, r.items))?,
+        };
+
+        let indices = super::check::select_candidates(
+            download_files.iter().map(|(m, _)| m),
+            if self.override_filters {
+                self.filters.clone()
+            } else {
+                [profile_filters, self.filters.clone()].concat()
+            }
+            .into_iter()
+            // Only mods are made for a mod loader
+            .filter(|filter| {
+                self.project_type.is_mod()
+                    || !matches!(filter, Filter::ModLoaderPrefer(_) | Filter::ModLoaderAny(_))
+            })
+            .collect_vec(),
+        )
+        .await?;
+        Ok((download_files, indices))
+    }
+
+    /// Get the metadata, including the changelogs, of the compatible files of this mod
+    /// that are newer than the file of `from`, up to and including the file of `to`, latest first
+    ///
+    /// `from` and `to` are identifiers of this mod pinned to its files.
+    /// If `from` is not known or is not one of the mod's files, only the file of `to` is returned.
+    /// Files that share a title and changelog, such as the assets of a GitHub, GitLab, Gitea, or Forgejo release, are only returned once.
+    /// The changelogs of CurseForge files are fetched separately and converted from HTML to text.
+    pub async fn fetch_changelogs(
+        &self,
+        from: Option<&ModIdentifier>,
+        to: &ModIdentifier,
+        profile_filters: Vec<Filter>,
+    ) -> Result<Vec<Metadata>> {
+        let (mut files, compatible) = self.fetch_files(profile_filters).await?;
+        let position = |identifier: &ModIdentifier| {
+            files.iter().position(|(_, download_data)| {
+                download_data.identifier.as_ref() == Some(identifier)
+            })
+        };
+        let to_index = position(to).ok_or(Error::InvalidPinID)?;
+        let from_index = from.and_then(position).unwrap_or(to_index + 1);
+
+        let mut indices = compatible
+            .into_iter()
+            .filter(|&i| to_index < i && i < from_index)
+            .collect_vec();
+        indices.push(to_index);
+        indices.sort_unstable();
+
+        let mut changelogs: Vec<(Metadata, Option<ModIdentifier>)> = Vec::new();
+        for i in indices {
+            let (metadata, download_data) = &mut files[i];
+            if changelogs.last().is_some_and(|(last, _)| {
+                last.title == metadata.title && last.description == metadata.description
+            }) {
+                continue;
+            }
+            changelogs.push((metadata.clone(), download_data.identifier.take()));
+        }
+        try_join_all(
+            changelogs
+                .into_iter()
+                .map(|(mut metadata, identifier)| async move {
+                    if let Some(ModIdentifier::CurseForgeProject(mod_id, Some(file_id))) =
+                        identifier
+                    {
+                        metadata.description = html_to_text(
+                            &CURSEFORGE_API
+                                .get_mod_file_changelog(mod_id, file_id)
+                                .await?,
+                        );
                     }
-                    ModIdentifier::ModrinthProject(id, None) => MODRINTH_API
-                        .version_list(id)
-                        .await?
-                        .into_iter()
-                        .map(from_mr_version)
-                        .collect_vec(),
-                    ModIdentifier::GitHubRepository((owner, repo), None) => GITHUB_API
-                        .repos(owner, repo)
-                        .releases()
-                        .list()
-                        .send()
-                        .await
-                        .map(|r| from_gh_releases(r.items))?,
-                    _ => unreachable!(),
-                };
+                    Ok(metadata)
+                }),
+        )
+        .await
+    }
+}
+
+/// Set the environment of the Modrinth projects in `mods` that don't have one to the environment the project reports,
+/// with one request for all of them
+///
+/// This only has to be done when resolving mods for a profile that isn't used on both sides.
+pub async fn fetch_environments(mods: &mut [Mod]) -> Result<()> {
+    let ids = mods
+        .iter()
+        .filter(|mod_| mod_.environment.is_none())
+        .filter_map(|mod_| match &mod_.identifier {
+            ModIdentifier::ModrinthProject(id, _) => Some(id.as_str()),
+            _ => None,
+        })
+        .collect_vec();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let projects = MODRINTH_API.project_get_multiple(&ids).await?;
+    for mod_ in mods.iter_mut().filter(|mod_| mod_.environment.is_none()) {
+        if let ModIdentifier::ModrinthProject(id, _) = &mod_.identifier {
+            mod_.environment = projects
+                .iter()
+                .find(|project| &project.id == id || &project.slug == id)
+                .map(|project| Environment::from_sides(project.client_side, project.server_side));
+        }
+    }
+    Ok(())
+}
 
-                let index = super::check::select_latest(
-                    download_files.iter().map(|(m, _)| m),
-                    if self.override_filters {
-                        self.filters.clone()
-                    } else {
-                        [profile_filters.clone(), self.filters.clone().clone()].concat()
-                    },
-                )
-                .await?;
-                Ok(download_files.into_iter().nth(index).unwrap().1)
+/// Fetch the hashes and length of a file from a Maven repository, which aren't listed with its versions
+async fn with_maven_checksum(mut download_data: DownloadData) -> Result<DownloadData> {
+    let (hashes, length) = join(
+        maven::checksum(&download_data.download_url),
+        content_length(&download_data.download_url),
+    )
+    .await;
+    download_data.hashes = hashes?;
+    download_data.length = length?;
+    Ok(download_data)
+}
+
+/// Convert the HTML of a CurseForge changelog to plain text
+///
+/// Line breaks, paragraphs, and list items are kept, other tags are removed,
+/// and the most common character references are decoded.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let closing = rest[start + 1..].starts_with('/');
+        match tag.as_str() {
+            "br" => text.push('\n'),
+            "li" if !closing => text.push_str("\n- "),
+            "p" | "div" | "ul" | "ol" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if closing => {
+                text.push('\n');
             }
+            _ => (),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    // Remove trailing whitespace and runs of empty lines
+    let mut lines = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if !(line.is_empty() && lines.last().is_none_or(|last: &&str| last.is_empty())) {
+            lines.push(line);
         }
     }
+    lines.join("\n").trim_end().to_owned()
 }
@@ -0,0 +1,564 @@
+use super::DownloadData;
+use crate::{config::structs::ModIdentifier, iter_ext::IterExt as _};
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+};
+
+/// The number of files the resolver tries for each root before giving up
+const MAX_STEPS: usize = 100_000;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Conflict {
+    #[error("{project} could not be resolved: {reason}")]
+    Failed { project: String, reason: String },
+    #[error(
+        "No compatible file of {project} satisfies these requirements: {}",
+        requirements.iter().display(", ")
+    )]
+    Unsatisfiable {
+        project: String,
+        requirements: Vec<String>,
+    },
+    #[error("Gave up after trying too many combinations of files")]
+    TooComplex,
+}
+
+/// The files of a project that the resolver can choose from
+#[derive(Debug, Clone)]
+pub enum Files {
+    /// The files that are compatible with the profile, in the order of preference
+    Compatible(Vec<DownloadData>),
+    /// The project is not used in the profile's environment, so it is left out
+    Unused,
+    /// Getting the files of the project failed with this reason
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+struct Project {
+    name: String,
+    files: Files,
+    install_optional: bool,
+}
+
+/// Chooses a file for each project, so that the dependencies of every chosen file are satisfied
+///
+/// Projects are added to the resolver with the files that are compatible with the profile,
+/// so the profile's filters are constraints on the files that can be chosen.
+/// Dependencies on a specific version of a project are constraints too,
+/// which can make the resolver choose an older file of the dependent or the dependency.
+/// Projects whose files could not be fetched fail the projects that require them.
+#[derive(Debug, Default, Clone)]
+pub struct Resolver {
+    projects: HashMap<ModIdentifier, Project>,
+}
+
+/// A file chosen by the resolver
+#[derive(Debug, Clone)]
+pub struct Selected {
+    /// The identifier of the project, which is not pinned
+    pub project: ModIdentifier,
+    pub file: DownloadData,
+    /// The projects that depend on this one
+    pub dependents: Vec<ModIdentifier>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    /// The chosen files, in the order their projects were required
+    pub selected: Vec<Selected>,
+    /// Projects that are not used in the profile's environment
+    pub unused: Vec<ModIdentifier>,
+    /// Projects that are required but have not been added to the resolver yet
+    ///
+    /// They are assumed to be compatible with everything,
+    /// so they should be added and the projects resolved again until this is empty.
+    pub missing: Vec<ModIdentifier>,
+    /// The roots that could not be resolved, and why
+    pub failed: Vec<(ModIdentifier, Conflict)>,
+    /// The optional dependencies that are left out because they could not be resolved, and why
+    pub skipped: Vec<(ModIdentifier, Conflict)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// The projects in the order they were first required
+    order: Vec<ModIdentifier>,
+    /// The requirements on each project, and the project that has each requirement
+    requirements: HashMap<ModIdentifier, Vec<(ModIdentifier, Option<ModIdentifier>)>>,
+    /// The index of the chosen file of each project
+    selected: HashMap<ModIdentifier, usize>,
+    /// The projects that still have to be chosen
+    queue: VecDeque<ModIdentifier>,
+}
+
+/// The version `identifier` is pinned to
+fn pin(identifier: &ModIdentifier) -> Option<String> {
+    match identifier {
+        ModIdentifier::CurseForgeProject(_, pin) => pin.map(|pin| pin.to_string()),
+        ModIdentifier::ModrinthProject(_, pin)
+        | ModIdentifier::GitHubRepository(_, pin)
+        | ModIdentifier::MavenArtifact(_, pin)
+        | ModIdentifier::ForgeRepository(_, pin) => pin.clone(),
+        ModIdentifier::DirectUrl(..) | ModIdentifier::LocalPath(_) => None,
+    }
+}
+
+/// Whether `file` is the version required by `requirement`
+fn satisfies(requirement: &ModIdentifier, file: &DownloadData) -> bool {
+    pin(requirement).is_none_or(|required| file.identifier.as_ref().and_then(pin) == Some(required))
+}
+
+impl Resolver {
+    /// Add the project of `identifier` with its `files`
+    ///
+    /// If `install_optional` is set, the optional dependencies of the project's chosen file are also resolved,
+    /// but they are left out instead of failing if they can't be resolved.
+    pub fn insert(
+        &mut self,
+        identifier: &ModIdentifier,
+        name: String,
+        files: Files,
+        install_optional: bool,
+    ) {
+        self.projects.insert(
+            identifier.unpinned(),
+            Project {
+                name,
+                files,
+                install_optional,
+            },
+        );
+    }
+
+    /// Whether the project of `identifier` has been added to the resolver
+    pub fn contains(&self, identifier: &ModIdentifier) -> bool {
+        self.projects.contains_key(&identifier.unpinned())
+    }
+
+    /// Choose the files of the `roots` and their dependencies
+    ///
+    /// The roots are added one at a time, preferring the latest files.
+    /// If a root conflicts with the files chosen for the previous ones, they are chosen again together,
+    /// and the root is only left out and returned with the conflict if no combination of files works.
+    pub fn resolve(&self, roots: &[ModIdentifier]) -> Resolution {
+        let mut state = State::default();
+        let mut required = Vec::new();
+        let mut failed = Vec::new();
+        for root in roots {
+            match self.add(&state, &required, root, None) {
+                Ok(next) => {
+                    state = next;
+                    required.push((root.clone(), None));
+                }
+                Err(conflict) => failed.push((root.clone(), conflict)),
+            }
+        }
+
+        // Add the optional dependencies of the chosen files that can be resolved
+        let mut tried = Vec::new();
+        let mut skipped = Vec::new();
+        while let Some((dependency, dependent)) = state
+            .order
+            .iter()
+            .filter(|project| {
+                self.projects
+                    .get(project)
+                    .is_some_and(|project| project.install_optional)
+            })
+            .filter_map(|project| Some((project, self.selected_file(&state, project)?)))
+            .flat_map(|(project, file)| {
+                file.optional_dependencies
+                    .iter()
+                    .map(move |dependency| (dependency, project))
+            })
+            .find(|(dependency, _)| {
+                !state.requirements.contains_key(&dependency.unpinned())
+                    && !tried.contains(*dependency)
+            })
+            .map(|(dependency, dependent)| (dependency.clone(), dependent.clone()))
+        {
+            tried.push(dependency.clone());
+            match self.add(&state, &required, &dependency, Some(&dependent)) {
+                Ok(next) => {
+                    state = next;
+                    required.push((dependency, Some(dependent)));
+                }
+                Err(conflict) => skipped.push((dependency, conflict)),
+            }
+        }
+
+        let mut resolution = Resolution {
+            failed,
+            skipped,
+            ..Default::default()
+        };
+        for project in &state.order {
+            match self.projects.get(project).map(|project| &project.files) {
+                None => resolution.missing.push(project.clone()),
+                Some(Files::Unused) => resolution.unused.push(project.clone()),
+                _ => {
+                    if let Some(file) = self.selected_file(&state, project) {
+                        resolution.selected.push(Selected {
+                            project: project.clone(),
+                            file: file.clone(),
+                            dependents: state.requirements[project]
+                                .iter()
+                                .filter_map(|(_, dependent)| dependent.clone())
+                                .collect_vec(),
+                        });
+                    }
+                }
+            }
+        }
+        resolution
+    }
+
+    fn selected_file(&self, state: &State, project: &ModIdentifier) -> Option<&DownloadData> {
+        match &self.projects.get(project)?.files {
+            Files::Compatible(files) => files.get(*state.selected.get(project)?),
+            _ => None,
+        }
+    }
+
+    /// Require `requirement` in `state` and resolve the projects that are required because of it
+    ///
+    /// If the files chosen in `state` conflict with it, the files are chosen again from scratch
+    /// for the `previous` requirements that `state` was resolved from and `requirement`.
+    fn add(
+        &self,
+        state: &State,
+        previous: &[(ModIdentifier, Option<ModIdentifier>)],
+        requirement: &ModIdentifier,
+        dependent: Option<&ModIdentifier>,
+    ) -> Result<State, Conflict> {
+        let steps = Cell::new(0);
+        let mut next = state.clone();
+        let conflict = match self
+            .require(&mut next, requirement, dependent)
+            .and_then(|()| self.search(next, &steps))
+        {
+            Ok(next) => return Ok(next),
+            Err(err @ (Conflict::Failed { .. } | Conflict::TooComplex)) => return Err(err),
+            Err(conflict) => conflict,
+        };
+
+        let mut next = State::default();
+        for (requirement, dependent) in previous {
+            self.require(&mut next, requirement, dependent.as_ref())?;
+        }
+        self.require(&mut next, requirement, dependent)?;
+        // The conflict with the files chosen before explains best why the requirement can't be satisfied
+        self.search(next, &steps).map_err(|err| match err {
+            Conflict::TooComplex => err,
+            _ => conflict,
+        })
+    }
+
+    /// Record that `dependent` (or the profile) requires `requirement`,
+    /// returns a conflict if the file chosen for its project doesn't satisfy it
+    fn require(
+        &self,
+        state: &mut State,
+        requirement: &ModIdentifier,
+        dependent: Option<&ModIdentifier>,
+    ) -> Result<(), Conflict> {
+        let project = requirement.unpinned();
+        let requirements = state.requirements.entry(project.clone()).or_default();
+        requirements.push((requirement.clone(), dependent.cloned()));
+        if requirements.len() == 1 {
+            state.order.push(project.clone());
+            state.queue.push_back(project.clone());
+        }
+
+        match self.projects.get(&project).map(|project| &project.files) {
+            Some(Files::Failed(reason)) => Err(Conflict::Failed {
+                project: self.name(&project),
+                reason: reason.clone(),
+            }),
+            Some(Files::Compatible(files)) => match state.selected.get(&project) {
+                Some(&index) if !satisfies(requirement, &files[index]) => {
+                    Err(self.unsatisfiable(state, &project))
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Choose the files of the projects in the queue of `state`, backtracking when there is a conflict
+    fn search(&self, mut state: State, steps: &Cell<usize>) -> Result<State, Conflict> {
+        let Some(project) = state.queue.pop_front() else {
+            return Ok(state);
+        };
+        // Missing projects are assumed to be compatible until they are added, and unused ones are left out
+        let Some(Files::Compatible(files)) = self.projects.get(&project).map(|p| &p.files) else {
+            return self.search(state, steps);
+        };
+
+        let mut conflict = None;
+        for (index, file) in files.iter().enumerate() {
+            if !state.requirements[&project]
+                .iter()
+                .all(|(requirement, _)| satisfies(requirement, file))
+            {
+                continue;
+            }
+            steps.set(steps.get() + 1);
+            if steps.get() > MAX_STEPS {
+                return Err(Conflict::TooComplex);
+            }
+
+            let mut next = state.clone();
+            next.selected.insert(project.clone(), index);
+            let result = file
+                .dependencies
+                .iter()
+                .try_for_each(|dependency| self.require(&mut next, dependency, Some(&project)))
+                .and_then(|()| self.search(next, steps));
+            match result {
+                Ok(state) => return Ok(state),
+                // Projects that could not be fetched shouldn't make the resolver fall back to older files
+                Err(err @ (Conflict::Failed { .. } | Conflict::TooComplex)) => return Err(err),
+                // The conflict of the most preferred file is the most relevant one
+                Err(err) => {
+                    conflict.get_or_insert(err);
+                }
+            }
+        }
+        Err(conflict.unwrap_or_else(|| self.unsatisfiable(&state, &project)))
+    }
+
+    fn name(&self, project: &ModIdentifier) -> String {
+        self.projects.get(project).map_or_else(
+            || match project {
+                ModIdentifier::CurseForgeProject(id, _) => id.to_string(),
+                ModIdentifier::ModrinthProject(id, _) => id.clone(),
+                ModIdentifier::GitHubRepository((owner, repo), _) => format!("{owner}/{repo}"),
+                ModIdentifier::MavenArtifact(artifact, _) => crate::maven::display_name(artifact),
+                ModIdentifier::ForgeRepository(repository, _) => {
+                    crate::forge::display_name(repository)
+                }
+                ModIdentifier::DirectUrl(url, _) => url.to_string(),
+                ModIdentifier::LocalPath(path) => path.display().to_string(),
+            },
+            |project| project.name.clone(),
+        )
+    }
+
+    /// Describe the version requirements on `project` that can't all be satisfied
+    fn unsatisfiable(&self, state: &State, project: &ModIdentifier) -> Conflict {
+        let files = match self.projects.get(project).map(|p| &p.files) {
+            Some(Files::Compatible(files)) => files.as_slice(),
+            _ => &[],
+        };
+        Conflict::Unsatisfiable {
+            project: self.name(project),
+            requirements: state.requirements[project]
+                .iter()
+                .filter_map(|(requirement, dependent)| {
+                    let version = pin(requirement)?;
+                    Some(format!(
+                        "{} requires {}",
+                        dependent
+                            .as_ref()
+                            .map_or_else(|| "the profile".to_owned(), |d| self.name(d)),
+                        files
+                            .iter()
+                            .find(|file| satisfies(requirement, file))
+                            .map_or_else(
+                                || format!(
+                                    "version {version}, which is not compatible with the profile"
+                                ),
+                                DownloadData::filename
+                            )
+                    ))
+                })
+                .collect_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upgrade::Hashes;
+    use reqwest::Url;
+
+    fn project(id: &str) -> ModIdentifier {
+        ModIdentifier::ModrinthProject(id.to_owned(), None)
+    }
+
+    fn pinned(id: &str, version: &str) -> ModIdentifier {
+        ModIdentifier::ModrinthProject(id.to_owned(), Some(version.to_owned()))
+    }
+
+    /// The file with `version` of the project `id`, which requires `dependencies`
+    fn file(id: &str, version: &str, dependencies: &[ModIdentifier]) -> DownloadData {
+        DownloadData {
+            identifier: Some(pinned(id, version)),
+            download_url: Url::parse(&format!("https://example.com/{id}-{version}.jar")).unwrap(),
+            output: format!("{id}-{version}.jar").into(),
+            length: 0,
+            hashes: Hashes::default(),
+            channel: None,
+            dependencies: dependencies.to_vec(),
+            optional_dependencies: vec![],
+            conflicts: vec![],
+        }
+    }
+
+    fn resolver(projects: Vec<(&str, Files)>) -> Resolver {
+        let mut resolver = Resolver::default();
+        for (id, files) in projects {
+            resolver.insert(&project(id), id.to_owned(), files, true);
+        }
+        resolver
+    }
+
+    /// The chosen files of `resolution`, as the identifiers pinned to them
+    fn chosen(resolution: &Resolution) -> Vec<ModIdentifier> {
+        resolution
+            .selected
+            .iter()
+            .filter_map(|selected| selected.file.identifier.clone())
+            .collect_vec()
+    }
+
+    #[test]
+    fn pins() {
+        let resolver = resolver(vec![
+            (
+                "a",
+                Files::Compatible(vec![
+                    file("a", "3", &[]),
+                    file("a", "2", &[]),
+                    file("a", "1", &[]),
+                ]),
+            ),
+            (
+                "b",
+                Files::Compatible(vec![
+                    file("b", "2", &[pinned("c", "1")]),
+                    file("b", "1", &[]),
+                ]),
+            ),
+            (
+                "c",
+                Files::Compatible(vec![file("c", "2", &[]), file("c", "1", &[])]),
+            ),
+        ]);
+        let resolution = resolver.resolve(&[pinned("a", "2"), project("b")]);
+        assert_eq!(
+            chosen(&resolution),
+            [pinned("a", "2"), pinned("b", "2"), pinned("c", "1")]
+        );
+        assert_eq!(resolution.selected[2].dependents, [project("b")]);
+        assert!(resolution.failed.is_empty());
+    }
+
+    #[test]
+    fn conflicts_between_roots() {
+        let resolver = resolver(vec![
+            (
+                "a",
+                Files::Compatible(vec![file("a", "2", &[]), file("a", "1", &[])]),
+            ),
+            (
+                "b",
+                Files::Compatible(vec![file("b", "1", &[pinned("a", "1")])]),
+            ),
+            ("c", Files::Compatible(vec![file("c", "1", &[])])),
+        ]);
+
+        // An older file of the first root is chosen so that the second root can be resolved
+        let resolution = resolver.resolve(&[project("a"), project("b")]);
+        assert_eq!(chosen(&resolution), [pinned("a", "1"), pinned("b", "1")]);
+        assert!(resolution.failed.is_empty());
+
+        // Unless the first root is pinned, then the second root is left out
+        let resolution = resolver.resolve(&[pinned("a", "2"), project("b"), project("c")]);
+        assert_eq!(chosen(&resolution), [pinned("a", "2"), pinned("c", "1")]);
+        assert_eq!(resolution.failed.len(), 1);
+        assert_eq!(resolution.failed[0].0, project("b"));
+        assert!(matches!(
+            resolution.failed[0].1,
+            Conflict::Unsatisfiable { .. }
+        ));
+    }
+
+    #[test]
+    fn optional_dependencies() {
+        let mut a = file("a", "1", &[]);
+        a.optional_dependencies = vec![project("b"), project("c")];
+        let resolver = resolver(vec![
+            ("a", Files::Compatible(vec![a])),
+            ("b", Files::Compatible(vec![file("b", "1", &[])])),
+            ("c", Files::Failed("Not found".to_owned())),
+            ("d", Files::Unused),
+        ]);
+        let resolution = resolver.resolve(&[project("a"), project("d")]);
+        assert_eq!(chosen(&resolution), [pinned("a", "1"), pinned("b", "1")]);
+        assert_eq!(resolution.unused, [project("d")]);
+        assert_eq!(resolution.skipped.len(), 1);
+        assert_eq!(resolution.skipped[0].0, project("c"));
+        assert!(resolution.failed.is_empty());
+
+        // They aren't resolved if the project doesn't install them
+        let mut resolver = resolver;
+        resolver
+            .projects
+            .get_mut(&project("a"))
+            .unwrap()
+            .install_optional = false;
+        let resolution = resolver.resolve(&[project("a")]);
+        assert_eq!(chosen(&resolution), [pinned("a", "1")]);
+        assert!(resolution.skipped.is_empty());
+    }
+
+    #[test]
+    fn failed_files() {
+        let resolver = resolver(vec![
+            (
+                "a",
+                Files::Compatible(vec![file("a", "2", &[project("b")]), file("a", "1", &[])]),
+            ),
+            ("b", Files::Failed("Not found".to_owned())),
+            ("c", Files::Compatible(vec![file("c", "1", &[])])),
+        ]);
+        // The older file of the root is not chosen instead, since the dependency might be fine
+        let resolution = resolver.resolve(&[project("a"), project("c")]);
+        assert_eq!(chosen(&resolution), [pinned("c", "1")]);
+        assert_eq!(resolution.failed.len(), 1);
+        assert_eq!(resolution.failed[0].0, project("a"));
+        assert!(matches!(resolution.failed[0].1, Conflict::Failed { .. }));
+    }
+
+    #[test]
+    fn too_complex() {
+        // Every combination of the files of the chain of projects is tried, but the last one's dependency doesn't exist
+        let chain = ["a", "b", "c", "d", "e", "f"];
+        let mut projects = chain
+            .windows(2)
+            .map(|pair| {
+                let files = (0..10)
+                    .map(|version| file(pair[0], &version.to_string(), &[project(pair[1])]))
+                    .collect_vec();
+                (pair[0], Files::Compatible(files))
+            })
+            .collect_vec();
+        projects.push((
+            "f",
+            Files::Compatible(
+                (0..10)
+                    .map(|version| file("f", &version.to_string(), &[pinned("g", "1")]))
+                    .collect_vec(),
+            ),
+        ));
+        projects.push(("g", Files::Compatible(vec![file("g", "2", &[])])));
+        let resolution = resolver(projects).resolve(&[project("a")]);
+        assert!(resolution.selected.is_empty());
+        assert!(matches!(resolution.failed[0].1, Conflict::TooComplex));
+    }
+}
@@ -5,7 +5,7 @@ use clap::{Args, Parser, Subcommand, ValueEnum, ValueHint};
 use clap_complete::Shell;
 use libium::config::{
     filters::{self, Filter},
-    structs::ModLoader,
+    structs::{Environment, ModLoader, OptionalDependencies},
 };
 use std::path::PathBuf;
 
@@ -24,6 +24,15 @@ pub struct Ferium {
     /// Set a GitHub personal access token for increasing the GitHub API rate limit.
     #[clap(long, visible_alias = "gh", env = "GITHUB_TOKEN")]
     pub github_token: Option<String>,
+    /// Set access tokens for GitLab, Gitea, and Forgejo instances, written as `<host>=<token>` (e.g. `gitlab.com=<token>`).
+    /// Separate the tokens of multiple instances with commas.
+    #[clap(
+        long = "forge-token",
+        value_name = "HOST=TOKEN",
+        env = "FORGE_TOKENS",
+        value_delimiter = ','
+    )]
+    pub forge_tokens: Vec<String>,
     /// Set a custom Curseforge API key.
     #[clap(long, visible_alias = "cf", env = "CURSEFORGE_API_KEY")]
     pub curseforge_api_key: Option<String>,
@@ -33,6 +42,16 @@ pub struct Ferium {
     #[clap(long, short, visible_aliases = ["config", "conf"])]
     #[clap(value_hint(ValueHint::FilePath))]
     pub config_file: Option<PathBuf>,
+    /// The format to print results in.
+    /// With `json`, the results are printed to stdout as versioned JSON and progress is printed to stderr.
+    #[clap(
+        long = "output",
+        global = true,
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t
+    )]
+    pub output_format: OutputFormat,
 }
 
 #[derive(Clone, Debug, Subcommand)]
@@ -41,7 +60,7 @@ pub enum SubCommands {
         Use this for filter arguments:
         https://docs.rs/clap/latest/clap/_derive/_tutorial/chapter_3/index.html#argument-relations
     */
-    /// Add mods to the profile
+    /// Add mods, resource packs, shader packs, or datapacks to the profile
     Add {
         /// The identifier(s) of the mod/project/repository
         ///
@@ -49,6 +68,15 @@ pub enum SubCommands {
         /// You can also use the project slug in the URL.
         /// The Curseforge project ID is specified at the top of the right sidebar under 'About Project'.
         /// The GitHub identifier is the repository's full name, e.g. `gorilla-devs/ferium`.
+        /// Identifiers can be prefixed with `mr:`, `cf:`, or `gh:` to choose the platform.
+        ///
+        /// The URLs of projects, versions, files, and releases on these platforms can be used too.
+        /// Other HTTPS URLs are downloaded directly, and need a `--hash`.
+        /// Paths of `.jar` or `.zip` files, or paths prefixed with `file:`, add the local file.
+        /// GitLab, Gitea, and Forgejo repositories are prefixed with `gitlab:`, `gitea:`, or `forgejo:`,
+        /// followed by the repository's full name on gitlab.com, gitea.com, or codeberg.org, or by its URL on another instance.
+        /// Artifacts in a Maven repository are written as `maven:<repository>/<group>:<artifact>[:<version>[:<classifier>]]`,
+        /// e.g. `maven:https://maven.fabricmc.net/net.fabricmc.fabric-api:fabric-api`.
         #[clap(required = true)]
         identifiers: Vec<String>,
 
@@ -56,6 +84,23 @@ pub enum SubCommands {
         #[clap(long, short, visible_alias = "override")]
         force: bool,
 
+        /// The environment the mod is used in, overriding the one reported by Modrinth
+        #[clap(long, short, value_enum)]
+        environment: Option<Environment>,
+
+        /// What to do with the mod's optional dependencies, overriding the profile's setting
+        #[clap(long, value_enum)]
+        optional_dependencies: Option<OptionalDependencies>,
+
+        /// Add the mod even if other mods in the profile have declared that they are incompatible with it,
+        /// and don't check the mod's own declared incompatibilities when upgrading
+        #[clap(long)]
+        ignore_conflicts: bool,
+
+        /// The SHA-1 or SHA-512 hash of the file, which is required when adding a file from another website
+        #[clap(long)]
+        hash: Option<String>,
+
         #[command(flatten)]
         filters: FilterArguments,
     },
@@ -76,6 +121,17 @@ pub enum SubCommands {
         #[clap(long, short, visible_alias = "override")]
         force: bool,
     },
+    /// Inspect, verify, or prune the download cache shared by all profiles and modpacks
+    Cache {
+        #[clap(subcommand)]
+        subcommand: Option<CacheSubCommands>,
+    },
+    /// Show the changelogs of the versions between the installed file and the latest compatible file of a mod,
+    /// or of every mod that isn't up to date
+    Changelog {
+        /// The project ID or case-insensitive name of the mod
+        mod_name: Option<String>,
+    },
     /// Print shell auto completions for the specified shell
     Complete {
         /// The shell to generate auto completions for
@@ -102,6 +158,8 @@ pub enum SubCommands {
     },
     /// List all the modpacks with their data
     Modpacks,
+    /// Compare the installed files of the profile's mods with their latest compatible versions, without changing anything
+    Outdated,
     /// Create, configure, delete, switch, or list profiles
     Profile {
         #[clap(subcommand)]
@@ -116,14 +174,70 @@ pub enum SubCommands {
         /// List of project IDs or case-insensitive names of mods to remove
         mod_names: Vec<String>,
     },
-    /// Download and install the latest compatible version of your mods
+    /// Restore the profile's mods and lockfile to a snapshot taken before an upgrade, the latest one by default
+    Rollback {
+        /// The snapshot to roll back to
+        #[clap(long)]
+        to: Option<String>,
+        /// List the snapshots of the profile instead
+        #[clap(long, short, conflicts_with = "to")]
+        list: bool,
+    },
+    /// Search Modrinth and Curseforge for mods that are compatible with the profile
+    Search {
+        /// What to search for
+        query: String,
+        /// Pick projects from the results to add to the profile
+        #[clap(long, short)]
+        add: bool,
+        /// The platform you prefer the picked mods to be added from.
+        /// If a mod isn't available from this platform, the other platform will still be used.
+        #[clap(long, short, default_value_t, requires = "add")]
+        platform: Platform,
+    },
+    /// Resolve the latest compatible version of your mods and update the lockfile without downloading them
+    Update,
+    /// Download and install the versions of your mods recorded in the lockfile.
+    /// Mods that are not in the lockfile yet will have their latest compatible version resolved.
     #[clap(visible_aliases = ["download", "install"])]
-    Upgrade,
+    Upgrade {
+        /// Don't connect to the internet, and install the locked files from the output directory's `.old` folder
+        /// and the download cache instead
+        #[clap(long)]
+        offline: bool,
+        /// Print the changes that would be made, such as the files that would be added, replaced, or moved to `.old`,
+        /// without changing anything
+        #[clap(long)]
+        dry_run: bool,
+        /// Print the changelogs of the versions between the installed and new files of the mods that are upgraded
+        #[clap(long, conflicts_with = "offline")]
+        changelogs: bool,
+        /// Make the changes without asking for confirmation
+        ///
+        /// Interactive runs print the changes and ask for confirmation before making them.
+        #[clap(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum CacheSubCommands {
+    /// Show where the cache is and how much space it uses
+    Info,
+    /// Check the files in the cache against their hashes, and remove the ones that don't match
+    Verify,
+    /// Remove files that are not in any profile's lockfile or installed by any modpack
+    Prune {
+        /// Remove all files, including the ones that are in use
+        #[clap(long, short)]
+        all: bool,
+    },
 }
 
 #[derive(Clone, Debug, Subcommand)]
 pub enum ProfileSubCommands {
-    /// Configure the current profile's name, Minecraft version, mod loader, and output directory.
+    /// Configure the current profile's name, Minecraft version, mod loader, output directory, environment,
+    /// and optional dependency handling.
     /// Optionally, provide the settings to change as arguments.
     #[clap(visible_aliases = ["config", "conf"])]
     Configure {
@@ -141,6 +255,12 @@ pub enum ProfileSubCommands {
         #[clap(long, short)]
         #[clap(value_hint(ValueHint::DirPath))]
         output_dir: Option<PathBuf>,
+        /// The environment to install mods for, mods not used in it will be skipped
+        #[clap(long, short, value_enum)]
+        environment: Option<Environment>,
+        /// What to do with the optional dependencies of mods
+        #[clap(long, value_enum)]
+        optional_dependencies: Option<OptionalDependencies>,
     },
     /// Create a new profile.
     /// Optionally, provide the settings as arguments.
@@ -166,6 +286,12 @@ pub enum ProfileSubCommands {
         #[clap(long, short)]
         #[clap(value_hint(ValueHint::DirPath))]
         output_dir: Option<PathBuf>,
+        /// The environment to install mods for, mods not used in it will be skipped
+        #[clap(long, short, value_enum)]
+        environment: Option<Environment>,
+        /// What to do with the optional dependencies of mods
+        #[clap(long, value_enum)]
+        optional_dependencies: Option<OptionalDependencies>,
     },
     /// Delete a profile.
     /// Optionally, provide the name of the profile to delete.
@@ -177,6 +303,45 @@ pub enum ProfileSubCommands {
         #[clap(long, short)]
         switch_to: Option<String>,
     },
+    /// Export the current profile as a modpack.
+    /// The files in the profile's lockfile are exported, so run `ferium upgrade` or `ferium update` first.
+    Export {
+        /// The modpack format to export to
+        #[clap(long, short, value_enum, default_value_t)]
+        format: ExportFormat,
+        /// The file (or directory for packwiz) to write the modpack to.
+        /// Defaults to the profile's name in the current directory.
+        #[clap(long, short = 'o')]
+        #[clap(value_hint(ValueHint::FilePath))]
+        file: Option<PathBuf>,
+        /// The version of the modpack
+        #[clap(long, default_value = "1.0.0")]
+        pack_version: String,
+        /// Export a packwiz pack to a directory that isn't empty,
+        /// replacing its `mods` folder and the folders of the profile's other project types
+        #[clap(long)]
+        force: bool,
+    },
+    /// Create a new profile from a modpack, which you can then add and remove mods from.
+    /// The modpack's files are added as pinned mods.
+    Import {
+        /// The modpack file (`.mrpack` or Curseforge `.zip`), the packwiz pack directory or `pack.toml`,
+        /// or the identifier of the modpack project
+        ///
+        /// The Modrinth project ID is specified at the bottom of the left sidebar under 'Technical information'.
+        /// You can also use the project slug for this.
+        /// The Curseforge project ID is specified at the top of the right sidebar under 'About Project'.
+        #[clap(value_hint(ValueHint::FilePath))]
+        source: String,
+        /// The name of the profile.
+        /// Defaults to the name of the modpack.
+        #[clap(long, short)]
+        name: Option<String>,
+        /// The directory to output mods to
+        #[clap(long, short)]
+        #[clap(value_hint(ValueHint::DirPath))]
+        output_dir: Option<PathBuf>,
+    },
     /// Show information about the current profile
     Info,
     /// List all the profiles with their data
@@ -323,3 +488,54 @@ impl std::fmt::Display for Platform {
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Coloured text for people to read
+    #[default]
+    Human,
+    /// JSON for scripts to read, with a schema version
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Human => write!(f, "human"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum ExportFormat {
+    /// A Modrinth modpack (`.mrpack`)
+    #[default]
+    Mrpack,
+    /// A Curseforge modpack (`.zip`)
+    #[clap(alias = "cf")]
+    Curseforge,
+    /// A packwiz pack directory
+    Packwiz,
+}
+
+impl ExportFormat {
+    /// The file extension of the format, or `None` if the format is a directory
+    pub const fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Mrpack => Some("mrpack"),
+            Self::Curseforge => Some("zip"),
+            Self::Packwiz => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mrpack => write!(f, "mrpack"),
+            Self::Curseforge => write!(f, "curseforge"),
+            Self::Packwiz => write!(f, "packwiz"),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{default_semaphore, SEMAPHORE, STYLE_BYTE, TICK};
+use crate::{cli::OutputFormat, default_semaphore, SEMAPHORE, STYLE_BYTE, TICK};
 use anyhow::{anyhow, bail, Error, Result};
 use colored::Colorize as _;
 use fs_extra::{
@@ -6,31 +6,46 @@ use fs_extra::{
     file::{move_file, CopyOptions as FileCopyOptions},
 };
 use indicatif::ProgressBar;
-use libium::{iter_ext::IterExt as _, upgrade::DownloadData};
+use libium::{
+    cache,
+    iter_ext::IterExt as _,
+    lockfile::LOCKFILE_NAME,
+    upgrade::{validator_path, DownloadData},
+};
 use parking_lot::Mutex;
 use std::{
     ffi::OsString,
-    fs::{copy, create_dir_all, read_dir, remove_file},
+    fs::{copy, create_dir_all, read_dir, remove_file, rename},
     path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
 use tokio::task::JoinSet;
 
-/// Check the given `directory`
+/// The changes [`clean`] makes to a directory
+#[derive(Debug, Default, Clone)]
+pub struct Stale {
+    /// The files that are moved to the `.old` folder
+    pub old: Vec<PathBuf>,
+    /// The partial downloads that are deleted
+    pub partial: Vec<PathBuf>,
+}
+
+/// Find the files in `directory` that [`clean`] would change, without changing anything
 ///
-/// - If there are files there that are not in `to_download` or `to_install`, they will be moved to `directory`/.old
-/// - If a file in `to_download` or `to_install` is already there, it will be removed from the respective vector
-/// - If the file is a `.part` file or if the move failed, the file will be deleted
-pub async fn clean(
+/// Files in `to_download` or `to_install` that are already there are removed from the respective vector,
+/// as are duplicate files in `to_download`.
+/// Other files are only moved to the `.old` folder if `owned` returns true for their filename.
+pub fn find_stale(
     directory: &Path,
     to_download: &mut Vec<DownloadData>,
     to_install: &mut Vec<(OsString, PathBuf)>,
-) -> Result<()> {
+    owned: impl Fn(&str) -> bool,
+    output: OutputFormat,
+) -> Result<Stale> {
     let dupes = find_dupes_by_key(to_download, DownloadData::filename);
     if !dupes.is_empty() {
-        println!(
-            "{}",
+        output.println(
             format!(
                 "WARNING: {} duplicate files were found {}. Remove the mod it belongs to",
                 dupes.len(),
@@ -40,10 +55,13 @@ pub async fn clean(
                     .display(", ")
             )
             .yellow()
-            .bold()
+            .bold(),
         );
     }
-    create_dir_all(directory.join(".old"))?;
+    let mut stale = Stale::default();
+    if !directory.is_dir() {
+        return Ok(stale);
+    }
     for file in read_dir(directory)? {
         let file = file?;
         // If it's a file
@@ -51,28 +69,85 @@ pub async fn clean(
             let filename = file.file_name();
             let filename = filename.to_string_lossy();
             let filename = filename.as_ref();
+            if filename == LOCKFILE_NAME {
+                continue;
+            }
             // If it is already downloaded
             if let Some(index) = to_download
                 .iter()
                 .position(|thing| filename == thing.filename())
             {
-                // Don't download it
-                to_download.remove(index);
+                let downloadable = &to_download[index];
+                // Local files can change without being renamed, so they are replaced if their contents changed
+                if downloadable.download_url.scheme() == "file"
+                    && downloadable.hashes.verify_file(file.path()).is_err()
+                {
+                    stale.old.push(file.path());
+                } else {
+                    // Don't download it
+                    to_download.remove(index);
+                }
             // Likewise, if it is already installed
             } else if let Some(index) = to_install.iter().position(|thing| filename == thing.0) {
                 // Don't install it
                 to_install.remove(index);
-            // Or else, move the file to `directory`/.old
-            // If the file is a `.part` file or if the move failed, delete the file
-            } else if filename.ends_with("part")
-                || move_file(
-                    file.path(),
-                    directory.join(".old").join(filename),
-                    &FileCopyOptions::new(),
-                )
-                .is_err()
+            // If it is a partial download, delete it unless it can be resumed
+            } else if filename.ends_with("part") {
+                if !to_download.iter().any(|thing| {
+                    let path = PathBuf::from(thing.filename());
+                    path.with_extension("part") == Path::new(filename)
+                        || validator_path(&path) == Path::new(filename)
+                }) {
+                    stale.partial.push(file.path());
+                }
+            // Or else, move the file to `directory`/.old if it was installed by ferium
+            } else if owned(filename) {
+                stale.old.push(file.path());
+            }
+        }
+    }
+    Ok(stale)
+}
+
+/// Check the given `directory`
+///
+/// - If there are files there that are not in `to_download` or `to_install`, they will be moved to `directory`/.old
+/// - If a file in `to_download` or `to_install` is already there, it will be removed from the respective vector
+/// - If the file is a `.part` file of a file in `to_download`, or its validator, it is kept so that the download can be resumed
+/// - If the file is any other `.part` file or if the move failed, the file will be deleted
+/// - The profile's lockfile is left untouched
+pub async fn clean(
+    directory: &Path,
+    to_download: &mut Vec<DownloadData>,
+    to_install: &mut Vec<(OsString, PathBuf)>,
+) -> Result<()> {
+    let stale = find_stale(
+        directory,
+        to_download,
+        to_install,
+        |_| true,
+        OutputFormat::Human,
+    )?;
+    remove_stale(directory, stale)
+}
+
+/// Move the `stale` files of `directory` to `directory`/.old, and delete the partial downloads
+pub fn remove_stale(directory: &Path, stale: Stale) -> Result<()> {
+    create_dir_all(directory.join(".old"))?;
+    for path in stale.partial {
+        remove_file(path)?;
+    }
+    for path in stale.old {
+        // If the move failed, delete the file
+        if let Some(filename) = path.file_name() {
+            if move_file(
+                &path,
+                directory.join(".old").join(filename),
+                &FileCopyOptions::new(),
+            )
+            .is_err()
             {
-                remove_file(file.path())?;
+                remove_file(path)?;
             }
         }
     }
@@ -92,10 +167,14 @@ pub fn read_overrides(directory: &Path) -> Result<Vec<(OsString, PathBuf)>> {
 }
 
 /// Download and install the files in `to_download` and `to_install` to `output_dir`
+///
+/// Files that are in the download cache are taken from there instead of being downloaded,
+/// unless they don't match their hashes anymore, and files that are downloaded are added to the cache.
 pub async fn download(
     output_dir: PathBuf,
     to_download: Vec<DownloadData>,
     to_install: Vec<(OsString, PathBuf)>,
+    output: OutputFormat,
 ) -> Result<()> {
     let progress_bar = Arc::new(Mutex::new(
         ProgressBar::new(
@@ -120,13 +199,26 @@ pub async fn download(
         tasks.spawn(async move {
             let _permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
 
-            let (length, filename) = downloadable
-                .download(client, &output_dir, |additional| {
-                    progress_bar.lock().inc(additional as u64);
-                })
-                .await?;
+            let out_file_path = output_dir.join(&downloadable.output);
+            let hashes = downloadable.hashes.clone();
+            let (action, (length, filename)) = if let Some(cached) = cache::get_verified(&hashes)? {
+                if let Some(up_dir) = out_file_path.parent() {
+                    create_dir_all(up_dir)?;
+                }
+                cache::link_or_copy(cached, &out_file_path)?;
+                progress_bar.lock().inc(downloadable.length as u64);
+                ("From cache", (downloadable.length, downloadable.filename()))
+            } else {
+                let downloaded = downloadable
+                    .download(client, &output_dir, |additional| {
+                        progress_bar.lock().inc(additional as u64);
+                    })
+                    .await?;
+                cache::insert(&hashes, &out_file_path)?;
+                ("Downloaded", downloaded)
+            };
             progress_bar.lock().println(format!(
-                "{} Downloaded  {:>7}  {}",
+                "{} {action}  {:>7}  {}",
                 &*TICK,
                 size::Size::from_bytes(length)
                     .format()
@@ -146,7 +238,11 @@ pub async fn download(
         .finish_and_clear();
     for (name, path) in to_install {
         if path.is_file() {
-            copy(path, output_dir.join(&name))?;
+            // Copy through a temporary file, so that the files hardlinked into snapshots aren't written to
+            let out_file_path = output_dir.join(&name);
+            let temp_file_path = out_file_path.with_extension("part");
+            copy(path, &temp_file_path)?;
+            rename(temp_file_path, out_file_path)?;
         } else if path.is_dir() {
             let mut copy_options = DirCopyOptions::new();
             copy_options.overwrite = true;
@@ -154,11 +250,11 @@ pub async fn download(
         } else {
             bail!("Could not determine whether installable is a file or folder")
         }
-        println!(
+        output.println(format!(
             "{} Installed          {}",
             &*TICK,
             name.to_string_lossy().dimmed()
-        );
+        ));
     }
 
     Ok(())
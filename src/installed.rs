@@ -0,0 +1,123 @@
+use anyhow::Result;
+use clap::ValueEnum as _;
+use libium::{
+    config::{
+        filters::ReleaseChannel,
+        structs::{ModIdentifier, Profile, ProjectType},
+    },
+    lockfile::{read_lockfile, Lockfile},
+    scan::{hash_files, identify, Identified},
+    upgrade::DownloadData,
+};
+use std::path::PathBuf;
+
+/// A file of a project that is in its install directory
+pub struct Installed {
+    /// The identifier of the project pinned to this file, if it is known
+    pub identifier: Option<ModIdentifier>,
+    pub filename: String,
+    pub channel: Option<ReleaseChannel>,
+}
+
+impl Installed {
+    /// Whether this is the same file as `file`
+    pub fn is(&self, file: &DownloadData) -> bool {
+        self.filename == file.filename()
+            || self
+                .identifier
+                .as_ref()
+                .is_some_and(|identifier| Some(identifier) == file.identifier.as_ref())
+    }
+}
+
+/// The files in the install directories of a profile
+pub struct InstalledFiles {
+    identified: Vec<Identified>,
+    lockfile: Lockfile,
+    output_dir: PathBuf,
+}
+
+impl InstalledFiles {
+    /// Identify the files in the install directories of the project types in `profile`,
+    /// using their hashes on Modrinth and Curseforge
+    pub async fn read(profile: &Profile) -> Result<Self> {
+        let mut hashed = Vec::new();
+        for project_type in ProjectType::value_variants()
+            .iter()
+            .filter(|&&project_type| {
+                profile
+                    .mods
+                    .iter()
+                    .any(|mod_| mod_.project_type == project_type)
+            })
+        {
+            let directory = project_type.install_dir(&profile.output_dir);
+            if directory.is_dir() {
+                hashed.extend(hash_files(
+                    directory,
+                    if project_type.is_mod() {
+                        &["jar"]
+                    } else {
+                        &["zip"]
+                    },
+                )?);
+            }
+        }
+        Ok(Self {
+            identified: identify(hashed).await?,
+            lockfile: read_lockfile(&profile.output_dir)?.unwrap_or_default(),
+            output_dir: profile.output_dir.clone(),
+        })
+    }
+
+    /// Get the installed file of the project of `identifier`
+    ///
+    /// Files that aren't on Modrinth or Curseforge, such as release assets from GitHub and other forges, Maven artifacts, and local files, are found using the profile's lockfile.
+    pub fn get(&self, identifier: &ModIdentifier, project_type: ProjectType) -> Option<Installed> {
+        self.identified
+            .iter()
+            .find_map(|identified| match identifier {
+                ModIdentifier::ModrinthProject(id, _) => {
+                    let version = identified.modrinth.as_ref()?;
+                    (&version.project_id == id).then(|| Installed {
+                        identifier: Some(ModIdentifier::ModrinthProject(
+                            id.clone(),
+                            Some(version.id.clone()),
+                        )),
+                        filename: identified.file.filename.clone(),
+                        channel: Some(version.version_type.into()),
+                    })
+                }
+                ModIdentifier::CurseForgeProject(id, _) => {
+                    let file = identified.curseforge.as_ref()?;
+                    (file.mod_id == *id).then(|| Installed {
+                        identifier: Some(ModIdentifier::CurseForgeProject(*id, Some(file.id))),
+                        filename: identified.file.filename.clone(),
+                        channel: Some(file.release_type.into()),
+                    })
+                }
+                ModIdentifier::GitHubRepository(..)
+                | ModIdentifier::MavenArtifact(..)
+                | ModIdentifier::ForgeRepository(..)
+                | ModIdentifier::DirectUrl(..)
+                | ModIdentifier::LocalPath(_) => None,
+            })
+            .or_else(|| {
+                self.lockfile
+                    .mods
+                    .iter()
+                    .find(|locked| {
+                        locked.identifier.is_same_as(identifier)
+                            && project_type
+                                .install_dir(&self.output_dir)
+                                .join(&locked.filename)
+                                .is_file()
+                    })
+                    .map(|locked| Installed {
+                        identifier: Some(locked.identifier.clone()),
+                        filename: locked.filename.clone(),
+                        channel: None,
+                    })
+            })
+    }
+}
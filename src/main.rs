@@ -19,6 +19,8 @@ mod add;
 mod cli;
 mod download;
 mod file_picker;
+mod installed;
+mod output;
 mod subcommands;
 
 #[cfg(test)]
@@ -26,16 +28,18 @@ mod tests;
 
 use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use clap::{CommandFactory, Parser};
-use cli::{Ferium, ModpackSubCommands, ProfileSubCommands, SubCommands};
+use cli::{CacheSubCommands, Ferium, ModpackSubCommands, ProfileSubCommands, SubCommands};
 use colored::{ColoredString, Colorize};
 use indicatif::ProgressStyle;
 use libium::{
     config::{
         self,
         filters::ProfileParameters as _,
-        structs::{Config, ModIdentifier, Modpack, Profile},
+        structs::{Config, Forge, MavenArtifact, ModIdentifier, Modpack, Profile},
     },
+    forge,
     iter_ext::IterExt as _,
+    upgrade::Hashes,
 };
 use std::{
     env::{set_var, var_os},
@@ -81,6 +85,7 @@ fn main() -> ExitCode {
     }
 
     let cli = Ferium::parse();
+    let output_format = cli.output_format;
 
     let mut builder = tokio::runtime::Builder::new_multi_thread();
     builder.enable_all();
@@ -92,6 +97,10 @@ fn main() -> ExitCode {
     let runtime = builder.build().expect("Could not initialise Tokio runtime");
 
     if let Err(err) = runtime.block_on(actual_main(cli)) {
+        if output_format.is_json() {
+            #[expect(clippy::expect_used)]
+            output::error(&err).expect("Could not print the error as JSON");
+        }
         if !err.to_string().is_empty() {
             eprintln!("{}", err.to_string().red().bold());
             if err
@@ -109,6 +118,11 @@ fn main() -> ExitCode {
                         .yellow()
                         .bold()
                 );
+                eprintln!(
+                    "{}",
+                    "To install mods that have already been downloaded, use `ferium upgrade --offline`"
+                        .yellow()
+                );
             }
         }
         ExitCode::FAILURE
@@ -147,6 +161,9 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
             set_var("GITHUB_TOKEN", token);
         }
     }
+    if !cli_app.forge_tokens.is_empty() {
+        set_var("FORGE_TOKENS", cli_app.forge_tokens.join(","));
+    }
     if let Some(key) = cli_app.curseforge_api_key {
         if !key.is_empty() {
             set_var("CURSEFORGE_API_KEY", key);
@@ -181,14 +198,41 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
     }
 
     let mut config = config::read_config(config_path)?;
+    let output = cli_app.output_format;
 
     let mut did_add_fail = false;
+    // Errors of commands that may have changed the config before failing, which is saved before returning them
+    let mut deferred_error = None;
 
     // Run function(s) based on the sub(sub)command to be executed
     match cli_app.subcommand {
         SubCommands::Complete { .. } | SubCommands::Profiles | SubCommands::Modpacks => {
             unreachable!();
         }
+        SubCommands::Cache { subcommand } => {
+            let mut default_flag = false;
+            let subcommand = subcommand.unwrap_or_else(|| {
+                default_flag = true;
+                CacheSubCommands::Info
+            });
+            match subcommand {
+                CacheSubCommands::Info => subcommands::cache::info()?,
+                CacheSubCommands::Verify => subcommands::cache::verify()?,
+                CacheSubCommands::Prune { all } => subcommands::cache::prune(&config, all)?,
+            }
+            if default_flag {
+                output.println(format!(
+                    "{} ferium cache help {}",
+                    "Use".yellow(),
+                    "for more information about this subcommand".yellow()
+                ));
+            }
+        }
+        SubCommands::Changelog { mod_name } => {
+            let profile = get_active_profile(&mut config)?;
+            check_empty_profile(profile)?;
+            subcommands::changelog(profile, mod_name).await?;
+        }
         SubCommands::Scan {
             platform,
             directory,
@@ -231,7 +275,7 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
             }
 
             let (successes, failures) =
-                libium::add(profile, send_ids, !force, false, vec![]).await?;
+                libium::add(profile, send_ids, !force, false, vec![], None, false).await?;
             spinner.finish_and_clear();
 
             did_add_fail = add::display_successes_failures(&successes, failures);
@@ -239,6 +283,10 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
         SubCommands::Add {
             identifiers,
             force,
+            environment,
+            optional_dependencies,
+            ignore_conflicts,
+            hash,
             filters,
         } => {
             let profile = get_active_profile(&mut config)?;
@@ -252,13 +300,42 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 "You can only configure filters when adding a single mod!"
             );
 
-            let identifiers = identifiers
-                .into_iter()
-                .map(libium::add::parse_id)
-                .collect::<libium::add::Result<Vec<_>>>()?;
+            ensure!(
+                hash.is_none() || identifiers.len() == 1,
+                "You can only provide a hash when adding a single file!"
+            );
 
-            let (successes, failures) =
-                libium::add(profile, identifiers, !force, override_profile, filters).await?;
+            let mut parsed = Vec::new();
+            for identifier in identifiers {
+                let mut identifier = libium::add::parse_id(identifier, &profile.filters).await?;
+                if let Some(hash) = &hash {
+                    let ModIdentifier::DirectUrl(_, hashes) = &mut identifier else {
+                        bail!("A hash can only be provided when adding a file from a URL");
+                    };
+                    *hashes = Hashes::from_hex(hash)
+                        .context("The hash should be a SHA-1 or SHA-512 hash in hexadecimal")?;
+                }
+                parsed.push(identifier);
+            }
+
+            let (successes, failures) = libium::add(
+                profile,
+                parsed,
+                !force,
+                override_profile,
+                filters,
+                environment,
+                ignore_conflicts,
+            )
+            .await?;
+            if optional_dependencies.is_some() {
+                for (_, id) in &successes {
+                    if let Some(mod_) = profile.mods.iter_mut().find(|mod_| &mod_.identifier == id)
+                    {
+                        mod_.optional_dependencies = optional_dependencies;
+                    }
+                }
+            }
 
             did_add_fail = add::display_successes_failures(&successes, failures);
         }
@@ -266,8 +343,20 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
             let profile = get_active_profile(&mut config)?;
             check_empty_profile(profile)?;
 
+            ensure!(
+                !(markdown && output.is_json()),
+                "The markdown flag can't be used with JSON output"
+            );
             if verbose {
-                subcommands::list::verbose(profile, markdown).await?;
+                subcommands::list::verbose(profile, markdown, output).await?;
+            } else if output.is_json() {
+                output::print(
+                    "mods",
+                    serde_json::json!({
+                        "profile": output::profile(profile, true),
+                        "mods": profile.mods.iter().map(output::mod_).collect_vec(),
+                    }),
+                )?;
             } else {
                 println!(
                     "{} {} on {} {}\n",
@@ -289,25 +378,84 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 );
                 for mod_ in &profile.mods {
                     println!(
-                        "{:20}  {}{}",
+                        "{:20}  {}{}{}{}",
                         match &mod_.identifier {
                             ModIdentifier::CurseForgeProject(id, _) =>
                                 format!("{} {:8}", "CF".red(), id.to_string().dimmed()),
                             ModIdentifier::ModrinthProject(id, _) =>
                                 format!("{} {:8}", "MR".green(), id.dimmed()),
                             ModIdentifier::GitHubRepository(..) => "GH".purple().to_string(),
+                            ModIdentifier::ForgeRepository(repository, _) => match repository.forge
+                            {
+                                Forge::GitLab => "GL".bright_red().to_string(),
+                                Forge::Gitea => "GT".bright_green().to_string(),
+                                Forge::Forgejo => "FJ".bright_yellow().to_string(),
+                            },
+                            ModIdentifier::MavenArtifact(..) => "MVN".blue().to_string(),
+                            ModIdentifier::DirectUrl(..) => "URL".cyan().to_string(),
+                            ModIdentifier::LocalPath(_) => "LOCAL".yellow().to_string(),
                         },
                         match &mod_.identifier {
                             ModIdentifier::ModrinthProject(..)
-                            | ModIdentifier::CurseForgeProject(..) => mod_.name.bold().to_string(),
+                            | ModIdentifier::CurseForgeProject(..)
+                            | ModIdentifier::DirectUrl(..)
+                            | ModIdentifier::LocalPath(_) => mod_.name.bold().to_string(),
                             ModIdentifier::GitHubRepository((owner, repo), _) =>
                                 format!("{}/{}", owner.dimmed(), repo.bold()),
+                            ModIdentifier::MavenArtifact(
+                                MavenArtifact {
+                                    group,
+                                    artifact,
+                                    classifier,
+                                    ..
+                                },
+                                _,
+                            ) => format!(
+                                "{}:{}{}",
+                                group.dimmed(),
+                                artifact.bold(),
+                                classifier
+                                    .as_ref()
+                                    .map(|classifier| format!(" ({classifier})")
+                                        .dimmed()
+                                        .to_string())
+                                    .unwrap_or_default()
+                            ),
+                            ModIdentifier::ForgeRepository(repository, _) => {
+                                let name = forge::display_name(repository);
+                                let (owner, repo) = name.rsplit_once('/').unwrap_or_default();
+                                format!("{}/{}", owner.dimmed(), repo.bold())
+                            }
+                        },
+                        if mod_.project_type.is_mod() {
+                            String::new()
+                        } else {
+                            format!(" ({})", mod_.project_type).cyan().to_string()
+                        },
+                        if mod_.is_dependency() {
+                            format!(
+                                " (dependency of {})",
+                                mod_.dependency_of
+                                    .iter()
+                                    .map(|parent| profile
+                                        .mods
+                                        .iter()
+                                        .find(|mod_| mod_.identifier.is_same_as(parent))
+                                        .map_or("an unknown mod", |mod_| mod_.name.as_str()))
+                                    .display(", ")
+                            )
+                            .dimmed()
+                            .to_string()
+                        } else {
+                            String::new()
                         },
                         match &mod_.identifier {
                             ModIdentifier::CurseForgeProject(_, Some(pin)) =>
                                 format!("\n   📌 {}", pin.to_string().dimmed()),
                             ModIdentifier::ModrinthProject(_, Some(pin))
-                            | ModIdentifier::GitHubRepository(_, Some(pin)) =>
+                            | ModIdentifier::GitHubRepository(_, Some(pin))
+                            | ModIdentifier::MavenArtifact(_, Some(pin))
+                            | ModIdentifier::ForgeRepository(_, Some(pin)) =>
                                 format!("\n   📌 {}", pin.dimmed()),
                             _ => String::new(),
                         },
@@ -369,11 +517,30 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                     subcommands::modpack::delete(&mut config, modpack_name, switch_to)?;
                 }
                 ModpackSubCommands::Info => {
-                    subcommands::modpack::info(get_active_modpack(&mut config)?, true);
+                    let modpack = get_active_modpack(&mut config)?;
+                    if output.is_json() {
+                        output::print("modpack", output::modpack(modpack, true))?;
+                    } else {
+                        subcommands::modpack::info(modpack, true);
+                    }
                 }
                 ModpackSubCommands::List => {
-                    for (i, modpack) in config.modpacks.iter().enumerate() {
-                        subcommands::modpack::info(modpack, i == config.active_modpack);
+                    if output.is_json() {
+                        output::print(
+                            "modpacks",
+                            config
+                                .modpacks
+                                .iter()
+                                .enumerate()
+                                .map(|(i, modpack)| {
+                                    output::modpack(modpack, i == config.active_modpack)
+                                })
+                                .collect(),
+                        )?;
+                    } else {
+                        for (i, modpack) in config.modpacks.iter().enumerate() {
+                            subcommands::modpack::info(modpack, i == config.active_modpack);
+                        }
                     }
                 }
                 ModpackSubCommands::Switch { modpack_name } => {
@@ -384,11 +551,11 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 }
             }
             if default_flag {
-                println!(
+                output.println(format!(
                     "{} ferium modpack help {}",
                     "Use".yellow(),
                     "for more information about this subcommand".yellow()
-                );
+                ));
             }
         }
         SubCommands::Profile { subcommand } => {
@@ -403,6 +570,8 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                     mod_loaders,
                     name,
                     output_dir,
+                    environment,
+                    optional_dependencies,
                 } => {
                     subcommands::profile::configure(
                         get_active_profile(&mut config)?,
@@ -410,6 +579,8 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                         mod_loaders,
                         name,
                         output_dir,
+                        environment,
+                        optional_dependencies,
                     )
                     .await?;
                 }
@@ -419,6 +590,8 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                     mod_loader,
                     name,
                     output_dir,
+                    environment,
+                    optional_dependencies,
                 } => {
                     subcommands::profile::create(
                         &mut config,
@@ -431,6 +604,8 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                         mod_loader,
                         name,
                         output_dir,
+                        environment,
+                        optional_dependencies,
                     )
                     .await?;
                 }
@@ -440,13 +615,50 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 } => {
                     subcommands::profile::delete(&mut config, profile_name, switch_to)?;
                 }
+                ProfileSubCommands::Export {
+                    format,
+                    file,
+                    pack_version,
+                    force,
+                } => {
+                    let profile = get_active_profile(&mut config)?;
+                    check_empty_profile(profile)?;
+                    subcommands::profile::export(profile, format, file, pack_version, force)
+                        .await?;
+                }
+                ProfileSubCommands::Import {
+                    source,
+                    name,
+                    output_dir,
+                } => {
+                    subcommands::profile::import(&mut config, source, name, output_dir).await?;
+                }
                 ProfileSubCommands::Info => {
-                    subcommands::profile::info(get_active_profile(&mut config)?, true);
+                    let profile = get_active_profile(&mut config)?;
+                    if output.is_json() {
+                        output::print("profile", output::profile(profile, true))?;
+                    } else {
+                        subcommands::profile::info(profile, true);
+                    }
                 }
 
                 ProfileSubCommands::List => {
-                    for (i, profile) in config.profiles.iter().enumerate() {
-                        subcommands::profile::info(profile, i == config.active_profile);
+                    if output.is_json() {
+                        output::print(
+                            "profiles",
+                            config
+                                .profiles
+                                .iter()
+                                .enumerate()
+                                .map(|(i, profile)| {
+                                    output::profile(profile, i == config.active_profile)
+                                })
+                                .collect(),
+                        )?;
+                    } else {
+                        for (i, profile) in config.profiles.iter().enumerate() {
+                            subcommands::profile::info(profile, i == config.active_profile);
+                        }
                     }
                 }
 
@@ -455,22 +667,57 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 }
             }
             if default_flag {
-                println!(
+                output.println(format!(
                     "{} ferium profile help {}",
                     "Use".yellow(),
                     "for more information about this subcommand".yellow()
-                );
+                ));
             }
         }
+        SubCommands::Outdated => {
+            let profile = get_active_profile(&mut config)?;
+            check_empty_profile(profile)?;
+            subcommands::outdated(profile, output).await?;
+        }
         SubCommands::Remove { mod_names } => {
             let profile = get_active_profile(&mut config)?;
             check_empty_profile(profile)?;
             subcommands::remove(profile, mod_names)?;
         }
-        SubCommands::Upgrade => {
+        SubCommands::Rollback { to, list } => {
+            let profile = get_active_profile(&mut config)?;
+            if list {
+                subcommands::rollback::list(profile, output)?;
+            } else {
+                subcommands::rollback::rollback(profile, to, output)?;
+            }
+        }
+        SubCommands::Search {
+            query,
+            add,
+            platform,
+        } => {
+            let profile = get_active_profile(&mut config)?;
+            did_add_fail = subcommands::search(profile, &query, add, platform, output).await?;
+        }
+        SubCommands::Update => {
+            let profile = get_active_profile(&mut config)?;
+            check_empty_profile(profile)?;
+            // The lockfile is written and the dependencies are recorded in the profile even if some mods failed
+            deferred_error = subcommands::update(profile, output).await.err();
+        }
+        SubCommands::Upgrade {
+            offline,
+            dry_run,
+            changelogs,
+            yes,
+        } => {
             let profile = get_active_profile(&mut config)?;
             check_empty_profile(profile)?;
-            subcommands::upgrade(profile).await?;
+            deferred_error =
+                subcommands::upgrade(profile, offline, dry_run, changelogs, yes, output)
+                    .await
+                    .err();
         }
     }
 
@@ -482,7 +729,9 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
     // Update config file with possibly edited config
     config::write_config(config_path, &config)?;
 
-    if did_add_fail {
+    if let Some(err) = deferred_error {
+        Err(err)
+    } else if did_add_fail {
         Err(anyhow!(""))
     } else {
         Ok(())
@@ -0,0 +1,221 @@
+//! The JSON printed with `--output json`
+//!
+//! Every result is printed as an object with the `schema_version`, the `kind` of result, and the `data`.
+//! Fields may be added to the data without changing the schema version,
+//! but it is incremented when fields are changed or removed.
+
+use crate::cli::OutputFormat;
+use anyhow::Result;
+use clap::ValueEnum;
+use libium::{
+    config::{
+        filters::{Filter, ProfileParameters as _},
+        structs::{Forge, Mod, ModIdentifier, Modpack, ModpackIdentifier, Profile},
+    },
+    forge,
+    iter_ext::IterExt as _,
+    lockfile::LockedMod,
+    maven,
+};
+use serde_json::{json, Value};
+use std::{
+    fmt::Display,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Whether a result has been printed
+static PRINTED: AtomicBool = AtomicBool::new(false);
+
+impl OutputFormat {
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+
+    /// Print a line of human readable output, to stderr if stdout is used for JSON
+    pub fn println(self, line: impl Display) {
+        match self {
+            Self::Human => println!("{line}"),
+            Self::Json => eprintln!("{line}"),
+        }
+    }
+}
+
+/// Print `data` as the result of `kind`
+pub fn print(kind: &str, data: Value) -> Result<()> {
+    let mut result = json!({
+        "schema_version": SCHEMA_VERSION,
+        "kind": kind,
+    });
+    result["data"] = data;
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    PRINTED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Print `err` as an `error` result, unless the command already printed its result
+///
+/// Commands can fail before printing anything, e.g. when the lockfile can't be read,
+/// so scripts still get a result to read.
+pub fn error(err: &anyhow::Error) -> Result<()> {
+    if PRINTED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    print("error", json!({ "message": err.to_string().trim() }))
+}
+
+/// The name of `value` as it is written on the command line
+pub fn value_name(value: &impl ValueEnum) -> Option<String> {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_owned())
+}
+
+/// The platform and project ID or repository of `identifier`
+pub fn platform_and_project(identifier: &ModIdentifier) -> (&'static str, String) {
+    match identifier {
+        ModIdentifier::CurseForgeProject(id, _) => ("curseforge", id.to_string()),
+        ModIdentifier::ModrinthProject(id, _) => ("modrinth", id.clone()),
+        ModIdentifier::GitHubRepository((owner, repo), _) => ("github", format!("{owner}/{repo}")),
+        ModIdentifier::MavenArtifact(artifact, _) => (
+            "maven",
+            format!(
+                "{}/{}",
+                artifact.repository.as_str().trim_end_matches('/'),
+                maven::display_name(artifact)
+            ),
+        ),
+        ModIdentifier::ForgeRepository(repository, _) => (
+            match repository.forge {
+                Forge::GitLab => "gitlab",
+                Forge::Gitea => "gitea",
+                Forge::Forgejo => "forgejo",
+            },
+            forge::repository_url(repository)
+                .map_or_else(|_| forge::display_name(repository), |url| url.to_string()),
+        ),
+        ModIdentifier::DirectUrl(url, _) => ("url", url.to_string()),
+        ModIdentifier::LocalPath(path) => ("local", path.display().to_string()),
+    }
+}
+
+/// The platform, project, and pinned file of `identifier`
+pub fn identifier(identifier: &ModIdentifier) -> Value {
+    let (platform, project) = platform_and_project(identifier);
+    json!({
+        "platform": platform,
+        "project": project,
+        "pin": match identifier {
+            ModIdentifier::CurseForgeProject(_, pin) => pin.map(|pin| pin.to_string()),
+            ModIdentifier::ModrinthProject(_, pin)
+            | ModIdentifier::GitHubRepository(_, pin)
+            | ModIdentifier::MavenArtifact(_, pin)
+            | ModIdentifier::ForgeRepository(_, pin) => pin.clone(),
+            ModIdentifier::DirectUrl(..) | ModIdentifier::LocalPath(_) => None,
+        },
+    })
+}
+
+/// The kind of `filter` and the values it selects files with
+pub fn filter(filter: &Filter) -> Value {
+    match filter {
+        Filter::ModLoaderPrefer(loaders) => json!({
+            "type": "mod_loader_prefer",
+            "mod_loaders": loaders.iter().filter_map(value_name).collect_vec(),
+        }),
+        Filter::ModLoaderAny(loaders) => json!({
+            "type": "mod_loader_any",
+            "mod_loaders": loaders.iter().filter_map(value_name).collect_vec(),
+        }),
+        Filter::GameVersionStrict(versions) => json!({
+            "type": "game_version_strict",
+            "game_versions": versions,
+        }),
+        Filter::GameVersionMinor(versions) => json!({
+            "type": "game_version_minor",
+            "game_versions": versions,
+        }),
+        Filter::ReleaseChannel(channel) => json!({
+            "type": "release_channel",
+            "release_channel": value_name(channel),
+        }),
+        Filter::Filename(regex) => json!({ "type": "filename", "regex": regex }),
+        Filter::Title(regex) => json!({ "type": "title", "regex": regex }),
+        Filter::Description(regex) => json!({ "type": "description", "regex": regex }),
+    }
+}
+
+pub fn mod_(mod_: &Mod) -> Value {
+    json!({
+        "name": mod_.name,
+        "slug": mod_.slug,
+        "identifier": identifier(&mod_.identifier),
+        "project_type": value_name(&mod_.project_type),
+        "filters": mod_.filters.iter().map(filter).collect_vec(),
+        "override_filters": mod_.override_filters,
+        "environment": mod_.environment.as_ref().and_then(value_name),
+        "dependency_of": mod_.dependency_of.iter().map(identifier).collect_vec(),
+    })
+}
+
+pub fn profile(profile: &Profile, active: bool) -> Value {
+    json!({
+        "name": profile.name,
+        "active": active,
+        "output_dir": profile.output_dir,
+        "game_versions": profile.filters.game_versions(),
+        "mod_loader": profile.filters.mod_loader().and_then(value_name),
+        "environment": value_name(&profile.environment),
+        "optional_dependencies": value_name(&profile.optional_dependencies),
+        "filters": profile.filters.iter().map(filter).collect_vec(),
+        "mod_count": profile.mods.len(),
+    })
+}
+
+pub fn modpack(modpack: &Modpack, active: bool) -> Value {
+    let (platform, project) = match &modpack.identifier {
+        ModpackIdentifier::CurseForgeModpack(id) => ("curseforge", id.to_string()),
+        ModpackIdentifier::ModrinthModpack(id) => ("modrinth", id.clone()),
+    };
+    json!({
+        "name": modpack.name,
+        "active": active,
+        "output_dir": modpack.output_dir,
+        "platform": platform,
+        "project": project,
+        "install_overrides": modpack.install_overrides,
+    })
+}
+
+/// A resolved file, with the `name` of the mod it belongs to
+pub fn locked(locked: &LockedMod, name: &str) -> Value {
+    json!({
+        "name": name,
+        "identifier": identifier(&locked.identifier),
+        "project_type": value_name(&locked.project_type),
+        "filename": locked.filename,
+        "url": locked.download_url,
+        "size": locked.length,
+        "dependency_of": locked.dependency_of.iter().map(identifier).collect_vec(),
+    })
+}
+
+/// A mod that could not be resolved or installed
+pub fn failure(name: &str, id: &ModIdentifier, error: impl Display) -> Value {
+    json!({
+        "name": name,
+        "identifier": identifier(id),
+        "error": error.to_string(),
+    })
+}
+
+/// The filenames of `paths`
+pub fn filenames<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> Vec<String> {
+    paths
+        .into_iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
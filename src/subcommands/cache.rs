@@ -0,0 +1,106 @@
+use crate::TICK;
+use anyhow::Result;
+use clap::ValueEnum as _;
+use colored::Colorize as _;
+use libium::{
+    cache,
+    config::structs::{Config, ProjectType},
+    iter_ext::IterExt as _,
+    lockfile::read_lockfile,
+    upgrade::Hashes,
+};
+use std::fs::read_dir;
+
+fn format_size(bytes: u64) -> String {
+    size::Size::from_bytes(bytes)
+        .format()
+        .with_base(size::Base::Base10)
+        .to_string()
+}
+
+/// Show the location of the download cache and how much space it uses
+pub fn info() -> Result<()> {
+    let entries = cache::entries()?;
+    println!(
+        "{}
+        \r  Location:  {}
+        \r  Files:     {}
+        \r  Size:      {}",
+        "Download Cache".bold(),
+        cache::store_dir().display().to_string().blue().underline(),
+        entries.len().to_string().yellow(),
+        format_size(entries.iter().map(|entry| entry.size).sum()).green(),
+    );
+    Ok(())
+}
+
+/// Check the files in the download cache against their hashes, and remove the ones that don't match
+pub fn verify() -> Result<()> {
+    let mut corrupted = 0;
+    for entry in cache::entries()? {
+        if !entry.verify()? {
+            println!(
+                "{} {}",
+                "Removing corrupted file".red(),
+                entry.hash.dimmed()
+            );
+            entry.remove()?;
+            corrupted += 1;
+        }
+    }
+    if corrupted == 0 {
+        println!("{} All files in the cache are intact", &*TICK);
+    } else {
+        println!(
+            "{}",
+            format!("Removed {corrupted} corrupted files from the cache").yellow()
+        );
+    }
+    Ok(())
+}
+
+/// Remove files from the download cache that are not locked by any profile or installed by any modpack,
+/// or all files if `all` is set
+pub fn prune(config: &Config, all: bool) -> Result<()> {
+    let mut locked = Vec::new();
+    if !all {
+        for profile in &config.profiles {
+            if let Some(lockfile) = read_lockfile(&profile.output_dir)? {
+                locked.extend(lockfile.mods.into_iter().map(|locked| locked.hashes));
+            }
+        }
+        // Modpacks don't have lockfiles, so the files they installed are hashed instead
+        for modpack in &config.modpacks {
+            for project_type in ProjectType::value_variants() {
+                let directory = modpack.output_dir.join(project_type.directory_name());
+                if !directory.is_dir() {
+                    continue;
+                }
+                for file in read_dir(directory)? {
+                    let file = file?;
+                    if file.file_type()?.is_file() {
+                        locked.push(Hashes::of_file(file.path())?);
+                    }
+                }
+            }
+        }
+    }
+
+    let (mut count, mut freed) = (0, 0);
+    for entry in cache::entries()?
+        .into_iter()
+        .filter(|entry| !locked.iter().any(|hashes| entry.matches(hashes)))
+        .collect_vec()
+    {
+        freed += entry.size;
+        entry.remove()?;
+        count += 1;
+    }
+    println!(
+        "{} Removed {} files, freeing {}",
+        &*TICK,
+        count.to_string().yellow(),
+        format_size(freed).green(),
+    );
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use super::{
+    outdated::{fetch_latest, Latest},
+    remove::is_named,
+};
+use crate::{
+    default_semaphore,
+    installed::{Installed, InstalledFiles},
+    CROSS, SEMAPHORE, TICK,
+};
+use anyhow::{anyhow, Context as _, Error, Result};
+use colored::Colorize as _;
+use libium::{
+    config::structs::{Mod, Profile},
+    iter_ext::IterExt as _,
+    upgrade::DownloadData,
+};
+use tokio::task::JoinSet;
+
+/// A project that changes from its installed file to a newer file
+pub(super) struct Change {
+    pub mod_: Mod,
+    pub installed: Option<Installed>,
+    pub file: DownloadData,
+}
+
+/// Print the changelogs of the files from the installed file to the new file of each of the `changes`
+///
+/// Returns whether the changelogs of some projects could not be fetched.
+pub(super) async fn print_changelogs(profile: &Profile, changes: Vec<Change>) -> Result<bool> {
+    let mut tasks = JoinSet::new();
+    for (index, change) in changes.iter().enumerate() {
+        let mod_ = change.mod_.clone();
+        let from = change
+            .installed
+            .as_ref()
+            .and_then(|installed| installed.identifier.clone());
+        let to = change
+            .file
+            .identifier
+            .clone()
+            .context("The file does not belong to a project")?;
+        let filters = profile.filters.clone();
+        tasks.spawn(async move {
+            let _permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
+            Ok::<_, Error>((
+                index,
+                mod_.fetch_changelogs(from.as_ref(), &to, filters).await,
+            ))
+        });
+    }
+    let mut changelogs = Vec::new();
+    for res in tasks.join_all().await {
+        changelogs.push(res?);
+    }
+    changelogs.sort_unstable_by_key(|(index, _)| *index);
+
+    let mut error = false;
+    for (change, (_, changelogs)) in changes.into_iter().zip(changelogs) {
+        println!(
+            "\n{}  {}",
+            change.mod_.name.bold(),
+            match &change.installed {
+                Some(installed) => format!("{} -> {}", installed.filename, change.file.filename()),
+                None => change.file.filename(),
+            }
+            .dimmed()
+        );
+        match changelogs {
+            Ok(changelogs) => {
+                for metadata in changelogs {
+                    println!(
+                        "\n  {} {}",
+                        metadata.title.yellow().bold(),
+                        format!("({})", metadata.channel.to_string().to_lowercase()).dimmed()
+                    );
+                    if metadata.description.trim().is_empty() {
+                        println!("    {}", "No changelog provided".dimmed());
+                    } else {
+                        for line in metadata.description.trim().lines() {
+                            println!("    {line}");
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error = true;
+                println!("  {}", format!("{CROSS} {err}").red());
+            }
+        }
+    }
+    Ok(error)
+}
+
+/// Print the changelogs of the files from the installed file to the latest compatible file of the mod named `mod_name`,
+/// or of every mod in `profile` that isn't up to date
+pub async fn changelog(profile: &Profile, mod_name: Option<String>) -> Result<()> {
+    let mods = match &mod_name {
+        Some(mod_name) => vec![profile
+            .mods
+            .iter()
+            .find(|mod_| is_named(mod_, mod_name))
+            .with_context(|| {
+                format!("A mod with ID or name {mod_name} is not present in this profile")
+            })?],
+        None => profile.mods.iter().collect_vec(),
+    };
+
+    eprint!("Identifying installed files... ");
+    let installed_files = InstalledFiles::read(profile).await?;
+    eprintln!("{}", &*TICK);
+    eprint!("Checking for updates... ");
+    let latest = fetch_latest(profile, mods.iter().copied()).await?;
+    eprintln!("{}", &*TICK);
+
+    let mut error = false;
+    let mut changes = Vec::new();
+    for (mod_, latest) in mods.into_iter().zip(latest) {
+        let installed = installed_files.get(&mod_.identifier, mod_.project_type);
+        match latest {
+            Latest::File(file) => {
+                if !installed
+                    .as_ref()
+                    .is_some_and(|installed| installed.is(&file))
+                {
+                    changes.push(Change {
+                        mod_: mod_.clone(),
+                        installed,
+                        file: *file,
+                    });
+                }
+            }
+            Latest::Unused => (),
+            Latest::Failed(reason) => {
+                error = true;
+                println!("{}", format!("{CROSS} {:20} {reason}", mod_.name).red());
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        match mod_name {
+            Some(_) if error => (),
+            Some(mod_name) => println!("\n{}", format!("{mod_name} is up to date!").bold()),
+            None => println!("\n{}", "All up to date!".bold()),
+        }
+    } else {
+        error |= print_changelogs(profile, changes).await?;
+    }
+
+    if error {
+        Err(anyhow!("\nCould not get the changelogs of some mods"))
+    } else {
+        Ok(())
+    }
+}
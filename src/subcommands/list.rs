@@ -1,20 +1,24 @@
-use crate::TICK;
+use crate::{cli::OutputFormat, output, TICK};
 use anyhow::{Context as _, Result};
 use colored::Colorize as _;
 use ferinth::structures::{project::Project, user::TeamMember};
 use furse::structures::mod_structs::Mod;
 use libium::{
-    config::structs::{ModIdentifier, Profile},
+    config::structs::{Forge, ModIdentifier, Profile},
+    forge,
     iter_ext::IterExt as _,
-    CURSEFORGE_API, GITHUB_API, MODRINTH_API,
+    maven, CURSEFORGE_API, GITHUB_API, MODRINTH_API,
 };
 use octocrab::models::{repos::Release, Repository};
+use serde_json::{json, Value};
 use tokio::task::JoinSet;
 
 enum ListData {
     CF(Box<Mod>),
     MD(Box<Project>, Vec<TeamMember>),
     GH(Box<Repository>, Vec<Release>),
+    /// A mod from another forge, a Maven repository, a URL, or a local path, with its name
+    File(ModIdentifier, String),
 }
 impl ListData {
     fn name(&self) -> &str {
@@ -22,6 +26,7 @@ impl ListData {
             ListData::CF(p) => &p.name,
             ListData::MD(p, _) => &p.title,
             ListData::GH(p, _) => &p.name,
+            ListData::File(_, name) => name,
         }
     }
 
@@ -34,6 +39,7 @@ impl ListData {
                 (p.owner.clone().unwrap().login, p.name.clone()),
                 None,
             ),
+            ListData::File(id, _) => id.clone(),
         }
     }
 
@@ -42,11 +48,63 @@ impl ListData {
             ListData::CF(p) => &p.slug,
             ListData::MD(p, _) => &p.slug,
             ListData::GH(p, _) => &p.name,
+            ListData::File(_, name) => name,
+        }
+    }
+
+    /// The metadata printed with `--output json`
+    #[expect(clippy::unwrap_used)]
+    fn json(&self) -> Value {
+        match self {
+            ListData::CF(p) => json!({
+                "title": p.name,
+                "summary": p.summary.trim(),
+                "url": p.links.website_url,
+                "source_url": p.links.source_url,
+                "downloads": p.download_count,
+                "authors": p.authors.iter().map(|author| &author.name).collect_vec(),
+                "categories": p.categories.iter().map(|category| &category.name).collect_vec(),
+                "license": Value::Null,
+            }),
+            ListData::MD(p, t) => json!({
+                "title": p.title,
+                "summary": p.description,
+                "url": format!("https://modrinth.com/mod/{}", p.slug),
+                "source_url": p.source_url,
+                "downloads": p.downloads,
+                "authors": t.iter().map(|member| &member.user.username).collect_vec(),
+                "categories": p.categories,
+                "license": if p.license.name.is_empty() { "Custom" } else { &p.license.name },
+            }),
+            ListData::GH(p, r) => json!({
+                "title": p.name,
+                "summary": p.description,
+                "url": p.html_url,
+                "source_url": p.html_url,
+                "downloads": r
+                    .iter()
+                    .flat_map(|release| &release.assets)
+                    .map(|asset| asset.download_count)
+                    .sum::<i64>(),
+                "authors": [&p.owner.as_ref().unwrap().login],
+                "categories": p.topics.clone().unwrap_or_default(),
+                "license": p.license.as_ref().map(|license| &license.name),
+            }),
+            ListData::File(id, name) => json!({
+                "title": name,
+                "summary": Value::Null,
+                "url": file_source(id).1,
+                "source_url": Value::Null,
+                "downloads": Value::Null,
+                "authors": [],
+                "categories": [],
+                "license": Value::Null,
+            }),
         }
     }
 }
 
-pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
+pub async fn verbose(profile: &mut Profile, markdown: bool, output: OutputFormat) -> Result<()> {
     if !markdown {
         eprint!("Querying metadata... ");
     }
@@ -54,6 +112,7 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
     let mut tasks = JoinSet::new();
     let mut mr_ids = Vec::new();
     let mut cf_ids = Vec::new();
+    let mut files = Vec::new();
     for mod_ in &profile.mods {
         match mod_.identifier.clone() {
             ModIdentifier::CurseForgeProject(project_id, _) => cf_ids.push(project_id),
@@ -67,6 +126,12 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
                     ))
                 });
             }
+            id @ (ModIdentifier::ForgeRepository(..)
+            | ModIdentifier::MavenArtifact(..)
+            | ModIdentifier::DirectUrl(..)
+            | ModIdentifier::LocalPath(_)) => {
+                files.push(ListData::File(id, mod_.name.clone()));
+            }
         }
     }
 
@@ -102,12 +167,14 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
         let (repo, releases) = res?;
         metadata.push(ListData::GH(Box::new(repo), releases.items));
     }
+    metadata.extend(files);
     metadata.sort_unstable_by_key(|e| e.name().to_lowercase());
 
     if !markdown {
-        println!("{}", &*TICK);
+        eprintln!("{}", &*TICK);
     }
 
+    let mut listed = Vec::new();
     for project in &metadata {
         let mod_ = profile
             .mods
@@ -118,20 +185,35 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
         mod_.name = project.name().to_string();
         mod_.slug = Some(project.slug().to_string());
 
-        if markdown {
+        if output.is_json() {
+            let mut json = output::mod_(mod_);
+            json["metadata"] = project.json();
+            listed.push(json);
+        } else if markdown {
             match project {
                 ListData::CF(p) => curseforge_md(p),
                 ListData::MD(p, t) => modrinth_md(p, t),
                 ListData::GH(p, _) => github_md(p),
+                ListData::File(id, name) => file_md(id, name),
             }
         } else {
             match project {
                 ListData::CF(p) => curseforge(p),
                 ListData::MD(p, t) => modrinth(p, t),
                 ListData::GH(p, r) => github(p, r),
+                ListData::File(id, name) => file(id, name),
             }
         }
     }
+    if output.is_json() {
+        output::print(
+            "mods",
+            json!({
+                "profile": output::profile(profile, true),
+                "mods": listed,
+            }),
+        )?;
+    }
 
     Ok(())
 }
@@ -372,3 +454,57 @@ pub fn github_md(repo: &Repository) {
         )),
     );
 }
+
+/// The source of a mod from another forge, a Maven repository, a URL, or a local path, and where it's from
+fn file_source(id: &ModIdentifier) -> (&'static str, String) {
+    match id {
+        ModIdentifier::ForgeRepository(repository, _) => {
+            return (
+                match repository.forge {
+                    Forge::GitLab => "GitLab Repository",
+                    Forge::Gitea => "Gitea Repository",
+                    Forge::Forgejo => "Forgejo Repository",
+                },
+                forge::repository_url(repository)
+                    .map_or_else(|_| forge::display_name(repository), |url| url.to_string()),
+            );
+        }
+        ModIdentifier::MavenArtifact(artifact, _) => {
+            return (
+                "Maven Repository",
+                maven::artifact_url(artifact, &[""])
+                    .map_or_else(|_| artifact.repository.to_string(), |url| url.to_string()),
+            );
+        }
+        _ => (),
+    }
+    match output::platform_and_project(id) {
+        ("url", url) => ("Direct Download", url),
+        (_, path) => ("Local File", path),
+    }
+}
+
+pub fn file(id: &ModIdentifier, name: &str) {
+    let (source, location) = file_source(id);
+    println!(
+        "
+{}\n
+  Link:         {}
+  Source:       {}",
+        name.bold(),
+        location.blue().underline(),
+        source.dimmed(),
+    );
+}
+
+pub fn file_md(id: &ModIdentifier, name: &str) {
+    let (source, location) = file_source(id);
+    println!(
+        "
+**{name}**
+
+|        |          |
+|--------|----------|
+| Source | {source} `{location}` |"
+    );
+}
@@ -1,7 +1,15 @@
+pub mod cache;
+mod changelog;
 pub mod list;
 pub mod modpack;
+mod outdated;
 pub mod profile;
 mod remove;
+pub mod rollback;
+mod search;
 mod upgrade;
+pub use changelog::changelog;
+pub use outdated::outdated;
 pub use remove::remove;
-pub use upgrade::upgrade;
+pub use search::search;
+pub use upgrade::{update, upgrade};
@@ -1,12 +1,15 @@
 use crate::{
+    cli::OutputFormat,
     download::{clean, download, read_overrides},
     STYLE_BYTE, TICK,
 };
 use anyhow::{Context as _, Result};
+use clap::ValueEnum as _;
 use colored::Colorize as _;
 use indicatif::ProgressBar;
 use libium::{
-    config::structs::{Modpack, ModpackIdentifier},
+    add::cf_project_type,
+    config::structs::{Modpack, ModpackIdentifier, ProjectType},
     iter_ext::IterExt as _,
     modpack::{
         curseforge::structs::Manifest as CFManifest, modrinth::structs::Metadata as MRMetadata,
@@ -15,12 +18,7 @@ use libium::{
     upgrade::{from_modpack_file, try_from_cf_file, DistributionDeniedError, DownloadData},
     CURSEFORGE_API,
 };
-use std::{
-    fs::File,
-    io::BufReader,
-    path::{Path, PathBuf},
-    time::Duration,
-};
+use std::{fs::File, io::BufReader, path::PathBuf, time::Duration};
 use tokio::task::JoinSet;
 
 pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
@@ -56,24 +54,24 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
 
             let file_ids = manifest.files.iter().map(|file| file.file_id).collect();
             let files = CURSEFORGE_API.get_files(file_ids).await?;
+            // The projects' categories determine which directory their files are installed to
+            let projects = CURSEFORGE_API
+                .get_mods(manifest.files.iter().map(|file| file.project_id).collect())
+                .await?;
             println!("{} Fetched {} mods", &*TICK, files.len());
 
             let mut tasks = JoinSet::new();
             let mut msg_shown = false;
             for file in files.into_iter().flatten() {
+                let project_type = projects
+                    .iter()
+                    .find(|project| project.id == file.mod_id)
+                    .and_then(cf_project_type)
+                    .unwrap_or_default();
                 match try_from_cf_file(file) {
                     Ok((_, mut downloadable)) => {
-                        downloadable.output = PathBuf::from(
-                            if Path::new(&downloadable.filename())
-                                .extension()
-                                .is_some_and(|ext| ext.eq_ignore_ascii_case(".zip"))
-                            {
-                                "resourcepacks"
-                            } else {
-                                "mods"
-                            },
-                        )
-                        .join(downloadable.filename());
+                        downloadable.output = PathBuf::from(project_type.directory_name())
+                            .join(downloadable.filename());
                         to_download.push(downloadable);
                     }
                     Err(DistributionDeniedError(mod_id, file_id)) => {
@@ -150,18 +148,26 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
             }
         }
     }
-    clean(
-        &modpack.output_dir.join("mods"),
-        &mut to_download,
-        &mut Vec::new(),
-    )
-    .await?;
-    clean(
-        &modpack.output_dir.join("resourcepacks"),
-        &mut to_download,
-        &mut Vec::new(),
-    )
-    .await?;
+    // The mods and resource packs directories are always cleaned, others only if the modpack has files for them
+    let directories = ProjectType::value_variants()
+        .iter()
+        .map(|project_type| project_type.directory_name())
+        .filter(|&directory| {
+            directory == ProjectType::Mod.directory_name()
+                || directory == ProjectType::ResourcePack.directory_name()
+                || to_download
+                    .iter()
+                    .any(|downloadable| downloadable.output.starts_with(directory))
+        })
+        .collect_vec();
+    for directory in directories {
+        clean(
+            &modpack.output_dir.join(directory),
+            &mut to_download,
+            &mut Vec::new(),
+        )
+        .await?;
+    }
     // TODO: Check for `to_install` files that are already installed
     if to_download.is_empty() && to_install.is_empty() {
         println!("\n{}", "All up to date!".bold());
@@ -170,7 +176,13 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
             "\n{}\n",
             format!("Downloading {} Mod Files", to_download.len()).bold()
         );
-        download(modpack.output_dir.clone(), to_download, to_install).await?;
+        download(
+            modpack.output_dir.clone(),
+            to_download,
+            to_install,
+            OutputFormat::Human,
+        )
+        .await?;
     }
     println!("\n{}", install_msg.bold());
     Ok(())
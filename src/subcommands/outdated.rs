@@ -0,0 +1,264 @@
+use crate::{
+    cli::OutputFormat,
+    default_semaphore,
+    installed::{Installed, InstalledFiles},
+    output, CROSS, SEMAPHORE, TICK,
+};
+use anyhow::{anyhow, Error, Result};
+use colored::Colorize as _;
+use libium::{
+    config::{
+        filters::ReleaseChannel,
+        structs::{Environment, Mod, ModIdentifier, Profile},
+    },
+    iter_ext::IterExt as _,
+    upgrade::{mod_downloadable, DownloadData},
+};
+use serde_json::{json, Value};
+use tokio::task::JoinSet;
+
+pub(super) enum Latest {
+    File(Box<DownloadData>),
+    /// The mod is not used in the profile's environment
+    Unused,
+    Failed(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    UpToDate,
+    Outdated,
+    NotInstalled,
+    Unused,
+    Failed,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::UpToDate => "up_to_date",
+            Self::Outdated => "outdated",
+            Self::NotInstalled => "not_installed",
+            Self::Unused => "unused",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+struct Report<'a> {
+    mod_: &'a Mod,
+    installed: Option<Installed>,
+    latest: Latest,
+}
+
+impl Report<'_> {
+    fn status(&self) -> Status {
+        match (&self.installed, &self.latest) {
+            (_, Latest::Failed(_)) => Status::Failed,
+            (_, Latest::Unused) => Status::Unused,
+            (None, Latest::File(_)) => Status::NotInstalled,
+            (Some(installed), Latest::File(latest)) => {
+                if installed.is(latest) {
+                    Status::UpToDate
+                } else {
+                    Status::Outdated
+                }
+            }
+        }
+    }
+
+    fn is_pinned(&self) -> bool {
+        matches!(
+            self.mod_.identifier,
+            ModIdentifier::CurseForgeProject(_, Some(_))
+                | ModIdentifier::ModrinthProject(_, Some(_))
+                | ModIdentifier::GitHubRepository(_, Some(_))
+                | ModIdentifier::MavenArtifact(_, Some(_))
+                | ModIdentifier::ForgeRepository(_, Some(_))
+        )
+    }
+}
+
+fn format_file(filename: &str, channel: Option<ReleaseChannel>) -> String {
+    match channel {
+        Some(channel) => format!("{filename} ({})", channel.to_string().to_lowercase()),
+        None => filename.to_owned(),
+    }
+}
+
+fn file_json(filename: &str, channel: Option<ReleaseChannel>) -> Value {
+    json!({
+        "filename": filename,
+        "channel": channel.map(|channel| channel.to_string().to_lowercase()),
+    })
+}
+
+/// Get the latest compatible file of each mod in `to_check`, using the filters of `profile` and ignoring the mods' pins
+pub(super) async fn fetch_latest(
+    profile: &Profile,
+    to_check: impl Iterator<Item = &Mod>,
+) -> Result<Vec<Latest>> {
+    let mut to_check = to_check
+        .map(|mod_| {
+            let mut mod_ = mod_.clone();
+            mod_.identifier = mod_.identifier.unpinned();
+            mod_
+        })
+        .collect_vec();
+    if profile.environment != Environment::Both {
+        mod_downloadable::fetch_environments(&mut to_check).await?;
+    }
+    let mut tasks = JoinSet::new();
+    for (index, mod_) in to_check.into_iter().enumerate() {
+        let filters = profile.filters.clone();
+        let environment = profile.environment;
+        tasks.spawn(async move {
+            let _permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
+            Ok::<_, Error>((
+                index,
+                match mod_.fetch_download_file(filters, environment).await {
+                    Ok(Some(latest)) => Latest::File(Box::new(latest)),
+                    Ok(None) => Latest::Unused,
+                    Err(err) => Latest::Failed(err.to_string()),
+                },
+            ))
+        });
+    }
+    let mut latest = Vec::new();
+    for res in tasks.join_all().await {
+        latest.push(res?);
+    }
+    latest.sort_unstable_by_key(|(index, _)| *index);
+    Ok(latest.into_iter().map(|(_, latest)| latest).collect_vec())
+}
+
+/// Compare the installed file of each mod in `profile` with the latest compatible file, without changing anything
+///
+/// Installed files are identified by their hashes on Modrinth and Curseforge, or else by the profile's lockfile.
+/// Pinned mods are compared with the latest file they would have if they weren't pinned.
+/// With `--output json`, the report is printed as JSON, with the `outdated` kind.
+pub async fn outdated(profile: &Profile, output: OutputFormat) -> Result<()> {
+    eprint!("Identifying installed files... ");
+    let installed_files = InstalledFiles::read(profile).await?;
+    eprintln!("{}", &*TICK);
+
+    eprint!("Checking for updates... ");
+    let latest = fetch_latest(profile, profile.mods.iter()).await?;
+    eprintln!("{}", &*TICK);
+
+    let reports = profile
+        .mods
+        .iter()
+        .zip(latest)
+        .map(|(mod_, latest)| {
+            let installed = installed_files.get(&mod_.identifier, mod_.project_type);
+            Report {
+                mod_,
+                installed,
+                latest,
+            }
+        })
+        .collect_vec();
+
+    if output.is_json() {
+        let reports = reports
+            .iter()
+            .map(|report| {
+                json!({
+                    "name": report.mod_.name,
+                    "identifier": output::identifier(&report.mod_.identifier),
+                    "status": report.status().as_str(),
+                    "installed": report
+                        .installed
+                        .as_ref()
+                        .map(|installed| file_json(&installed.filename, installed.channel)),
+                    "latest": match &report.latest {
+                        Latest::File(latest) => file_json(&latest.filename(), latest.channel),
+                        _ => Value::Null,
+                    },
+                    "error": match &report.latest {
+                        Latest::Failed(reason) => Value::String(reason.clone()),
+                        _ => Value::Null,
+                    },
+                })
+            })
+            .collect_vec();
+        output::print("outdated", reports.into())?;
+    } else {
+        let pad_len = profile
+            .mods
+            .iter()
+            .map(|mod_| mod_.name.len())
+            .max()
+            .unwrap_or(20)
+            .clamp(20, 50);
+        println!();
+        for report in &reports {
+            let installed = report
+                .installed
+                .as_ref()
+                .map(|installed| format_file(&installed.filename, installed.channel));
+            let latest = match &report.latest {
+                Latest::File(latest) => format_file(&latest.filename(), latest.channel),
+                _ => String::new(),
+            };
+            let (symbol, details) = match report.status() {
+                Status::UpToDate => (TICK.to_string(), installed.unwrap_or_default().dimmed()),
+                Status::Outdated => (
+                    "↑".yellow().to_string(),
+                    format!("{} -> {latest}", installed.unwrap_or_default()).normal(),
+                ),
+                Status::NotInstalled => (
+                    "+".green().to_string(),
+                    format!("not installed, the latest version is {latest}").normal(),
+                ),
+                Status::Unused => (
+                    "-".dimmed().to_string(),
+                    "not used in the profile's environment".dimmed(),
+                ),
+                Status::Failed => (
+                    CROSS.red().to_string(),
+                    match &report.latest {
+                        Latest::Failed(reason) => reason.red(),
+                        _ => "".normal(),
+                    },
+                ),
+            };
+            println!(
+                "{symbol} {:pad_len$}  {details}{}",
+                report.mod_.name,
+                if report.is_pinned() { "  📌" } else { "" },
+            );
+        }
+
+        let outdated = reports
+            .iter()
+            .filter(|report| matches!(report.status(), Status::Outdated | Status::NotInstalled))
+            .count();
+        if outdated == 0 {
+            println!("\n{}", "All up to date!".bold());
+        } else {
+            println!(
+                "\n{} of {} mods are not up to date",
+                outdated.to_string().yellow(),
+                reports.len()
+            );
+            println!(
+                "{}",
+                "Run `ferium update` and then `ferium upgrade` to install the latest versions, pinned mods (📌) won't be changed"
+                    .yellow()
+            );
+        }
+    }
+
+    if reports
+        .iter()
+        .any(|report| report.status() == Status::Failed)
+    {
+        Err(anyhow!(
+            "\nCould not get the latest compatible version of some mods"
+        ))
+    } else {
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use anyhow::{Context as _, Result};
 use inquire::{Select, Text};
 use libium::{
     config::filters::ProfileParameters as _,
-    config::structs::{ModLoader, Profile},
+    config::structs::{Environment, ModLoader, OptionalDependencies, Profile},
 };
 use std::path::PathBuf;
 
@@ -14,6 +14,8 @@ pub async fn configure(
     mod_loaders: Vec<ModLoader>,
     name: Option<String>,
     output_dir: Option<PathBuf>,
+    environment: Option<Environment>,
+    optional_dependencies: Option<OptionalDependencies>,
 ) -> Result<()> {
     let mut interactive = true;
 
@@ -41,6 +43,14 @@ pub async fn configure(
         profile.output_dir = output_dir;
         interactive = false;
     }
+    if let Some(environment) = environment {
+        profile.environment = environment;
+        interactive = false;
+    }
+    if let Some(optional_dependencies) = optional_dependencies {
+        profile.optional_dependencies = optional_dependencies;
+        interactive = false;
+    }
 
     if interactive {
         let items = vec![
@@ -52,6 +62,10 @@ pub async fn configure(
             "Mod loader",
             // Show a dialog to change name
             "Profile Name",
+            // Show a picker to change the environment
+            "Environment",
+            // Show a picker to change what is done with optional dependencies
+            "Optional dependencies",
             // Quit the configuration
             "Quit",
         ];
@@ -103,7 +117,50 @@ pub async fn configure(
                         continue;
                     }
                 }
-                4 => break,
+                4 => {
+                    let environments =
+                        [Environment::Client, Environment::Server, Environment::Both];
+                    if let Ok(selection) = Select::new(
+                        "Which environment should mods be installed for",
+                        environments.to_vec(),
+                    )
+                    .with_starting_cursor(
+                        environments
+                            .iter()
+                            .position(|&environment| environment == profile.environment)
+                            .unwrap_or_default(),
+                    )
+                    .prompt()
+                    {
+                        profile.environment = selection;
+                    } else {
+                        continue;
+                    }
+                }
+                5 => {
+                    let policies = [
+                        OptionalDependencies::Ignore,
+                        OptionalDependencies::Suggest,
+                        OptionalDependencies::Install,
+                    ];
+                    if let Ok(selection) = Select::new(
+                        "What should be done with the optional dependencies of mods",
+                        policies.to_vec(),
+                    )
+                    .with_starting_cursor(
+                        policies
+                            .iter()
+                            .position(|&policy| policy == profile.optional_dependencies)
+                            .unwrap_or_default(),
+                    )
+                    .prompt()
+                    {
+                        profile.optional_dependencies = selection;
+                    } else {
+                        continue;
+                    }
+                }
+                6 => break,
                 _ => unreachable!(),
             }
             println!();
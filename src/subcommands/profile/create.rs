@@ -7,13 +7,13 @@ use inquire::{
     Confirm, Select, Text,
 };
 use libium::{
-    config::structs::{Config, ModLoader, Profile},
+    config::structs::{Config, Environment, ModLoader, OptionalDependencies, Profile},
     get_minecraft_dir,
     iter_ext::IterExt as _,
 };
 use std::path::PathBuf;
 
-#[expect(clippy::option_option)]
+#[expect(clippy::option_option, clippy::too_many_arguments)]
 pub async fn create(
     config: &mut Config,
     import: Option<Option<String>>,
@@ -21,6 +21,8 @@ pub async fn create(
     mod_loader: Option<ModLoader>,
     name: Option<String>,
     output_dir: Option<PathBuf>,
+    environment: Option<Environment>,
+    optional_dependencies: Option<OptionalDependencies>,
 ) -> Result<()> {
     let mut profile = match (game_versions, mod_loader, name, output_dir) {
         (Some(game_versions), Some(mod_loader), Some(name), output_dir) => {
@@ -83,6 +85,13 @@ pub async fn create(
         }
     };
 
+    if let Some(environment) = environment {
+        profile.environment = environment;
+    }
+    if let Some(optional_dependencies) = optional_dependencies {
+        profile.optional_dependencies = optional_dependencies;
+    }
+
     if let Some(from) = import {
         ensure!(
             !config.profiles.is_empty(),
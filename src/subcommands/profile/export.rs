@@ -0,0 +1,433 @@
+use crate::{
+    cli::{ExportFormat, OutputFormat},
+    download::download,
+    subcommands::upgrade::read_user_mods,
+    TICK,
+};
+use anyhow::{ensure, Context as _, Result};
+use clap::ValueEnum as _;
+use colored::Colorize as _;
+use ferinth::structures::{project::ProjectSupportRange, version::Hash};
+use libium::{
+    config::{
+        filters::ProfileParameters as _,
+        structs::{ModIdentifier, Profile, ProjectType},
+    },
+    iter_ext::IterExt as _,
+    lockfile::{read_lockfile, LockedMod},
+    modpack::{
+        curseforge::{
+            self,
+            structs::{
+                Manifest, ManifestType, Minecraft, ModpackFile as CFModpackFile, ModpackModLoader,
+            },
+        },
+        latest_loader_version,
+        modrinth::{
+            self,
+            structs::{DependencyID, Game, Metadata, ModpackFile, ModpackFileEnvironment},
+        },
+        packwiz::{
+            self,
+            structs::{
+                CurseForgeUpdate, Download, IndexReference, MetaFile, ModrinthUpdate, Pack, Update,
+                PACK_FORMAT,
+            },
+        },
+    },
+    upgrade::{DownloadData, Hashes},
+    MODRINTH_API,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{create_dir_all, remove_dir_all},
+    path::{Path, PathBuf},
+};
+
+/// Export `profile` as a modpack in `format` to `output`, which defaults to the profile's name in the current directory
+///
+/// The files in the profile's lockfile are exported, which is only read,
+/// so an error is returned if some of the profile's mods have not been locked.
+/// Packwiz packs are only exported to a directory that isn't empty if `force` is set.
+pub async fn export(
+    profile: &Profile,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    pack_version: String,
+    force: bool,
+) -> Result<()> {
+    let output = output.unwrap_or_else(|| match format.extension() {
+        Some(extension) => PathBuf::from(format!("{}.{extension}", profile.name)),
+        None => PathBuf::from(&profile.name),
+    });
+    // The directory might be a repository with other files, or a pack with metadata files that weren't written by ferium
+    ensure!(
+        force
+            || format.extension().is_some()
+            || output
+                .read_dir()
+                .map_or(true, |mut entries| entries.next().is_none()),
+        "{} is not empty, use `--force` to export to it anyway, \
+        which replaces its `mods` folder and the folders of the profile's other project types",
+        output.display()
+    );
+
+    let mut lockfile = read_lockfile(&profile.output_dir)?.context(
+        "The profile has not been locked yet, run `ferium upgrade` or `ferium update` first",
+    )?;
+    let unlocked = lockfile.retain_for(profile);
+    ensure!(
+        unlocked.is_empty(),
+        "The following mods have not been locked, or were locked with different settings, \
+        run `ferium upgrade` or `ferium update` first:\n{}",
+        unlocked
+            .iter()
+            .map(|mod_| format!("  - {}", mod_.name))
+            .display("\n")
+    );
+
+    let game_version = profile
+        .filters
+        .game_versions()
+        .and_then(|versions| versions.first())
+        .context("The profile needs a game version to be exported")?;
+    let mod_loader = *profile
+        .filters
+        .mod_loader()
+        .context("The profile needs a mod loader to be exported")?;
+    let loader_version = latest_loader_version(mod_loader, game_version)
+        .await?
+        .with_context(|| format!("{mod_loader} is not available for Minecraft {game_version}"))?;
+
+    // Files that cannot be referenced by the modpack format are downloaded and included in the modpack,
+    // mods are added to the overrides' `mods` folder and other projects are put in their folder in `overrides_dir`
+    let export_dir = tempfile::Builder::new()
+        .prefix("ferium-export-")
+        .tempdir()?;
+    let included_dir = export_dir.path().join("mods");
+    let overrides_dir = export_dir.path().join("overrides");
+    create_dir_all(&included_dir)?;
+
+    match format {
+        ExportFormat::Mrpack => {
+            let (referenced, included): (Vec<_>, Vec<_>) =
+                lockfile.mods.into_iter().partition(|locked| {
+                    matches!(locked.identifier, ModIdentifier::ModrinthProject(..))
+                        && locked.hashes.sha1.is_some()
+                        && locked.hashes.sha512.is_some()
+                });
+            download_included(
+                included_dir.clone(),
+                &overrides_dir.join("overrides"),
+                included,
+                profile,
+            )
+            .await?;
+
+            let metadata = Metadata {
+                format_version: 1,
+                game: Game::Minecraft,
+                version_id: pack_version,
+                name: profile.name.clone(),
+                summary: None,
+                files: mrpack_files(profile, referenced).await?,
+                dependencies: HashMap::from([
+                    (DependencyID::Minecraft, game_version.clone()),
+                    (mod_loader.into(), loader_version),
+                ]),
+            };
+            modrinth::create(
+                &output,
+                &serde_json::to_string_pretty(&metadata)?,
+                overrides_dir.exists().then_some(&overrides_dir),
+                Some(&included_dir),
+            )?;
+        }
+        ExportFormat::Curseforge => {
+            let (referenced, included): (Vec<_>, Vec<_>) =
+                lockfile.mods.into_iter().partition(|locked| {
+                    matches!(
+                        locked.identifier,
+                        ModIdentifier::CurseForgeProject(_, Some(_))
+                    )
+                });
+            if !included.is_empty() {
+                println!(
+                    "{}",
+                    format!(
+                        "WARNING: These mods are not from CurseForge, so they will be included in the modpack's overrides: {}",
+                        included.iter().map(|locked| &locked.filename).display(", ")
+                    )
+                    .yellow()
+                    .bold()
+                );
+            }
+            download_included(
+                included_dir.clone(),
+                &overrides_dir.join("overrides"),
+                included,
+                profile,
+            )
+            .await?;
+
+            let manifest = Manifest {
+                minecraft: Minecraft {
+                    version: game_version.clone(),
+                    mod_loaders: vec![ModpackModLoader {
+                        id: format!("{}-{loader_version}", mod_loader.to_string().to_lowercase()),
+                        primary: true,
+                    }],
+                },
+                manifest_type: ManifestType::MinecraftModpack,
+                manifest_version: 1,
+                name: profile.name.clone(),
+                version: Some(pack_version),
+                author: String::new(),
+                files: referenced
+                    .into_iter()
+                    .filter_map(|locked| match locked.identifier {
+                        ModIdentifier::CurseForgeProject(project_id, Some(file_id)) => {
+                            Some(CFModpackFile {
+                                project_id,
+                                file_id,
+                                required: true,
+                            })
+                        }
+                        _ => None,
+                    })
+                    .collect_vec(),
+                overrides: "overrides".to_owned(),
+            };
+            curseforge::create(
+                &output,
+                &manifest,
+                overrides_dir.exists().then_some(&overrides_dir),
+                Some(&included_dir),
+            )?;
+        }
+        ExportFormat::Packwiz => {
+            let (referenced, included): (Vec<_>, Vec<_>) =
+                lockfile.mods.into_iter().partition(|locked| {
+                    match locked.identifier {
+                        ModIdentifier::ModrinthProject(_, Some(_))
+                        | ModIdentifier::CurseForgeProject(_, Some(_)) => {
+                            locked.hashes.sha1.is_some()
+                        }
+                        // Packwiz downloads files from any URL, and checks them with their hash
+                        ModIdentifier::MavenArtifact(..) | ModIdentifier::DirectUrl(..) => {
+                            !locked.hashes.is_empty()
+                        }
+                        _ => false,
+                    }
+                });
+            // Only the directories of the profile's project types are replaced,
+            // which are only there if the export was forced
+            for project_type in ProjectType::value_variants() {
+                let directory = output.join(project_type.directory_name());
+                if directory.exists()
+                    && (project_type.is_mod()
+                        || profile
+                            .mods
+                            .iter()
+                            .any(|mod_| mod_.project_type == *project_type))
+                {
+                    remove_dir_all(directory)?;
+                }
+            }
+            // Files that can't be referenced are put directly in the pack
+            download_included(output.join("mods"), &output, included, profile).await?;
+
+            let pack = Pack {
+                name: profile.name.clone(),
+                author: None,
+                version: Some(pack_version),
+                pack_format: Some(PACK_FORMAT.to_owned()),
+                index: IndexReference {
+                    file: String::new(),
+                    hash_format: String::new(),
+                    hash: String::new(),
+                },
+                versions: BTreeMap::from([
+                    ("minecraft".to_owned(), game_version.clone()),
+                    (mod_loader.to_string().to_lowercase(), loader_version),
+                ]),
+            };
+            packwiz::write_pack(
+                &output,
+                pack,
+                referenced
+                    .into_iter()
+                    .map(|locked| packwiz_metafile(profile, locked)),
+            )?;
+        }
+    }
+    export_dir.close()?;
+
+    println!(
+        "{} Exported {} to {}",
+        &*TICK,
+        profile.name.bold(),
+        output.display().to_string().blue().underline()
+    );
+    Ok(())
+}
+
+/// Create the packwiz metadata file for `locked` and the path to write it to
+fn packwiz_metafile(profile: &Profile, locked: LockedMod) -> (PathBuf, MetaFile) {
+    let mod_ = profile.mods.iter().find(|mod_| locked.locks(mod_));
+    let file_stem = Path::new(&locked.filename)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let path = PathBuf::from(locked.project_type.directory_name()).join(format!(
+        "{}.pw.toml",
+        mod_.and_then(|mod_| mod_.slug.clone())
+            .unwrap_or(file_stem.clone())
+    ));
+    let metafile = MetaFile {
+        name: mod_.map_or(file_stem, |mod_| mod_.name.clone()),
+        side: mod_
+            .and_then(|mod_| mod_.environment)
+            .unwrap_or_default()
+            .into(),
+        download: match locked.hashes {
+            Hashes {
+                sha1: None,
+                sha512: Some(sha512),
+            } => Download {
+                url: Some(locked.download_url),
+                hash_format: "sha512".to_owned(),
+                hash: sha512,
+                mode: None,
+            },
+            Hashes { sha1, .. } => Download {
+                url: Some(locked.download_url),
+                hash_format: "sha1".to_owned(),
+                hash: sha1.unwrap_or_default(),
+                mode: None,
+            },
+        },
+        update: match locked.identifier {
+            ModIdentifier::ModrinthProject(mod_id, Some(version)) => Update {
+                modrinth: Some(ModrinthUpdate { mod_id, version }),
+                curseforge: None,
+            },
+            ModIdentifier::CurseForgeProject(project_id, Some(file_id)) => Update {
+                modrinth: None,
+                curseforge: Some(CurseForgeUpdate {
+                    project_id,
+                    file_id,
+                }),
+            },
+            _ => Update::default(),
+        },
+        filename: locked.filename,
+    };
+    (path, metafile)
+}
+
+/// Download the `included` files and copy the profile's user mods to `mods_dir`
+///
+/// Included projects that are not mods are downloaded to their folder in `others_dir`.
+async fn download_included(
+    mods_dir: PathBuf,
+    others_dir: &Path,
+    included: Vec<LockedMod>,
+    profile: &Profile,
+) -> Result<()> {
+    let user_mods = read_user_mods(profile)?;
+    let (mods, others): (Vec<_>, Vec<_>) = included
+        .into_iter()
+        .partition(|locked| locked.project_type.is_mod());
+    if !mods.is_empty() || !others.is_empty() || !user_mods.is_empty() {
+        println!("\n{}\n", "Downloading Files to Include".bold());
+        download(
+            mods_dir,
+            mods.into_iter().map(Into::into).collect_vec(),
+            user_mods,
+            OutputFormat::Human,
+        )
+        .await?;
+        download(
+            others_dir.to_owned(),
+            others
+                .into_iter()
+                .map(|locked| {
+                    let directory = locked.project_type.directory_name();
+                    let mut download_data = DownloadData::from(locked);
+                    download_data.output = Path::new(directory).join(download_data.filename());
+                    download_data
+                })
+                .collect_vec(),
+            vec![],
+            OutputFormat::Human,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Create modpack file entries for the Modrinth files in `referenced`,
+/// with the environments set for their mods in `profile` or else the environments of their projects
+async fn mrpack_files(profile: &Profile, referenced: Vec<LockedMod>) -> Result<Vec<ModpackFile>> {
+    let project_ids = referenced
+        .iter()
+        .filter_map(|locked| match &locked.identifier {
+            ModIdentifier::ModrinthProject(id, _) => Some(id.as_str()),
+            _ => None,
+        })
+        .collect_vec();
+    let environments = if project_ids.is_empty() {
+        HashMap::new()
+    } else {
+        MODRINTH_API
+            .project_get_multiple(&project_ids)
+            .await?
+            .into_iter()
+            .map(|project| (project.id, (project.client_side, project.server_side)))
+            .collect::<HashMap<_, _>>()
+    };
+
+    Ok(referenced
+        .into_iter()
+        .map(|locked| {
+            let env = profile
+                .mods
+                .iter()
+                .find(|mod_| locked.locks(mod_))
+                .and_then(|mod_| mod_.environment)
+                .map(Into::into)
+                .or_else(|| {
+                    match &locked.identifier {
+                        ModIdentifier::ModrinthProject(id, _) => environments.get(id),
+                        _ => None,
+                    }
+                    .map(|&(client, server)| ModpackFileEnvironment {
+                        client: support_range(client),
+                        server: support_range(server),
+                    })
+                });
+            ModpackFile {
+                path: PathBuf::from(locked.project_type.directory_name()).join(&locked.filename),
+                hashes: Hash {
+                    sha512: locked.hashes.sha512.unwrap_or_default(),
+                    sha1: locked.hashes.sha1.unwrap_or_default(),
+                    others: HashMap::new(),
+                },
+                env,
+                downloads: vec![locked.download_url],
+                file_size: locked.length,
+            }
+        })
+        .collect_vec())
+}
+
+/// The modpack format does not allow unknown support, so assume the project is required
+fn support_range(range: ProjectSupportRange) -> ProjectSupportRange {
+    if range == ProjectSupportRange::Unknown {
+        ProjectSupportRange::Required
+    } else {
+        range
+    }
+}
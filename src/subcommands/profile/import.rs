@@ -0,0 +1,394 @@
+use crate::{cli::OutputFormat, download::download, STYLE_BYTE, TICK};
+use anyhow::{bail, ensure, Context as _, Result};
+use colored::Colorize as _;
+use indicatif::ProgressBar;
+use libium::{
+    add::cf_project_type,
+    config::structs::{
+        Config, Environment, Mod, ModIdentifier, ModLoader, ModpackIdentifier, Profile, ProjectType,
+    },
+    get_minecraft_dir,
+    iter_ext::IterExt as _,
+    modpack::{
+        curseforge::structs::Manifest as CFManifest,
+        extract_overrides,
+        modrinth::structs::{DependencyID, Metadata as MRMetadata},
+        packwiz::{self, structs::Side, PACK_FILE},
+        read_file_from_zip,
+    },
+    upgrade::{from_modpack_file, DownloadData, Hashes},
+    CURSEFORGE_API, MODRINTH_API,
+};
+use std::{
+    fs::{copy, create_dir_all, File},
+    io::BufReader,
+    path::{Component, Path, PathBuf},
+    str::FromStr as _,
+    time::Duration,
+};
+
+/// Create a new profile from the modpack at `source`,
+/// which is either a modpack file, a packwiz pack, or a modpack project ID
+///
+/// The modpack's files are added as mods pinned to those files.
+/// The overrides' mods are extracted to the profile's `user` folder,
+/// and the other overrides to the `overrides` folder inside it.
+pub async fn import(
+    config: &mut Config,
+    source: String,
+    name: Option<String>,
+    output_dir: Option<PathBuf>,
+) -> Result<()> {
+    let output_dir = output_dir.unwrap_or_else(|| get_minecraft_dir().join("mods"));
+    ensure!(
+        output_dir.is_absolute(),
+        "The provided output directory is not absolute, i.e. it is a relative path"
+    );
+    let overrides_dir = output_dir.join("user").join("overrides");
+
+    // A packwiz pack can be provided as its directory or its `pack.toml`
+    let pack_file = if Path::new(&source).is_dir() {
+        Path::new(&source).join(PACK_FILE)
+    } else {
+        PathBuf::from(&source)
+    };
+    let profile = if pack_file.is_file() && pack_file.ends_with(PACK_FILE) {
+        packwiz(config, &pack_file, name, output_dir).await?
+    } else {
+        let modpack_path = if Path::new(&source).is_file() {
+            PathBuf::from(source)
+        } else {
+            download_modpack(if let Ok(project_id) = source.parse() {
+                ModpackIdentifier::CurseForgeModpack(project_id)
+            } else {
+                ModpackIdentifier::ModrinthModpack(source)
+            })
+            .await?
+        };
+        modpack(config, &modpack_path, name, output_dir).await?
+    };
+
+    println!(
+        "{} Imported {} with {} mods",
+        &*TICK,
+        profile.name.bold(),
+        profile.mods.len().to_string().yellow()
+    );
+    if overrides_dir.exists() {
+        println!(
+            "{}",
+            format!(
+                "The modpack's other files have been put in {}, copy them to your instance directory if you need them",
+                overrides_dir.display()
+            )
+            .yellow()
+        );
+    }
+    println!(
+        "{}",
+        "Remember to run `ferium upgrade` to download the mods!".yellow()
+    );
+
+    config.profiles.push(profile);
+    config.active_profile = config.profiles.len() - 1; // Make imported profile active
+    Ok(())
+}
+
+/// Create a new profile from the Modrinth or Curseforge modpack file at `modpack_path`
+async fn modpack(
+    config: &Config,
+    modpack_path: &Path,
+    name: Option<String>,
+    output_dir: PathBuf,
+) -> Result<Profile> {
+    let user_dir = output_dir.join("user");
+    let overrides_dir = user_dir.join("overrides");
+
+    Ok(
+        if let Some(metadata) = read_file_from_zip(
+            BufReader::new(File::open(modpack_path)?),
+            "modrinth.index.json",
+        )? {
+            let metadata: MRMetadata = serde_json::from_str(&metadata)?;
+            let mut profile = Profile::new(
+                name.unwrap_or_else(|| metadata.name.clone()),
+                output_dir,
+                vec![metadata
+                    .dependencies
+                    .get(&DependencyID::Minecraft)
+                    .context("The modpack does not specify a Minecraft version")?
+                    .clone()],
+                metadata
+                    .dependencies
+                    .keys()
+                    .find_map(DependencyID::mod_loader)
+                    .context("The modpack does not specify a mod loader")?,
+            );
+            check_name(config, &profile.name)?;
+            for file in &metadata.files {
+                enclosed_path(&file.path)?;
+            }
+
+            eprint!("Looking up the modpack's files... ");
+            let mut versions = MODRINTH_API
+                .version_get_from_multiple_hashes(
+                    metadata
+                        .files
+                        .iter()
+                        .map(|file| file.hashes.sha1.clone())
+                        .collect_vec(),
+                )
+                .await?;
+            let mut not_found = Vec::new();
+            let mut pinned = Vec::new();
+            for file in metadata.files {
+                // Projects are added to the profile if they are in the directory of their project type
+                let project_type = file.path.iter().next().and_then(|directory| {
+                    ProjectType::from_directory_name(&directory.to_string_lossy())
+                });
+                match versions.remove(&file.hashes.sha1) {
+                    Some(version) if project_type.is_some() => {
+                        // Only keep the modpack's environment if it restricts the file to one side
+                        let environment = file
+                            .env
+                            .map(Environment::from)
+                            .filter(|&environment| environment != Environment::Both);
+                        pinned.push((version.project_id, version.id, environment, project_type));
+                    }
+                    // Files that are not mods, or are not on Modrinth, are downloaded to the overrides
+                    _ => {
+                        let mut download_data = from_modpack_file(file);
+                        if download_data.output.starts_with("mods") {
+                            download_data.output = download_data.filename().into();
+                            not_found.push((user_dir.clone(), download_data));
+                        } else {
+                            not_found.push((overrides_dir.clone(), download_data));
+                        }
+                    }
+                }
+            }
+            let projects = MODRINTH_API
+                .project_get_multiple(&pinned.iter().map(|(id, ..)| id.as_str()).collect_vec())
+                .await?;
+            for (project_id, version_id, environment, project_type) in pinned {
+                let project = projects
+                    .iter()
+                    .find(|project| project.id == project_id)
+                    .context("Modrinth did not return a project in the modpack")?;
+                profile
+                    .push_mod(
+                        project.title.clone(),
+                        ModIdentifier::ModrinthProject(project_id, Some(version_id)),
+                        project.slug.clone(),
+                        false,
+                        vec![],
+                        environment,
+                    )
+                    .project_type = project_type.unwrap_or_default();
+            }
+            println!("{}", &*TICK);
+
+            for (directory, download_data) in not_found {
+                download(directory, vec![download_data], vec![], OutputFormat::Human).await?;
+            }
+            extract_overrides(
+                BufReader::new(File::open(modpack_path)?),
+                "overrides",
+                &user_dir,
+                &overrides_dir,
+            )?;
+            profile
+        } else if let Some(manifest) =
+            read_file_from_zip(BufReader::new(File::open(modpack_path)?), "manifest.json")?
+        {
+            let manifest: CFManifest = serde_json::from_str(&manifest)?;
+            let mod_loader = manifest
+                .minecraft
+                .mod_loaders
+                .iter()
+                .find(|loader| loader.primary)
+                .or(manifest.minecraft.mod_loaders.first())
+                .and_then(|loader| loader.id.split('-').next())
+                .and_then(|loader| ModLoader::from_str(loader).ok())
+                .context("The modpack does not specify a mod loader")?;
+            let mut profile = Profile::new(
+                name.unwrap_or_else(|| manifest.name.clone()),
+                output_dir,
+                vec![manifest.minecraft.version.clone()],
+                mod_loader,
+            );
+            check_name(config, &profile.name)?;
+
+            eprint!("Looking up the modpack's files... ");
+            let projects = CURSEFORGE_API
+                .get_mods(manifest.files.iter().map(|file| file.project_id).collect())
+                .await?;
+            for file in manifest.files {
+                let project = projects
+                    .iter()
+                    .find(|project| project.id == file.project_id)
+                    .context("CurseForge did not return a project in the modpack")?;
+                profile
+                    .push_mod(
+                        project.name.clone(),
+                        ModIdentifier::CurseForgeProject(file.project_id, Some(file.file_id)),
+                        project.slug.clone(),
+                        false,
+                        vec![],
+                        None,
+                    )
+                    .project_type = cf_project_type(project).unwrap_or_default();
+            }
+            println!("{}", &*TICK);
+
+            extract_overrides(
+                BufReader::new(File::open(modpack_path)?),
+                &manifest.overrides,
+                &user_dir,
+                &overrides_dir,
+            )?;
+            profile
+        } else {
+            bail!("The file is not a Modrinth or CurseForge modpack")
+        },
+    )
+}
+/// Create a new profile from the packwiz pack at `pack_file`
+async fn packwiz(
+    config: &Config,
+    pack_file: &Path,
+    name: Option<String>,
+    output_dir: PathBuf,
+) -> Result<Profile> {
+    let user_dir = output_dir.join("user");
+    let overrides_dir = user_dir.join("overrides");
+
+    let (pack, index, base_dir) = packwiz::read(pack_file)?;
+    let mut profile = Profile::new(
+        name.unwrap_or_else(|| pack.name.clone()),
+        output_dir,
+        vec![pack
+            .versions
+            .get("minecraft")
+            .context("The pack does not specify a Minecraft version")?
+            .clone()],
+        pack.versions
+            .keys()
+            .find_map(|loader| ModLoader::from_str(loader).ok())
+            .context("The pack does not specify a mod loader")?,
+    );
+    check_name(config, &profile.name)?;
+
+    let mut to_download = Vec::new();
+    for file in index.files {
+        let file_path = enclosed_path(&file.file)?;
+        let path = base_dir.join(&file_path);
+        let in_mods = file_path.starts_with("mods");
+        let project_type = file_path
+            .iter()
+            .next()
+            .and_then(|directory| ProjectType::from_directory_name(&directory.to_string_lossy()))
+            .unwrap_or_default();
+        if file.metafile {
+            let metafile = packwiz::read_metafile(&path)?;
+            let identifier = if let Some(modrinth) = metafile.update.modrinth {
+                ModIdentifier::ModrinthProject(modrinth.mod_id, Some(modrinth.version))
+            } else if let Some(curseforge) = metafile.update.curseforge {
+                ModIdentifier::CurseForgeProject(curseforge.project_id, Some(curseforge.file_id))
+            } else {
+                // Files that can't be resolved by ferium are downloaded to the overrides
+                let url = metafile.download.url.with_context(|| {
+                    format!("{} does not have a download URL", metafile.filename)
+                })?;
+                let filename = enclosed_path(&metafile.filename)?;
+                let (directory, output) = if in_mods {
+                    (user_dir.clone(), filename)
+                } else {
+                    (overrides_dir.clone(), file_path.with_file_name(filename))
+                };
+                to_download.push((
+                    directory,
+                    DownloadData {
+                        identifier: None,
+                        download_url: url,
+                        output,
+                        length: 0,
+                        hashes: Hashes {
+                            sha1: (metafile.download.hash_format == "sha1")
+                                .then(|| metafile.download.hash.clone()),
+                            sha512: (metafile.download.hash_format == "sha512")
+                                .then(|| metafile.download.hash.clone()),
+                        },
+                        channel: None,
+                        dependencies: Vec::new(),
+                        optional_dependencies: Vec::new(),
+                        conflicts: Vec::new(),
+                    },
+                ));
+                continue;
+            };
+            let mut mod_ = Mod::new(metafile.name, identifier, vec![], false);
+            mod_.project_type = project_type;
+            if metafile.side != Side::Both {
+                mod_.environment = Some(metafile.side.into());
+            }
+            profile.mods.push(mod_);
+        } else {
+            let output = if in_mods {
+                user_dir.join(path.file_name().unwrap_or_default())
+            } else {
+                overrides_dir.join(&file_path)
+            };
+            if let Some(parent) = output.parent() {
+                create_dir_all(parent)?;
+            }
+            copy(&path, output)?;
+        }
+    }
+    for (directory, download_data) in to_download {
+        download(directory, vec![download_data], vec![], OutputFormat::Human).await?;
+    }
+    Ok(profile)
+}
+
+/// Check that `path` from a modpack is relative and stays inside the directory it is joined onto
+fn enclosed_path(path: impl AsRef<Path>) -> Result<PathBuf> {
+    let path = path.as_ref();
+    ensure!(
+        path.components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir)),
+        "The modpack's file {} is outside the instance",
+        path.display()
+    );
+    Ok(path.to_owned())
+}
+
+fn check_name(config: &Config, name: &str) -> Result<()> {
+    ensure!(
+        !config
+            .profiles
+            .iter()
+            .any(|profile| profile.name.eq_ignore_ascii_case(name)),
+        "A profile with name {name} already exists, provide a different name"
+    );
+    Ok(())
+}
+
+/// Download the latest version of the modpack with `identifier`, returns the path to the downloaded file
+async fn download_modpack(identifier: ModpackIdentifier) -> Result<PathBuf> {
+    let progress_bar = ProgressBar::new(0).with_style(STYLE_BYTE.clone());
+    let modpack_path = identifier
+        .download_file(
+            |total| {
+                progress_bar.println("Downloading Modpack".bold().to_string());
+                progress_bar.enable_steady_tick(Duration::from_millis(100));
+                progress_bar.set_length(total as u64);
+            },
+            |additional| {
+                progress_bar.inc(additional as u64);
+            },
+        )
+        .await?;
+    progress_bar.finish_and_clear();
+    Ok(modpack_path)
+}
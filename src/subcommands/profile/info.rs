@@ -1,13 +1,16 @@
 use colored::Colorize;
 use libium::{
-    config::{filters::ProfileParameters as _, structs::Profile},
+    config::{
+        filters::ProfileParameters as _,
+        structs::{Environment, Profile},
+    },
     iter_ext::IterExt as _,
 };
 
 pub fn info(profile: &Profile, active: bool) {
     println!(
         "{}{}
-        \r  Output directory:   {}{}{}
+        \r  Output directory:   {}{}{}{}{}
         \r  Mods:               {}\n",
         if active {
             profile.name.bold().italic()
@@ -32,6 +35,22 @@ pub fn info(profile: &Profile, active: bool) {
             .mod_loader()
             .map(|l| format!("\n  Mod Loader:         {}", l.to_string().purple()))
             .unwrap_or_default(),
+        if profile.environment == Environment::Both {
+            String::new()
+        } else {
+            format!(
+                "\n  Environment:        {}",
+                profile.environment.to_string().cyan()
+            )
+        },
+        if profile.optional_dependencies.is_ignore() {
+            String::new()
+        } else {
+            format!(
+                "\n  Optional deps:      {}",
+                profile.optional_dependencies.to_string().cyan()
+            )
+        },
         profile.mods.len().to_string().yellow(),
     );
 }
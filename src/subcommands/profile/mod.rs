@@ -1,11 +1,15 @@
 mod configure;
 mod create;
 mod delete;
+mod export;
+mod import;
 mod info;
 mod switch;
 pub use configure::configure;
 pub use create::create;
 pub use delete::delete;
+pub use export::export;
+pub use import::import;
 pub use info::info;
 pub use switch::switch;
 
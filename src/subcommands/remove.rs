@@ -2,10 +2,35 @@ use anyhow::{bail, Result};
 use colored::Colorize as _;
 use inquire::MultiSelect;
 use libium::{
-    config::structs::{ModIdentifier, Profile},
+    config::structs::{Forge, Mod, ModIdentifier, Profile},
+    forge,
     iter_ext::IterExt as _,
+    maven,
 };
 
+/// Whether `name` is the case-insensitive name, ID, or slug of `mod_`
+pub(super) fn is_named(mod_: &Mod, name: &str) -> bool {
+    mod_.name.eq_ignore_ascii_case(name)
+        || match &mod_.identifier {
+            ModIdentifier::CurseForgeProject(id, _) => id.to_string() == name,
+            ModIdentifier::ModrinthProject(id, _) => id == name,
+            ModIdentifier::GitHubRepository((owner, repo), _) => {
+                format!("{owner}/{repo}").eq_ignore_ascii_case(name)
+            }
+            ModIdentifier::MavenArtifact(artifact, _) => maven::display_name(artifact) == name,
+            ModIdentifier::ForgeRepository(repository, _) => {
+                forge::display_name(repository).eq_ignore_ascii_case(name)
+                    || repository.path.eq_ignore_ascii_case(name)
+            }
+            ModIdentifier::DirectUrl(url, _) => url.as_str() == name,
+            ModIdentifier::LocalPath(path) => path.as_os_str() == name,
+        }
+        || mod_
+            .slug
+            .as_ref()
+            .is_some_and(|slug| name.eq_ignore_ascii_case(slug))
+}
+
 /// If `to_remove` is empty, display a list of projects in the profile to select from and remove selected ones
 ///
 /// Else, search the given strings with the projects' name and IDs and remove them
@@ -16,25 +41,46 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
             .iter()
             .map(|mod_| {
                 format!(
-                    "{:11}  {}{}",
+                    "{:11}  {}{}{}",
                     match &mod_.identifier {
                         ModIdentifier::CurseForgeProject(id, _) =>
                             format!("CF {:8}", id.to_string()),
                         ModIdentifier::ModrinthProject(id, _) => format!("MR {id:8}"),
                         ModIdentifier::GitHubRepository(..) => "GH".to_string(),
+                        ModIdentifier::ForgeRepository(repository, _) => match repository.forge {
+                            Forge::GitLab => "GL",
+                            Forge::Gitea => "GT",
+                            Forge::Forgejo => "FJ",
+                        }
+                        .to_string(),
+                        ModIdentifier::MavenArtifact(..) => "MVN".to_string(),
+                        ModIdentifier::DirectUrl(..) => "URL".to_string(),
+                        ModIdentifier::LocalPath(_) => "LOCAL".to_string(),
                     },
                     match &mod_.identifier {
                         ModIdentifier::ModrinthProject(..)
-                        | ModIdentifier::CurseForgeProject(..) => mod_.name.clone(),
+                        | ModIdentifier::CurseForgeProject(..)
+                        | ModIdentifier::DirectUrl(..)
+                        | ModIdentifier::LocalPath(_) => mod_.name.clone(),
                         ModIdentifier::GitHubRepository((owner, repo), _) =>
                             format!("{owner}/{repo}"),
+                        ModIdentifier::MavenArtifact(artifact, _) => maven::display_name(artifact),
+                        ModIdentifier::ForgeRepository(repository, _) =>
+                            forge::display_name(repository),
                     },
                     match &mod_.identifier {
                         ModIdentifier::CurseForgeProject(_, Some(pin)) => format!(" (📌 {pin})"),
                         ModIdentifier::ModrinthProject(_, Some(pin))
-                        | ModIdentifier::GitHubRepository(_, Some(pin)) => format!(" (📌 {pin})"),
+                        | ModIdentifier::GitHubRepository(_, Some(pin))
+                        | ModIdentifier::MavenArtifact(_, Some(pin))
+                        | ModIdentifier::ForgeRepository(_, Some(pin)) => format!(" (📌 {pin})"),
                         _ => String::new(),
                     },
+                    if mod_.is_dependency() {
+                        " (dependency)"
+                    } else {
+                        ""
+                    },
                 )
             })
             .collect_vec();
@@ -47,20 +93,11 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
     } else {
         let mut items_to_remove = Vec::new();
         for to_remove in to_remove {
-            if let Some(index) = profile.mods.iter().position(|mod_| {
-                mod_.name.eq_ignore_ascii_case(&to_remove)
-                    || match &mod_.identifier {
-                        ModIdentifier::CurseForgeProject(id, _) => id.to_string() == to_remove,
-                        ModIdentifier::ModrinthProject(id, _) => id == &to_remove,
-                        ModIdentifier::GitHubRepository((owner, name), _) => {
-                            format!("{owner}/{name}").eq_ignore_ascii_case(&to_remove)
-                        }
-                    }
-                    || mod_
-                        .slug
-                        .as_ref()
-                        .is_some_and(|slug| to_remove.eq_ignore_ascii_case(slug))
-            }) {
+            if let Some(index) = profile
+                .mods
+                .iter()
+                .position(|mod_| is_named(mod_, &to_remove))
+            {
                 items_to_remove.push(index);
             } else {
                 bail!("A mod with ID or name {to_remove} is not present in this profile");
@@ -85,5 +122,13 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
         );
     }
 
+    let unused = profile.remove_unused_dependencies();
+    if !unused.is_empty() {
+        println!(
+            "Removed dependencies that are no longer needed: {}",
+            unused.iter().map(|mod_| mod_.name.bold()).display(", ")
+        );
+    }
+
     Ok(())
 }
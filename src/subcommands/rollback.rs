@@ -0,0 +1,117 @@
+use crate::{cli::OutputFormat, output, TICK};
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use libium::{
+    config::structs::Profile,
+    iter_ext::IterExt as _,
+    snapshot::{self, Snapshot, ROLLBACK_REASON},
+};
+use serde_json::json;
+
+/// List the snapshots of `profile`, newest first
+pub fn list(profile: &Profile, output: OutputFormat) -> Result<()> {
+    let snapshots = snapshot::list(&profile.output_dir)?;
+    if output.is_json() {
+        return output::print(
+            "snapshots",
+            snapshots
+                .iter()
+                .rev()
+                .map(|snapshot| {
+                    json!({
+                        "id": snapshot.id,
+                        "created": snapshot.created,
+                        "reason": snapshot.reason,
+                        "file_count": snapshot.len(),
+                    })
+                })
+                .collect(),
+        );
+    }
+    if snapshots.is_empty() {
+        println!("{}", "There are no snapshots of this profile yet".yellow());
+    }
+    for snapshot in snapshots.iter().rev() {
+        println!(
+            "{}  {:20} {}",
+            snapshot.id.bold(),
+            snapshot.reason,
+            format!("{} files", snapshot.len()).dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Restore the install directories and lockfile of `profile` to the snapshot `to`, or to the latest snapshot
+///
+/// A snapshot of the current files is taken first, so the rollback can be undone too.
+/// Those snapshots are skipped when choosing the latest snapshot,
+/// so rolling back again goes further back instead of undoing the last rollback.
+pub fn rollback(profile: &Profile, to: Option<String>, output: OutputFormat) -> Result<()> {
+    let mut snapshots = snapshot::list(&profile.output_dir)?;
+    let index = if let Some(to) = to {
+        snapshots
+            .iter()
+            .position(|snapshot| snapshot.id == to)
+            .with_context(|| {
+                format!("There is no snapshot {to}, use `ferium rollback --list` to see the snapshots of this profile")
+            })?
+    } else {
+        // If the last change was a rollback, go back further than the snapshot it restored
+        let older_than = snapshots
+            .last()
+            .and_then(Snapshot::rolled_back_to)
+            .and_then(|id| snapshots.iter().find(|snapshot| snapshot.id == id))
+            .map(|restored| restored.created);
+        snapshots
+            .iter()
+            .rposition(|snapshot| {
+                snapshot.rolled_back_to().is_none()
+                    && older_than.map_or(true, |older_than| snapshot.created < older_than)
+            })
+            .context("There are no snapshots of this profile to roll back to")?
+    };
+    let target = snapshots.swap_remove(index);
+
+    let current = snapshot::take(
+        &profile.output_dir,
+        &target
+            .directories
+            .iter()
+            .map(|directory| directory.project_type)
+            .collect_vec(),
+        format!("{ROLLBACK_REASON}{}", target.id),
+    )?;
+    snapshot::restore(&profile.output_dir, &target)?;
+    snapshot::prune(&profile.output_dir)?;
+
+    output.println(format!(
+        "{} Restored {} files from snapshot {} ({})",
+        &*TICK,
+        target.len(),
+        target.id.bold(),
+        target.reason.to_lowercase()
+    ));
+    output.println(
+        format!(
+            "The replaced files were saved in snapshot {}, run `ferium rollback --to {}` to undo this",
+            current.id, current.id
+        )
+        .dimmed(),
+    );
+    if output.is_json() {
+        output::print(
+            "rollback",
+            json!({
+                "restored": {
+                    "id": target.id,
+                    "created": target.created,
+                    "reason": target.reason,
+                    "file_count": target.len(),
+                },
+                "saved": current.id,
+            }),
+        )?;
+    }
+    Ok(())
+}
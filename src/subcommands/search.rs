@@ -0,0 +1,219 @@
+use crate::{
+    add,
+    cli::{OutputFormat, Platform},
+    output,
+};
+use anyhow::{bail, ensure, Result};
+use colored::Colorize as _;
+use inquire::MultiSelect;
+use libium::{
+    config::structs::{ModIdentifier, Profile},
+    iter_ext::IterExt as _,
+    search::{self, SearchResult},
+};
+use serde_json::json;
+use std::fmt::Display;
+
+/// A project found on Modrinth, Curseforge, or both
+struct Found {
+    name: String,
+    slug: String,
+    summary: String,
+    authors: Vec<String>,
+    downloads: usize,
+    modrinth: Option<ModIdentifier>,
+    curseforge: Option<ModIdentifier>,
+}
+
+impl Found {
+    fn is(&self, result: &SearchResult) -> bool {
+        self.slug == result.slug || self.name.to_lowercase() == result.name.to_lowercase()
+    }
+
+    /// The identifier to add this project with, from `platform` if it's available there
+    fn identifier(&self, platform: Platform) -> Option<&ModIdentifier> {
+        match platform {
+            Platform::Modrinth => self.modrinth.as_ref().or(self.curseforge.as_ref()),
+            Platform::Curseforge => self.curseforge.as_ref().or(self.modrinth.as_ref()),
+        }
+    }
+
+    fn platforms(&self) -> String {
+        [
+            self.modrinth.as_ref().map(|_| "Modrinth"),
+            self.curseforge.as_ref().map(|_| "CurseForge"),
+        ]
+        .into_iter()
+        .flatten()
+        .display(", ")
+    }
+}
+
+impl Display for Found {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.platforms())
+    }
+}
+
+/// Merge the results of both platforms in order of relevance,
+/// combining the results that are the same project on both platforms
+fn merge(modrinth: Vec<SearchResult>, curseforge: Vec<SearchResult>) -> Vec<Found> {
+    let mut found: Vec<Found> = Vec::new();
+    let mut modrinth = modrinth.into_iter();
+    let mut curseforge = curseforge.into_iter();
+    loop {
+        let results = [modrinth.next(), curseforge.next()];
+        if results.iter().all(Option::is_none) {
+            break;
+        }
+        for result in results.into_iter().flatten() {
+            let is_modrinth = matches!(result.identifier, ModIdentifier::ModrinthProject(..));
+            let existing = found.iter_mut().find(|found| {
+                found.is(&result)
+                    && if is_modrinth {
+                        found.modrinth.is_none()
+                    } else {
+                        found.curseforge.is_none()
+                    }
+            });
+            if let Some(existing) = existing {
+                existing.downloads += result.downloads;
+                if is_modrinth {
+                    existing.modrinth = Some(result.identifier);
+                } else {
+                    existing.curseforge = Some(result.identifier);
+                }
+            } else {
+                found.push(Found {
+                    name: result.name,
+                    slug: result.slug,
+                    summary: result.summary,
+                    authors: result.authors,
+                    downloads: result.downloads,
+                    modrinth: is_modrinth.then(|| result.identifier.clone()),
+                    curseforge: (!is_modrinth).then_some(result.identifier),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// A shorter form of `count`, e.g. 12.3M
+fn compact(count: usize) -> String {
+    #[expect(clippy::cast_precision_loss, reason = "Only one decimal is shown")]
+    let value = count as f64;
+    if count >= 1_000_000 {
+        format!("{:.1}M", value / 1_000_000.)
+    } else if count >= 1_000 {
+        format!("{:.1}K", value / 1_000.)
+    } else {
+        count.to_string()
+    }
+}
+
+/// Search Modrinth and Curseforge for mods matching `query` that are compatible with `profile`
+///
+/// If `add` is set, the user picks the projects to add to `profile`, which are added from `platform` if possible.
+/// Returns whether adding any of them failed.
+pub async fn search(
+    profile: &mut Profile,
+    query: &str,
+    add: bool,
+    platform: Platform,
+    output: OutputFormat,
+) -> Result<bool> {
+    ensure!(
+        !(add && output.is_json()),
+        "Projects can't be picked to add with JSON output"
+    );
+
+    let (modrinth, curseforge) = tokio::join!(
+        search::modrinth(query, &profile.filters),
+        search::curseforge(query, &profile.filters)
+    );
+    let (modrinth, curseforge) = match (modrinth, curseforge) {
+        (Err(mr_err), Err(cf_err)) => {
+            bail!("Could not search Modrinth ({mr_err}) or CurseForge ({cf_err})")
+        }
+        (modrinth, curseforge) => {
+            if let Err(err) = &modrinth {
+                output.println(format!("Could not search Modrinth: {err}").red());
+            }
+            if let Err(err) = &curseforge {
+                output.println(format!("Could not search CurseForge: {err}").red());
+            }
+            (modrinth.unwrap_or_default(), curseforge.unwrap_or_default())
+        }
+    };
+    let found = merge(modrinth, curseforge);
+
+    if output.is_json() {
+        return output::print(
+            "search",
+            json!({
+                "query": query,
+                "results": found
+                    .iter()
+                    .map(|found| {
+                        json!({
+                            "name": found.name,
+                            "slug": found.slug,
+                            "summary": found.summary,
+                            "authors": found.authors,
+                            "downloads": found.downloads,
+                            "modrinth": found.modrinth.as_ref().map(output::identifier),
+                            "curseforge": found.curseforge.as_ref().map(output::identifier),
+                        })
+                    })
+                    .collect_vec(),
+            }),
+        )
+        .map(|()| false);
+    }
+
+    if found.is_empty() {
+        println!(
+            "{}",
+            format!("No compatible mods found for {query}").yellow()
+        );
+        return Ok(false);
+    }
+
+    if !add {
+        for found in &found {
+            let modrinth = match &found.modrinth {
+                Some(ModIdentifier::ModrinthProject(id, _)) => format!("MR {id}"),
+                _ => String::new(),
+            };
+            let curseforge = match &found.curseforge {
+                Some(ModIdentifier::CurseForgeProject(id, _)) => format!("CF {id}"),
+                _ => String::new(),
+            };
+            println!(
+                "{}  {}  {}  {}\n  {}\n  {}",
+                found.name.bold(),
+                format!("{modrinth:11}").green(),
+                format!("{curseforge:10}").red(),
+                format!("{} downloads", compact(found.downloads)).yellow(),
+                found.summary.italic(),
+                format!("by {}", found.authors.iter().display(", ")).dimmed(),
+            );
+        }
+        return Ok(false);
+    }
+
+    let picked = MultiSelect::new("Select mods to add", found.iter().collect_vec())
+        .raw_prompt_skippable()?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|option| option.value.identifier(platform).cloned())
+        .collect_vec();
+    if picked.is_empty() {
+        return Ok(false);
+    }
+
+    let (successes, failures) =
+        libium::add(profile, picked, true, false, vec![], None, false).await?;
+    Ok(add::display_successes_failures(&successes, failures))
+}
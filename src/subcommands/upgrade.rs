@@ -1,157 +1,486 @@
+use super::changelog::{print_changelogs, Change};
 use crate::{
+    cli::OutputFormat,
     default_semaphore,
-    download::{clean, download},
-    CROSS, SEMAPHORE, STYLE_NO, TICK,
+    download::{download, find_stale, remove_stale, Stale},
+    installed::InstalledFiles,
+    output, CROSS, SEMAPHORE, STYLE_NO, TICK,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
+use clap::ValueEnum as _;
 use colored::Colorize as _;
 use indicatif::ProgressBar;
+use inquire::Confirm;
 use libium::{
+    cache,
     config::{
         filters::ProfileParameters as _,
-        structs::{Mod, ModIdentifier, ModLoader, Profile},
+        structs::{
+            Environment, Mod, ModIdentifier, ModLoader, OptionalDependencies, Profile, ProjectType,
+        },
     },
-    upgrade::{mod_downloadable, DownloadData},
+    forge,
+    iter_ext::{IterExt as _, IterExtPositions as _},
+    lockfile::{read_lockfile, write_lockfile, LockedMod, Lockfile},
+    maven, snapshot,
+    upgrade::{
+        mod_downloadable,
+        resolve::{Conflict, Files, Resolver},
+        DownloadData,
+    },
+    CURSEFORGE_API, MODRINTH_API,
 };
-use parking_lot::Mutex;
+use serde_json::{json, Value};
 use std::{
-    fs::read_dir,
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, read_dir},
+    io::{stdin, IsTerminal as _},
     mem::take,
-    sync::{mpsc, Arc},
+    path::{Path, PathBuf},
     time::Duration,
 };
 use tokio::task::JoinSet;
 
-/// Get the latest compatible downloadable for the mods in `profile`
+/// A mod that could not be resolved, and why
+pub struct Failed {
+    pub name: String,
+    pub identifier: ModIdentifier,
+    pub conflict: Conflict,
+}
+
+/// Resolve the files of `mods` and their dependencies, using the filters of `profile`
 ///
-/// If an error occurs with a resolving task, instead of failing immediately,
-/// resolution will continue and the error return flag is set to true.
-pub async fn get_platform_downloadables(profile: &Profile) -> Result<(Vec<DownloadData>, bool)> {
-    let progress_bar = Arc::new(Mutex::new(ProgressBar::new(0).with_style(STYLE_NO.clone())));
-    let mut tasks = JoinSet::new();
-    let mut done_mods = Vec::new();
-    let (mod_sender, mod_rcvr) = mpsc::channel();
-
-    // Wrap it again in an Arc so that I can count the references to it,
-    // because I cannot drop the main thread's sender due to the recursion
-    let mod_sender = Arc::new(mod_sender);
-
-    println!("{}\n", "Determining the Latest Compatible Versions".bold());
-    progress_bar
-        .lock()
-        .enable_steady_tick(Duration::from_millis(100));
-    let pad_len = profile
-        .mods
+/// The `locked` files are kept, so the files of `mods` have to be compatible with them.
+/// Returns the files to lock, and the identifiers of the `mods` that are not used in the profile's environment.
+/// If some mods cannot be resolved, instead of failing immediately,
+/// the rest of the mods are still resolved and the mods that failed are returned too.
+pub async fn get_platform_downloadables(
+    profile: &Profile,
+    mods: Vec<Mod>,
+    locked: &[LockedMod],
+    output: OutputFormat,
+) -> Result<(Vec<LockedMod>, Vec<ModIdentifier>, Vec<Failed>)> {
+    let progress_bar = ProgressBar::new(0).with_style(STYLE_NO.clone());
+    let mut resolver = Resolver::default();
+    // The mods whose files have been fetched, which are used for their names and project types
+    let mut fetched: Vec<Mod> = Vec::new();
+
+    for locked in locked {
+        resolver.insert(
+            &locked.identifier,
+            profile
+                .mods
+                .iter()
+                .find(|mod_| locked.locks(mod_))
+                .map_or_else(|| locked.filename.clone(), |mod_| mod_.name.clone()),
+            Files::Compatible(vec![locked.clone().into()]),
+            false,
+        );
+    }
+
+    output.println(format!(
+        "{}\n",
+        "Determining the Latest Compatible Versions".bold()
+    ));
+    progress_bar.enable_steady_tick(Duration::from_millis(100));
+    let pad_len = mods
         .iter()
         .map(|m| m.name.len())
         .max()
         .unwrap_or(20)
         .clamp(20, 50);
 
-    for mod_ in profile.mods.clone() {
-        mod_sender.send(mod_)?;
-    }
-
-    let mut initial = true;
-
-    // A race condition exists where if the last task drops its sender before this thread receives the message,
-    // that particular message will get ignored. I used the ostrich algorithm to solve this.
-
-    // `initial` accounts for the edge case where at first,
-    // no tasks have been spawned yet but there are messages in the channel
-    while Arc::strong_count(&mod_sender) > 1 || initial {
-        if let Ok(mod_) = mod_rcvr.try_recv() {
-            initial = false;
-
-            if done_mods.contains(&mod_.identifier) {
-                continue;
-            }
-
-            done_mods.push(mod_.identifier.clone());
-            progress_bar.lock().inc_length(1);
-
+    let roots = mods
+        .iter()
+        .map(|mod_| mod_.identifier.clone())
+        .collect_vec();
+    let mut to_fetch = mods;
+    // Fetch the files of the projects that are required, until every required project has been fetched
+    let resolution = loop {
+        if profile.environment != Environment::Both {
+            mod_downloadable::fetch_environments(&mut to_fetch).await?;
+        }
+        progress_bar.inc_length(to_fetch.len() as u64);
+        let mut tasks = JoinSet::new();
+        for mod_ in to_fetch {
             let filters = profile.filters.clone();
-            let dep_sender = Arc::clone(&mod_sender);
-            let progress_bar = Arc::clone(&progress_bar);
-
+            let environment = profile.environment;
+            let progress_bar = progress_bar.clone();
             tasks.spawn(async move {
                 let permit = SEMAPHORE.get_or_init(default_semaphore).acquire().await?;
-
-                let result = mod_.fetch_download_file(filters).await;
-
+                let result = mod_.fetch_candidates(filters, environment).await;
                 drop(permit);
 
-                progress_bar.lock().inc(1);
+                progress_bar.inc(1);
                 match result {
-                    Ok(mut download_file) => {
-                        progress_bar.lock().println(format!(
-                            "{} {:pad_len$}  {}",
-                            TICK.clone(),
-                            mod_.name,
-                            download_file.filename().dimmed()
-                        ));
-                        for dep in take(&mut download_file.dependencies) {
-                            dep_sender.send(Mod::new(
-                                format!(
-                                    "Dependency: {}",
-                                    match &dep {
-                                        ModIdentifier::CurseForgeProject(id, _) => id.to_string(),
-                                        ModIdentifier::ModrinthProject(id, _) => id.to_owned(),
-                                        ModIdentifier::GitHubRepository(..) => unreachable!(),
-                                    }
-                                ),
-                                match dep {
-                                    ModIdentifier::ModrinthProject(id, Some(_)) => {
-                                        ModIdentifier::ModrinthProject(id, None)
-                                    }
-                                    _ => dep,
-                                },
-                                vec![],
-                                false,
-                            ))?;
-                        }
-                        Ok(Some(download_file))
-                    }
-                    Err(err) => {
-                        if let mod_downloadable::Error::ModrinthError(
+                    // Immediately fail if the rate limit has been exceeded
+                    Err(
+                        err @ mod_downloadable::Error::ModrinthError(
                             ferinth::Error::RateLimitExceeded(_),
-                        ) = err
-                        {
-                            // Immediately fail if the rate limit has been exceeded
-                            progress_bar.lock().finish_and_clear();
-                            bail!(err);
-                        }
-                        progress_bar.lock().println(format!(
-                            "{}",
-                            format!("{CROSS} {:pad_len$}  {err}", mod_.name).red()
-                        ));
-                        Ok(None)
+                        ),
+                    ) => {
+                        progress_bar.finish_and_clear();
+                        bail!(err);
                     }
+                    result => Ok((mod_, result)),
                 }
             });
         }
-    }
+        for (mod_, result) in tasks
+            .join_all()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
+        {
+            resolver.insert(
+                &mod_.identifier,
+                mod_.name.clone(),
+                match result {
+                    Ok(Some(files)) => Files::Compatible(files),
+                    Ok(None) => Files::Unused,
+                    Err(err) => Files::Failed(err.to_string()),
+                },
+                mod_.optional_dependencies
+                    .unwrap_or(profile.optional_dependencies)
+                    == OptionalDependencies::Install,
+            );
+            fetched.push(mod_);
+        }
 
-    Arc::try_unwrap(progress_bar)
-        .map_err(|_| anyhow!("Failed to run threads to completion"))?
-        .into_inner()
-        .finish_and_clear();
+        let resolution = resolver.resolve(&roots);
+        if resolution.missing.is_empty() {
+            break resolution;
+        }
+        // Dependencies that are in the profile use the profile's mod, so that its filters are used
+        to_fetch = resolution
+            .missing
+            .into_iter()
+            .map(|id| {
+                profile
+                    .mods
+                    .iter()
+                    .find(|mod_| mod_.identifier.is_same_as(&id))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        Mod::new(
+                            format!(
+                                "Dependency: {}",
+                                match &id {
+                                    ModIdentifier::CurseForgeProject(id, _) => id.to_string(),
+                                    ModIdentifier::ModrinthProject(id, _) => id.to_owned(),
+                                    ModIdentifier::GitHubRepository((owner, repo), _) =>
+                                        format!("{owner}/{repo}"),
+                                    ModIdentifier::MavenArtifact(artifact, _) =>
+                                        maven::display_name(artifact),
+                                    ModIdentifier::ForgeRepository(repository, _) =>
+                                        forge::display_name(repository),
+                                    ModIdentifier::DirectUrl(url, _) => url.to_string(),
+                                    ModIdentifier::LocalPath(path) => path.display().to_string(),
+                                }
+                            ),
+                            id,
+                            vec![],
+                            false,
+                        )
+                    })
+            })
+            .collect_vec();
+    };
+    progress_bar.finish_and_clear();
 
-    let tasks = tasks
-        .join_all()
-        .await
+    let name = |id: &ModIdentifier| {
+        fetched
+            .iter()
+            .find(|mod_| mod_.identifier.is_same_as(id))
+            .map_or_else(String::new, |mod_| mod_.name.clone())
+    };
+    let mut to_lock = Vec::new();
+    for selected in resolution.selected {
+        let Some(project) = fetched
+            .iter()
+            .find(|mod_| mod_.identifier.is_same_as(&selected.project))
+        else {
+            // Locked files are already in the lockfile, but they might have new dependents
+            if let Some(locked) = locked
+                .iter()
+                .find(|locked| locked.identifier.is_same_as(&selected.project))
+            {
+                if !selected.dependents.is_empty() {
+                    to_lock.push(LockedMod {
+                        dependency_of: selected.dependents,
+                        ..locked.clone()
+                    });
+                }
+            }
+            continue;
+        };
+        output.println(format!(
+            "{} {:pad_len$}  {}",
+            TICK.clone(),
+            project.name,
+            selected.file.filename().dimmed()
+        ));
+        to_lock.extend(LockedMod::new(selected.file, project, selected.dependents));
+    }
+    let mut unused = Vec::new();
+    for id in resolution.unused {
+        // Only the mods themselves are recorded, since dependencies are resolved again with the mods that need them
+        if let Some(root) = roots.iter().find(|root| root.is_same_as(&id)) {
+            unused.push(root.clone());
+        }
+        output.println(format!(
+            "{} {:pad_len$}  {}",
+            "-".dimmed(),
+            name(&id),
+            format!(
+                "Not used on the {} side",
+                profile.environment.to_string().to_lowercase()
+            )
+            .dimmed()
+        ));
+    }
+    // Optional dependencies that can't be resolved are left out
+    for (id, conflict) in resolution.skipped {
+        output.println(format!(
+            "{} {:pad_len$}  {}",
+            "-".dimmed(),
+            name(&id),
+            format!("Optional dependency not installed: {conflict}").dimmed()
+        ));
+    }
+    let failed = resolution
+        .failed
         .into_iter()
-        .collect::<Result<Vec<_>>>()?;
+        .map(|(identifier, conflict)| Failed {
+            name: name(&identifier),
+            identifier,
+            conflict,
+        })
+        .collect_vec();
+    for failed in &failed {
+        output.println(format!("{CROSS} {:pad_len$}  {}", failed.name, failed.conflict).red());
+    }
+
+    Ok((to_lock, unused, failed))
+}
+
+/// Re-resolve the latest compatible versions of the mods in `profile` and rewrite its lockfile
+pub async fn update(profile: &mut Profile, output: OutputFormat) -> Result<()> {
+    let (resolved, unused, failed) = get_platform_downloadables(
+        profile,
+        profile
+            .mods
+            .iter()
+            .filter(|mod_| !mod_.is_dependency())
+            .cloned()
+            .collect_vec(),
+        &[],
+        output,
+    )
+    .await?;
+    let error = !failed.is_empty();
+
+    let mut lockfile = Lockfile {
+        environment: profile.environment,
+        filters: profile.filters.clone(),
+        unused,
+        ..Default::default()
+    };
+    for locked in resolved {
+        lockfile.insert(locked);
+    }
+    if let Some(mut old) = read_lockfile(&profile.output_dir)? {
+        let mut replaced = take(&mut old.replaced);
+        replaced.extend(old.mods.iter().cloned());
+        // Keep the previously locked files of mods that failed to resolve
+        if error {
+            old.retain_for(profile);
+            for locked in old.mods {
+                if !lockfile
+                    .mods
+                    .iter()
+                    .any(|new| new.identifier.is_same_as(&locked.identifier))
+                {
+                    lockfile.insert(locked);
+                }
+            }
+        }
+        // Only files that were locked are removed from directories other than the mods directory,
+        // and snapshots only know the files that were locked,
+        // so the files that aren't locked anymore are kept track of until the next upgrade
+        replaced.retain(|locked| !lockfile.owns(locked.project_type, &locked.filename));
+        lockfile.replaced = replaced;
+    }
+    write_lockfile(&profile.output_dir, &lockfile)?;
+    output.println(format!("\n{}", "Updated the lockfile".bold()));
+    record_dependencies(profile, &lockfile, false, error, output).await?;
+    check_conflicts(profile, &lockfile)?;
+    if output.is_json() {
+        output::print(
+            "update",
+            json!({
+                "resolved": resolved_json(profile, &lockfile),
+                "errors": failed_json(&failed),
+            }),
+        )?;
+    }
+
+    if error {
+        Err(anyhow!(
+            "\nCould not get the latest compatible version of some mods"
+        ))
+    } else {
+        Ok(())
+    }
+}
 
-    let error = tasks.iter().any(Option::is_none);
-    let to_download = tasks.into_iter().flatten().collect();
+/// Read the lockfile of `profile`, and resolve the mods that have not been locked yet,
+/// without writing the lockfile or checking for conflicts
+///
+/// If `offline` is set, an error listing the unlocked mods is returned instead.
+/// Returns the lockfile and the mods that could not be resolved.
+async fn resolve_lockfile(
+    profile: &Profile,
+    offline: bool,
+    output: OutputFormat,
+) -> Result<(Lockfile, Vec<Failed>)> {
+    let mut lockfile = read_lockfile(&profile.output_dir)?.unwrap_or_default();
+    let unlocked = lockfile.retain_for(profile);
 
-    Ok((to_download, error))
+    let failed = if unlocked.is_empty() {
+        Vec::new()
+    } else if offline {
+        bail!(
+            "The following mods have not been locked yet, run `ferium update` while online to lock them:\n{}",
+            unlocked
+                .iter()
+                .map(|mod_| format!("  - {}", mod_.name))
+                .display("\n")
+        );
+    } else {
+        let (resolved, unused, failed) =
+            get_platform_downloadables(profile, unlocked, &lockfile.mods, output).await?;
+        for locked in resolved {
+            lockfile.insert(locked);
+        }
+        lockfile.unused.extend(unused);
+        failed
+    };
+    Ok((lockfile, failed))
 }
 
-pub async fn upgrade(profile: &Profile) -> Result<()> {
-    let (mut to_download, error) = get_platform_downloadables(profile).await?;
-    let mut to_install = Vec::new();
+/// Record the dependencies in `lockfile` in `profile` as mods that were added implicitly,
+/// so that they can be listed, pinned, and removed like other mods
+///
+/// Dependencies that are no longer needed are removed from the profile, unless some mods could not be resolved (`error`).
+/// New dependencies are only added when not `offline`, since their names have to be looked up.
+async fn record_dependencies(
+    profile: &mut Profile,
+    lockfile: &Lockfile,
+    offline: bool,
+    error: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let dependencies = lockfile
+        .mods
+        .iter()
+        .filter(|locked| {
+            !locked.dependency_of.is_empty()
+                && !profile.mods.iter().any(|mod_| {
+                    !mod_.is_dependency() && mod_.identifier.is_same_as(&locked.identifier)
+                })
+        })
+        .collect_vec();
+
+    if !error {
+        let (unused, kept): (Vec<_>, Vec<_>) =
+            take(&mut profile.mods).into_iter().partition(|mod_| {
+                mod_.is_dependency()
+                    && !dependencies
+                        .iter()
+                        .any(|locked| locked.identifier.is_same_as(&mod_.identifier))
+            });
+        profile.mods = kept;
+        if !unused.is_empty() {
+            output.println(format!(
+                "\nRemoved dependencies that are no longer needed: {}",
+                unused.iter().map(|mod_| mod_.name.bold()).display(", ")
+            ));
+        }
+    }
+    let mut new = Vec::new();
+    for locked in dependencies {
+        if let Some(mod_) = profile
+            .mods
+            .iter_mut()
+            .find(|mod_| mod_.is_dependency() && mod_.identifier.is_same_as(&locked.identifier))
+        {
+            mod_.dependency_of.clone_from(&locked.dependency_of);
+        } else {
+            new.push(locked);
+        }
+    }
+    if offline || new.is_empty() {
+        return Ok(());
+    }
+
+    let names = project_names(new.iter().map(|locked| &locked.identifier)).await?;
+    for locked in new {
+        let (name, slug) = match &locked.identifier {
+            ModIdentifier::CurseForgeProject(id, _) => names.get(&id.to_string()),
+            ModIdentifier::ModrinthProject(id, _) => names.get(id),
+            ModIdentifier::GitHubRepository(..)
+            | ModIdentifier::MavenArtifact(..)
+            | ModIdentifier::ForgeRepository(..)
+            | ModIdentifier::DirectUrl(..)
+            | ModIdentifier::LocalPath(_) => None,
+        }
+        .cloned()
+        .map_or_else(
+            || (locked.filename.clone(), None),
+            |(name, slug)| (name, Some(slug)),
+        );
+        let mut mod_ = Mod::new(name, locked.identifier.unpinned(), vec![], false);
+        mod_.slug = slug;
+        mod_.project_type = locked.project_type;
+        mod_.dependency_of.clone_from(&locked.dependency_of);
+        profile.mods.push(mod_);
+    }
+    Ok(())
+}
+
+/// Return an error naming both mods of every pair of locked files where one has declared that it is incompatible with the other
+fn check_conflicts(profile: &Profile, lockfile: &Lockfile) -> Result<()> {
+    let name = |locked: &LockedMod| {
+        profile
+            .mods
+            .iter()
+            .find(|mod_| locked.locks(mod_))
+            .map_or_else(|| locked.filename.clone(), |mod_| mod_.name.clone())
+    };
+    let conflicts = lockfile.conflicts(profile);
+    ensure!(
+        conflicts.is_empty(),
+        "The following mods have declared that they are incompatible with other mods in the profile:\n{}\n\
+        Remove one of the mods, or set `ignore_conflicts` on the mod in the config if this is a false positive",
+        conflicts
+            .into_iter()
+            .map(|(locked, other)| format!(
+                "  - {} is incompatible with {}",
+                name(locked).bold(),
+                name(other).bold()
+            ))
+            .display("\n")
+    );
+    Ok(())
+}
+
+/// Get the user mods of `profile`, which are the jar files in the `user` folder of the output directory
+///
+/// Quilt loads these by itself, so none are returned for Quilt profiles.
+pub fn read_user_mods(profile: &Profile) -> Result<Vec<(OsString, PathBuf)>> {
+    let mut user_mods = Vec::new();
     if profile.output_dir.join("user").exists()
         && profile.filters.mod_loader() != Some(&ModLoader::Quilt)
     {
@@ -163,22 +492,249 @@ pub async fn upgrade(profile: &Profile) -> Result<()> {
                     .extension()
                     .is_some_and(|ext| ext.eq_ignore_ascii_case("jar"))
             {
-                to_install.push((file.file_name(), path));
+                user_mods.push((file.file_name(), path));
             }
         }
     }
+    Ok(user_mods)
+}
+
+/// The changes [`upgrade`] makes to the install directory of a project type
+struct Plan {
+    project_type: ProjectType,
+    directory: PathBuf,
+    to_download: Vec<DownloadData>,
+    to_install: Vec<(OsString, PathBuf)>,
+    stale: Stale,
+}
 
-    clean(&profile.output_dir, &mut to_download, &mut to_install).await?;
-    to_download
-        .iter_mut()
-        // Download directly to the output directory
-        .map(|thing| thing.output = thing.filename().into())
-        .for_each(drop); // Doesn't drop any data, just runs the iterator
-    if to_download.is_empty() && to_install.is_empty() {
-        println!("\n{}", "All up to date!".bold());
+impl Plan {
+    /// The files this plan adds and removes, printed with `--output json`
+    fn json(&self) -> Value {
+        json!({
+            "project_type": output::value_name(&self.project_type),
+            "download": self.to_download.iter().map(DownloadData::filename).collect_vec(),
+            "install": self
+                .to_install
+                .iter()
+                .map(|(name, _)| name.to_string_lossy())
+                .collect_vec(),
+            "remove": output::filenames(&self.stale.old),
+            "delete_partial": output::filenames(&self.stale.partial),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.to_download.is_empty()
+            && self.to_install.is_empty()
+            && self.stale.old.is_empty()
+            && self.stale.partial.is_empty()
+    }
+}
+
+/// Install the files locked for `profile`, resolving any mods that have not been locked yet
+///
+/// If `offline` is set, nothing is resolved or downloaded.
+/// The locked files are instead taken from the output directory, its `.old` folder, and the download cache.
+///
+/// If `dry_run` is set, the changes are only printed and nothing is written to disk.
+/// Otherwise, interactive runs print the changes and ask for confirmation before making them, unless `yes` is set.
+/// If `changelogs` is set, the changelogs of the new files of installed projects are printed before making the changes.
+/// A snapshot of the install directories is taken before they are changed, which `ferium rollback` restores.
+///
+/// With JSON `output`, the changes are printed as JSON instead and no confirmation is asked for.
+#[expect(clippy::fn_params_excessive_bools)]
+pub async fn upgrade(
+    profile: &mut Profile,
+    offline: bool,
+    dry_run: bool,
+    changelogs: bool,
+    yes: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    ensure!(
+        !(changelogs && output.is_json()),
+        "Changelogs can't be printed with JSON output, use `ferium changelog` instead"
+    );
+    let previous = read_lockfile(&profile.output_dir)?.unwrap_or_default();
+    let (mut lockfile, failed) = resolve_lockfile(profile, offline, output).await?;
+    let error = !failed.is_empty();
+    check_conflicts(profile, &lockfile)?;
+
+    let mut plans = Vec::new();
+    for &project_type in ProjectType::value_variants() {
+        let mut to_download: Vec<DownloadData> = lockfile
+            .mods
+            .iter()
+            .filter(|locked| locked.project_type == project_type)
+            .cloned()
+            .map(Into::into)
+            .collect();
+        let mut to_install = if project_type.is_mod() {
+            read_user_mods(profile)?
+        } else {
+            // Other directories are only managed if the profile has or had projects that are installed to them
+            if to_download.is_empty()
+                && !profile
+                    .mods
+                    .iter()
+                    .any(|mod_| mod_.project_type == project_type)
+                && !previous
+                    .mods
+                    .iter()
+                    .chain(&previous.replaced)
+                    .any(|locked| locked.project_type == project_type)
+            {
+                continue;
+            }
+            Vec::new()
+        };
+        let directory = project_type.install_dir(&profile.output_dir);
+        // Files that the user put in other directories are left alone
+        let stale = find_stale(
+            &directory,
+            &mut to_download,
+            &mut to_install,
+            |filename| project_type.is_mod() || previous.owns(project_type, filename),
+            output,
+        )?;
+        if offline {
+            // Fail before anything is changed if some files are not available
+            find_offline(&directory, to_download.clone(), &mut Vec::new())?;
+        }
+        plans.push(Plan {
+            project_type,
+            directory,
+            to_download,
+            to_install,
+            stale,
+        });
+    }
+    let up_to_date = plans.iter().all(Plan::is_empty);
+
+    let interactive = !yes && !output.is_json() && stdin().is_terminal();
+    if !up_to_date && !output.is_json() && (dry_run || interactive) {
+        print_plan(profile, &previous, &plans, offline);
+    }
+    let changes = plans.iter().map(Plan::json).collect_vec();
+    if changelogs && !up_to_date {
+        let installed_files = InstalledFiles::read(profile).await?;
+        let changes = plans
+            .iter()
+            .flat_map(|plan| plan.to_download.iter().map(move |file| (plan, file)))
+            .filter_map(|(plan, file)| {
+                let identifier = file.identifier.as_ref()?;
+                // Only projects that are already installed have changes to show
+                let installed = installed_files
+                    .get(identifier, plan.project_type)
+                    .filter(|installed| !installed.is(file))?;
+                let mod_ = profile
+                    .mods
+                    .iter()
+                    .find(|mod_| mod_.identifier.is_same_as(identifier))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        let mut mod_ =
+                            Mod::new(file.filename(), identifier.unpinned(), vec![], false);
+                        mod_.project_type = plan.project_type;
+                        mod_
+                    });
+                Some(Change {
+                    mod_,
+                    installed: Some(installed),
+                    file: file.clone(),
+                })
+            })
+            .collect_vec();
+        if !changes.is_empty() {
+            println!("\n{}", "Changelogs".bold());
+            // Changelogs that couldn't be fetched are reported, but they don't stop the upgrade
+            print_changelogs(profile, changes).await?;
+        }
+    }
+    let mut snapshot = None;
+    if dry_run {
+        if up_to_date {
+            output.println(format!("\n{}", "All up to date!".bold()));
+        } else {
+            output.println(format!(
+                "\n{}",
+                "This was a dry run, run `ferium upgrade` to make these changes".yellow()
+            ));
+        }
     } else {
-        println!("\n{}\n", "Downloading Mod Files".bold());
-        download(profile.output_dir.clone(), to_download, to_install).await?;
+        if !up_to_date
+            && interactive
+            && !Confirm::new("Apply these changes?")
+                .with_default(true)
+                .prompt()?
+        {
+            output.println("Upgrade cancelled, nothing was changed".yellow());
+            return Ok(());
+        }
+
+        if !up_to_date {
+            // Save the files that are about to be replaced so that the upgrade can be rolled back
+            snapshot = Some(snapshot::take(
+                &profile.output_dir,
+                &plans.iter().map(|plan| plan.project_type).collect_vec(),
+                "Before upgrading",
+            )?);
+            snapshot::prune(&profile.output_dir)?;
+        }
+
+        // The replaced files are moved to `.old` below
+        lockfile.replaced.clear();
+        write_lockfile(&profile.output_dir, &lockfile)?;
+        record_dependencies(profile, &lockfile, offline, error, output).await?;
+        for mut plan in plans {
+            if plan.is_empty() {
+                continue;
+            }
+            remove_stale(&plan.directory, plan.stale)?;
+            if offline {
+                find_offline(
+                    &plan.directory,
+                    take(&mut plan.to_download),
+                    &mut plan.to_install,
+                )?;
+            }
+            plan.to_download
+                .iter_mut()
+                // Download directly to the output directory
+                .map(|thing| thing.output = thing.filename().into())
+                .for_each(drop); // Doesn't drop any data, just runs the iterator
+            if !plan.to_download.is_empty() || !plan.to_install.is_empty() {
+                output.println(format!(
+                    "\n{}\n",
+                    format!("Downloading {} Files", plan.project_type).bold()
+                ));
+                download(plan.directory, plan.to_download, plan.to_install, output).await?;
+            }
+        }
+        match &snapshot {
+            Some(snapshot) => output.println(format!(
+                "\n{} {}",
+                format!("Saved snapshot {} of the previous files,", snapshot.id).dimmed(),
+                "run `ferium rollback` to undo this upgrade".dimmed()
+            )),
+            None => output.println(format!("\n{}", "All up to date!".bold())),
+        }
+        suggest(profile, &lockfile, offline, output).await?;
+    }
+    if output.is_json() {
+        output::print(
+            "upgrade",
+            json!({
+                "dry_run": dry_run,
+                "offline": offline,
+                "up_to_date": up_to_date,
+                "snapshot": snapshot.map(|snapshot| snapshot.id),
+                "resolved": resolved_json(profile, &lockfile),
+                "changes": changes,
+                "errors": failed_json(&failed),
+            }),
+        )?;
     }
 
     if error {
@@ -189,3 +745,326 @@ pub async fn upgrade(profile: &Profile) -> Result<()> {
         Ok(())
     }
 }
+
+/// The files in `lockfile` with the names of their mods, printed with `--output json`
+fn resolved_json(profile: &Profile, lockfile: &Lockfile) -> Vec<Value> {
+    lockfile
+        .mods
+        .iter()
+        .map(|locked| {
+            output::locked(
+                locked,
+                profile
+                    .mods
+                    .iter()
+                    .find(|mod_| locked.locks(mod_))
+                    .map_or(&locked.filename, |mod_| &mod_.name),
+            )
+        })
+        .collect_vec()
+}
+
+fn failed_json(failed: &[Failed]) -> Vec<Value> {
+    failed
+        .iter()
+        .map(|failed| output::failure(&failed.name, &failed.identifier, &failed.conflict))
+        .collect_vec()
+}
+
+/// Print the changes `plans` make, using the `previous` lockfile to show which files are replaced by newer versions
+fn print_plan(profile: &Profile, previous: &Lockfile, plans: &[Plan], offline: bool) {
+    let name_of = |identifier: &ModIdentifier| {
+        profile
+            .mods
+            .iter()
+            .find(|mod_| mod_.identifier.is_same_as(identifier))
+            .map(|mod_| mod_.name.clone())
+    };
+    // The project of a file that is in the install directory, if it was locked
+    let previous_project = |path: &PathBuf| {
+        previous
+            .mods
+            .iter()
+            .find(|locked| {
+                path.file_name()
+                    .is_some_and(|name| *name == *locked.filename)
+            })
+            .map(|locked| &locked.identifier)
+    };
+
+    let (mut size, mut files, mut cached) = (0, 0, 0);
+    for plan in plans.iter().filter(|plan| !plan.is_empty()) {
+        println!("\n{}\n", format!("{} Changes", plan.project_type).bold());
+        let mut old = plan.stale.old.iter().collect_vec();
+        for downloadable in &plan.to_download {
+            let name = downloadable
+                .identifier
+                .as_ref()
+                .and_then(name_of)
+                .unwrap_or_else(|| downloadable.filename());
+            // A file of the same project that is replaced by this one,
+            // which is guessed from the filename if it isn't in the previous lockfile (e.g. after `ferium update`)
+            let replaced = old
+                .iter()
+                .position(|path| {
+                    previous_project(path).is_some_and(|previous| {
+                        downloadable
+                            .identifier
+                            .as_ref()
+                            .is_some_and(|identifier| identifier.is_same_as(previous))
+                    })
+                })
+                .or_else(|| {
+                    let filename = downloadable.filename();
+                    let mut same_name = old.iter().enumerate().positions(|path| {
+                        previous_project(path).is_none()
+                            && path.file_name().is_some_and(|old_filename| {
+                                same_unversioned_name(&old_filename.to_string_lossy(), &filename)
+                            })
+                    });
+                    same_name.next().filter(|_| same_name.next().is_none())
+                });
+            if let Some(index) = replaced {
+                let path = old.remove(index);
+                println!(
+                    "  {} {}  {} -> {}",
+                    "~".yellow(),
+                    name.bold(),
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .dimmed(),
+                    downloadable.filename().dimmed(),
+                );
+            } else {
+                println!(
+                    "  {} {}  {}",
+                    "+".green(),
+                    name.bold(),
+                    downloadable.filename().dimmed()
+                );
+            }
+            if cache::get(&downloadable.hashes).is_some() {
+                cached += 1;
+            } else {
+                size += downloadable.length;
+            }
+            files += 1;
+        }
+        for (name, _) in &plan.to_install {
+            println!(
+                "  {} {}  {}",
+                "+".green(),
+                name.to_string_lossy().bold(),
+                "from the user folder".dimmed()
+            );
+        }
+        for path in old {
+            let filename = path.file_name().unwrap_or_default().to_string_lossy();
+            match previous_project(path).and_then(name_of) {
+                Some(name) => println!(
+                    "  {} {}  {}",
+                    "-".red(),
+                    name.bold(),
+                    format!("{filename} will be moved to .old").dimmed()
+                ),
+                None => println!(
+                    "  {} {}  {}",
+                    "-".red(),
+                    filename.bold(),
+                    "will be moved to .old".dimmed()
+                ),
+            }
+        }
+        for path in &plan.stale.partial {
+            println!(
+                "  {} {}  {}",
+                "-".red(),
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .bold(),
+                "partial download will be deleted".dimmed()
+            );
+        }
+    }
+    if files > 0 && offline {
+        println!(
+            "\n{} files will be taken from the `.old` folder and the cache",
+            files.to_string().yellow()
+        );
+    } else if files > 0 {
+        println!(
+            "\nDownload size: {} ({files} files, {cached} from the cache)",
+            size::Size::from_bytes(size)
+                .format()
+                .with_base(size::Base::Base10)
+                .to_string()
+                .yellow()
+        );
+    }
+}
+
+/// Whether the filenames `a` and `b` are the same up to their version numbers,
+/// e.g. `sodium-fabric-0.5.8.jar` and `sodium-fabric-0.6.0.jar`
+fn same_unversioned_name(a: &str, b: &str) -> bool {
+    let unversioned = |filename: &str| {
+        filename
+            .split(|c: char| c.is_ascii_digit())
+            .next()
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    let name = unversioned(a);
+    name.len() >= 3 && name == unversioned(b)
+}
+
+/// Print the optional dependencies of the locked files that aren't in `lockfile`,
+/// for mods that have their optional dependencies suggested
+///
+/// The names of the suggested projects are only shown when not `offline`.
+async fn suggest(
+    profile: &Profile,
+    lockfile: &Lockfile,
+    offline: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    let mut suggested: Vec<(ModIdentifier, Vec<String>)> = Vec::new();
+    for locked in &lockfile.mods {
+        let mod_ = profile.mods.iter().find(|mod_| locked.locks(mod_));
+        if mod_
+            .and_then(|mod_| mod_.optional_dependencies)
+            .unwrap_or(profile.optional_dependencies)
+            != OptionalDependencies::Suggest
+        {
+            continue;
+        }
+        let name = mod_.map_or_else(|| locked.filename.clone(), |mod_| mod_.name.clone());
+        for dependency in &locked.optional_dependencies {
+            if lockfile
+                .mods
+                .iter()
+                .any(|locked| locked.identifier.is_same_as(dependency))
+            {
+                continue;
+            }
+            if let Some((_, suggested_by)) = suggested
+                .iter_mut()
+                .find(|(id, _)| id.is_same_as(dependency))
+            {
+                suggested_by.push(name.clone());
+            } else {
+                suggested.push((dependency.clone(), vec![name.clone()]));
+            }
+        }
+    }
+    if suggested.is_empty() {
+        return Ok(());
+    }
+
+    let names = if offline {
+        HashMap::new()
+    } else {
+        project_names(suggested.iter().map(|(id, _)| id)).await?
+    };
+
+    output.println(format!("\n{}\n", "Suggested Optional Dependencies".bold()));
+    for (id, suggested_by) in suggested {
+        let id = match id {
+            ModIdentifier::CurseForgeProject(id, _) => id.to_string(),
+            ModIdentifier::ModrinthProject(id, _) => id,
+            ModIdentifier::GitHubRepository((owner, repo), _) => format!("{owner}/{repo}"),
+            ModIdentifier::MavenArtifact(artifact, _) => maven::display_name(&artifact),
+            ModIdentifier::ForgeRepository(repository, _) => forge::display_name(&repository),
+            ModIdentifier::DirectUrl(url, _) => url.to_string(),
+            ModIdentifier::LocalPath(path) => path.display().to_string(),
+        };
+        output.println(format!(
+            "  {}  {}",
+            match names.get(&id) {
+                Some((name, _)) => format!("{} {}", name.bold(), id.dimmed()),
+                None => id,
+            },
+            format!("for {}", suggested_by.iter().display(", ")).dimmed(),
+        ));
+    }
+    output.println(format!(
+        "\n{}",
+        "Add the ones you want using `ferium add`, or set the optional dependencies option to `ignore` to hide these"
+            .yellow()
+    ));
+    Ok(())
+}
+
+/// Look up the names and slugs of the Modrinth and Curseforge projects in `ids`, keyed by their project IDs
+async fn project_names(
+    ids: impl Iterator<Item = &ModIdentifier> + Clone,
+) -> Result<HashMap<String, (String, String)>> {
+    let mut names = HashMap::new();
+    let mr_ids = ids
+        .clone()
+        .filter_map(|id| match id {
+            ModIdentifier::ModrinthProject(id, _) => Some(id.as_str()),
+            _ => None,
+        })
+        .collect_vec();
+    if !mr_ids.is_empty() {
+        for project in MODRINTH_API.project_get_multiple(&mr_ids).await? {
+            names.insert(project.id, (project.title, project.slug));
+        }
+    }
+    let cf_ids = ids
+        .filter_map(|id| match id {
+            ModIdentifier::CurseForgeProject(id, _) => Some(*id),
+            _ => None,
+        })
+        .collect_vec();
+    if !cf_ids.is_empty() {
+        for project in CURSEFORGE_API.get_mods(cf_ids).await? {
+            names.insert(project.id.to_string(), (project.name, project.slug));
+        }
+    }
+    Ok(names)
+}
+
+/// Find the files in `to_download` in the `.old` folder of `output_dir`, in the download cache,
+/// or for local files at their paths, and add them to `to_install`
+///
+/// Returns an error listing the files that could not be found.
+fn find_offline(
+    output_dir: &Path,
+    to_download: Vec<DownloadData>,
+    to_install: &mut Vec<(OsString, PathBuf)>,
+) -> Result<()> {
+    let mut missing = Vec::new();
+    for downloadable in to_download {
+        let filename = downloadable.filename();
+        let old_file = output_dir.join(".old").join(&filename);
+        if old_file.is_file()
+            && fs::metadata(&old_file)?.len() == downloadable.length as u64
+            && downloadable.hashes.verify_file(&old_file).is_ok()
+        {
+            to_install.push((filename.into(), old_file));
+        } else if let Some(cached) = cache::get_verified(&downloadable.hashes)? {
+            to_install.push((filename.into(), cached));
+        } else if let Some(local_file) = downloadable
+            .download_url
+            .to_file_path()
+            .ok()
+            .filter(|path| downloadable.hashes.verify_file(path).is_ok())
+        {
+            to_install.push((filename.into(), local_file));
+        } else {
+            missing.push(filename);
+        }
+    }
+    ensure!(
+        missing.is_empty(),
+        "The following files are not available offline:\n{}",
+        missing
+            .iter()
+            .map(|filename| format!("  - {filename}"))
+            .display("\n")
+    );
+    Ok(())
+}
@@ -2,14 +2,35 @@
 
 use crate::{
     actual_main,
-    cli::{Ferium, FilterArguments, ModpackSubCommands, Platform, ProfileSubCommands, SubCommands},
+    cli::{
+        ExportFormat, Ferium, FilterArguments, ModpackSubCommands, OutputFormat, Platform,
+        ProfileSubCommands, SubCommands,
+    },
+    output,
+};
+use clap::Parser as _;
+use libium::{
+    config::{
+        filters::{Filter, ReleaseChannel},
+        read_config,
+        structs::{
+            Config, Environment, Forge, ForgeRepository, MavenArtifact, Mod, ModIdentifier,
+            ModLoader, OptionalDependencies, Profile, ProjectType,
+        },
+        write_config,
+    },
+    lockfile::{read_lockfile, write_lockfile, LockedMod, Lockfile},
+    snapshot,
+    upgrade::{validator_path, DownloadData, Hashes},
 };
-use libium::config::structs::ModLoader;
 use std::{
     assert_matches,
     env::current_dir,
-    fs::{copy, create_dir_all},
-    path::PathBuf,
+    fs::{copy, create_dir_all, read_dir, read_to_string, write},
+    io::{Read as _, Write as _},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    thread,
 };
 
 const DEFAULT: Ferium = Ferium {
@@ -17,8 +38,10 @@ const DEFAULT: Ferium = Ferium {
     threads: None,
     parallel_tasks: 10,
     github_token: None,
+    forge_tokens: Vec::new(),
     curseforge_api_key: None,
     config_file: None,
+    output_format: OutputFormat::Human,
 };
 
 fn get_args(subcommand: SubCommands, config_file: Option<&str>) -> Ferium {
@@ -38,6 +61,74 @@ fn get_args(subcommand: SubCommands, config_file: Option<&str>) -> Ferium {
     }
 }
 
+/// Get the arguments for running `subcommand` with a config that only has `profile`,
+/// and the new directory in `tests/mods` that the profile outputs to
+fn get_args_with_profile(subcommand: SubCommands, mut profile: Profile) -> (Ferium, PathBuf) {
+    let args = get_args(subcommand, None);
+    profile.output_dir = current_dir()
+        .unwrap()
+        .join("tests")
+        .join("mods")
+        .join(format!("{:X}", rand::random::<u32>()))
+        .join("mods");
+    create_dir_all(&profile.output_dir).unwrap();
+    let output_dir = profile.output_dir.clone();
+    write_config(
+        args.config_file.as_ref().unwrap(),
+        &Config {
+            profiles: vec![profile],
+            ..Config::default()
+        },
+    )
+    .unwrap();
+    (args, output_dir)
+}
+
+/// A profile with a mod from a file in `tests/test_mods`
+fn local_profile(environment: Environment, filename: &str) -> Profile {
+    let mut profile = Profile::new(
+        "Local Profile".to_owned(),
+        PathBuf::new(),
+        vec!["1.21.4".to_owned()],
+        ModLoader::Fabric,
+    );
+    profile.environment = environment;
+    profile.mods.push(Mod::new(
+        filename.to_owned(),
+        ModIdentifier::LocalPath(
+            current_dir()
+                .unwrap()
+                .join("tests")
+                .join("test_mods")
+                .join(filename),
+        ),
+        vec![],
+        false,
+    ));
+    profile
+}
+
+/// The paths of the files and folders in `directory` and its subfolders, sorted
+fn directory_contents(directory: &Path) -> Vec<PathBuf> {
+    let mut contents = Vec::new();
+    for entry in read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            contents.extend(directory_contents(&path));
+        }
+        contents.push(path);
+    }
+    contents.sort();
+    contents
+}
+
+const UPGRADE_OFFLINE: SubCommands = SubCommands::Upgrade {
+    offline: true,
+    dry_run: false,
+    changelogs: false,
+    yes: true,
+};
+
 // TODO
 // #[tokio::test(flavor = "multi_thread")]
 // async fn arg_parse() {}
@@ -54,6 +145,8 @@ async fn create_profile_no_profiles_to_import() {
                     mod_loader: Some(ModLoader::Fabric),
                     name: Some("Test Profile".to_owned()),
                     output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    environment: None,
+                    optional_dependencies: None,
                 })
             },
             None,
@@ -75,6 +168,8 @@ async fn create_profile_rel_dir() {
                     mod_loader: Some(ModLoader::Fabric),
                     name: Some("Test Profile".to_owned()),
                     output_dir: Some(PathBuf::from(".").join("tests").join("mods")),
+                    environment: None,
+                    optional_dependencies: None,
                 })
             },
             None,
@@ -96,6 +191,8 @@ async fn create_profile_import_mods() {
                     mod_loader: Some(ModLoader::Fabric),
                     name: Some("Test Profile".to_owned()),
                     output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    environment: None,
+                    optional_dependencies: None,
                 })
             },
             Some("one_profile_full"),
@@ -115,7 +212,9 @@ async fn create_profile_existing_name() {
                     game_version: vec!["1.21.4".to_owned()],
                     mod_loader: Some(ModLoader::Fabric),
                     name: Some("Default Modded".to_owned()),
-                    output_dir: Some(current_dir().unwrap().join("tests").join("mods"))
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    environment: None,
+                    optional_dependencies: None,
                 })
             },
             None,
@@ -135,7 +234,31 @@ async fn create_profile() {
                     game_version: vec!["1.21.4".to_owned()],
                     mod_loader: Some(ModLoader::Fabric),
                     name: Some("Test Profile".to_owned()),
-                    output_dir: Some(current_dir().unwrap().join("tests").join("mods"))
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    environment: None,
+                    optional_dependencies: None,
+                })
+            },
+            None,
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_server_profile() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Create {
+                    import: None,
+                    game_version: vec!["1.21.4".to_owned()],
+                    mod_loader: Some(ModLoader::Fabric),
+                    name: Some("Test Server".to_owned()),
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    environment: Some(Environment::Server),
+                    optional_dependencies: None,
                 })
             },
             None,
@@ -145,6 +268,27 @@ async fn create_profile() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn configure_optional_dependencies() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Configure {
+                    game_versions: vec![],
+                    mod_loaders: vec![],
+                    name: None,
+                    output_dir: None,
+                    environment: None,
+                    optional_dependencies: Some(OptionalDependencies::Suggest),
+                })
+            },
+            Some("one_profile_full"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn add_modrinth() {
     assert_matches!(
@@ -152,6 +296,10 @@ async fn add_modrinth() {
             SubCommands::Add {
                 identifiers: vec!["starlight".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
             Some("empty_profile"),
@@ -162,12 +310,16 @@ async fn add_modrinth() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_curseforge() {
+async fn add_shader_pack() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Add {
-                identifiers: vec!["591388".to_owned()],
+                identifiers: vec!["complementary-reimagined".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
             Some("empty_profile"),
@@ -178,12 +330,16 @@ async fn add_curseforge() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_github() {
+async fn add_curseforge() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Add {
-                identifiers: vec!["CaffeineMC/sodium".to_owned()],
+                identifiers: vec!["591388".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
             Some("empty_profile"),
@@ -194,16 +350,16 @@ async fn add_github() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_all() {
+async fn add_github() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Add {
-                identifiers: vec![
-                    "starlight".to_owned(),
-                    "591388".to_owned(),
-                    "CaffeineMC/sodium".to_owned()
-                ],
+                identifiers: vec!["CaffeineMC/sodium".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
             Some("empty_profile"),
@@ -214,19 +370,19 @@ async fn add_all() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn already_added() {
+async fn add_modrinth_url() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Add {
-                identifiers: vec![
-                    "starlight".to_owned(),
-                    "591388".to_owned(),
-                    "CaffeineMC/sodium".to_owned()
-                ],
+                identifiers: vec!["https://modrinth.com/mod/starlight".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
-            Some("one_profile_full"),
+            Some("empty_profile"),
         ))
         .await,
         Ok(()),
@@ -234,16 +390,16 @@ async fn already_added() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_all_pinned() {
+async fn add_curseforge_prefix() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Add {
-                identifiers: vec![
-                    "starlight:HZYU0kdg".to_owned(),
-                    "591388:6713391".to_owned(),
-                    "CaffeineMC/sodium:RA_kwDODijHac4Kh-Lc".to_owned()
-                ],
+                identifiers: vec!["cf:591388".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
             Some("empty_profile"),
@@ -254,53 +410,59 @@ async fn add_all_pinned() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn add_all_invalid_pins() {
+async fn add_github_url() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Add {
-                identifiers: vec![
-                    "starlight:ihzX2Dvy".to_owned(),
-                    "591388:4947005".to_owned(),
-                    "CaffeineMC/sodium:kwDODijHac4Kh".to_owned()
-                ],
+                identifiers: vec!["https://github.com/CaffeineMC/sodium".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
                 filters: FilterArguments::default(),
             },
             Some("empty_profile"),
         ))
         .await,
-        Err(_),
+        Ok(()),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn scan() {
+async fn add_unsupported_url() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Scan {
-                platform: Platform::default(),
-                directory: Some(current_dir().unwrap().join("tests").join("test_mods")),
+            SubCommands::Add {
+                identifiers: vec!["http://example.com/mod/starlight".to_owned()],
                 force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
             Some("empty_profile"),
         ))
         .await,
-        Ok(()),
+        Err(_),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn modpack_add_modrinth() {
+async fn add_local_file() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Modpack {
-                subcommand: Some(ModpackSubCommands::Add {
-                    identifier: "1KVo5zza".to_owned(),
-                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
-                    install_overrides: Some(true),
-                })
+            SubCommands::Add {
+                identifiers: vec!["./tests/test_mods/Sodium.jar".to_owned()],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("empty")
+            Some("empty_profile"),
         ))
         .await,
         Ok(()),
@@ -308,17 +470,19 @@ async fn modpack_add_modrinth() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn modpack_add_curseforge() {
+async fn add_direct_url() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Modpack {
-                subcommand: Some(ModpackSubCommands::Add {
-                    identifier: "452013".to_owned(),
-                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
-                    install_overrides: Some(true),
-                })
+            SubCommands::Add {
+                identifiers: vec!["https://example.com/mods/example.jar".to_owned()],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: Some("da39a3ee5e6b4b0d3255bfef95601890afd80709".to_owned()),
+                filters: FilterArguments::default(),
             },
-            Some("empty")
+            Some("empty_profile"),
         ))
         .await,
         Ok(()),
@@ -326,14 +490,19 @@ async fn modpack_add_curseforge() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list_no_profile() {
+async fn add_direct_url_without_hash() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::List {
-                verbose: false,
-                markdown: false
+            SubCommands::Add {
+                identifiers: vec!["https://example.com/mods/example.jar".to_owned()],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("empty"),
+            Some("empty_profile"),
         ))
         .await,
         Err(_),
@@ -341,105 +510,186 @@ async fn list_no_profile() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list_empty_profile() {
+async fn add_maven_artifact() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::List {
-                verbose: false,
-                markdown: false
+            SubCommands::Add {
+                identifiers: vec![
+                    "maven:https://maven.fabricmc.net/net.fabricmc.fabric-api:fabric-api"
+                        .to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
             Some("empty_profile"),
         ))
         .await,
-        Err(_),
+        Ok(()),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list() {
+async fn add_maven_artifact_with_classifier() {
+    let args = get_args(
+        SubCommands::Add {
+            identifiers: vec![
+                "maven:https://maven.fabricmc.net/net.fabricmc.fabric-api:fabric-api:0.92.2+1.20.1:sources"
+                    .to_owned(),
+            ],
+            force: false,
+            environment: None,
+            optional_dependencies: None,
+            ignore_conflicts: false,
+            hash: None,
+            filters: FilterArguments::default(),
+        },
+        Some("empty_profile"),
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    let config = read_config(args.config_file.unwrap()).unwrap();
     assert_matches!(
-        actual_main(get_args(
-            SubCommands::List {
-                verbose: false,
-                markdown: false
+        &config.profiles[0].mods[0].identifier,
+        ModIdentifier::MavenArtifact(
+            MavenArtifact {
+                classifier: Some(classifier),
+                ..
             },
-            Some("one_profile_full"),
-        ))
-        .await,
-        Ok(()),
+            Some(version),
+        ) if classifier == "sources" && version == "0.92.2+1.20.1"
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list_verbose() {
+async fn add_maven_artifact_with_empty_classifier() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::List {
-                verbose: true,
-                markdown: false
+            SubCommands::Add {
+                identifiers: vec![
+                    "maven:https://maven.fabricmc.net/net.fabricmc.fabric-api:fabric-api::"
+                        .to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("one_profile_full"),
+            Some("empty_profile"),
         ))
         .await,
-        Ok(()),
+        Err(_),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list_markdown() {
+async fn add_maven_artifact_over_http() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::List {
-                verbose: true,
-                markdown: true
+            SubCommands::Add {
+                identifiers: vec![
+                    "maven:http://maven.fabricmc.net/net.fabricmc.fabric-api:fabric-api".to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("one_profile_full"),
+            Some("empty_profile"),
         ))
         .await,
-        Ok(()),
+        Err(_),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list_profiles() {
+async fn add_forge_repository_over_http() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Profiles,
-            Some("two_profiles_one_empty"),
+            SubCommands::Add {
+                identifiers: vec!["gitlab:http://gitlab.example.com/owner/repo".to_owned()],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
+            },
+            Some("empty_profile"),
         ))
         .await,
-        Ok(()),
+        Err(_),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn list_modpacks() {
+async fn add_forge_repository_without_owner() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Modpacks,
-            Some("two_modpacks_mdactive"),
+            SubCommands::Add {
+                identifiers: vec!["forgejo:repo".to_owned()],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
+            },
+            Some("empty_profile"),
         ))
         .await,
-        Ok(()),
+        Err(_),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn upgrade() {
+async fn add_all() {
     assert_matches!(
-        actual_main(get_args(SubCommands::Upgrade, Some("one_profile_full"))).await,
+        actual_main(get_args(
+            SubCommands::Add {
+                identifiers: vec![
+                    "starlight".to_owned(),
+                    "591388".to_owned(),
+                    "CaffeineMC/sodium".to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
+            },
+            Some("empty_profile"),
+        ))
+        .await,
         Ok(()),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn upgrade_md_modpacks() {
+async fn already_added() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Modpack {
-                subcommand: Some(ModpackSubCommands::Upgrade)
+            SubCommands::Add {
+                identifiers: vec![
+                    "starlight".to_owned(),
+                    "591388".to_owned(),
+                    "CaffeineMC/sodium".to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("two_modpacks_mdactive")
+            Some("one_profile_full"),
         ))
         .await,
         Ok(()),
@@ -447,13 +697,23 @@ async fn upgrade_md_modpacks() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn upgrade_cf_modpack() {
+async fn add_all_pinned() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Modpack {
-                subcommand: Some(ModpackSubCommands::Upgrade)
+            SubCommands::Add {
+                identifiers: vec![
+                    "starlight:HZYU0kdg".to_owned(),
+                    "591388:6713391".to_owned(),
+                    "CaffeineMC/sodium:RA_kwDODijHac4Kh-Lc".to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("two_modpacks_cfactive")
+            Some("empty_profile"),
         ))
         .await,
         Ok(()),
@@ -461,23 +721,1070 @@ async fn upgrade_cf_modpack() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn profile_switch() {
+async fn add_all_invalid_pins() {
     assert_matches!(
         actual_main(get_args(
-            SubCommands::Profile {
-                subcommand: Some(ProfileSubCommands::Switch {
-                    profile_name: Some("Profile Two".to_owned())
-                })
+            SubCommands::Add {
+                identifiers: vec![
+                    "starlight:ihzX2Dvy".to_owned(),
+                    "591388:4947005".to_owned(),
+                    "CaffeineMC/sodium:kwDODijHac4Kh".to_owned()
+                ],
+                force: false,
+                environment: None,
+                optional_dependencies: None,
+                ignore_conflicts: false,
+                hash: None,
+                filters: FilterArguments::default(),
             },
-            Some("two_profiles_one_empty")
+            Some("empty_profile"),
         ))
         .await,
-        Ok(()),
+        Err(_),
     );
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn modpack_switch() {
+async fn scan() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Scan {
+                platform: Platform::default(),
+                directory: Some(current_dir().unwrap().join("tests").join("test_mods")),
+                force: false,
+            },
+            Some("empty_profile"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn modpack_add_modrinth() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Modpack {
+                subcommand: Some(ModpackSubCommands::Add {
+                    identifier: "1KVo5zza".to_owned(),
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    install_overrides: Some(true),
+                })
+            },
+            Some("empty")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn modpack_add_curseforge() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Modpack {
+                subcommand: Some(ModpackSubCommands::Add {
+                    identifier: "452013".to_owned(),
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                    install_overrides: Some(true),
+                })
+            },
+            Some("empty")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_no_profile() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: false,
+                markdown: false
+            },
+            Some("empty"),
+        ))
+        .await,
+        Err(_),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_empty_profile() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Export {
+                    format: ExportFormat::Mrpack,
+                    file: None,
+                    pack_version: "1.0.0".to_owned(),
+                    force: false,
+                })
+            },
+            Some("empty_profile"),
+        ))
+        .await,
+        Err(_),
+    );
+}
+
+#[test]
+fn export_file_with_output_format() {
+    let cli = Ferium::try_parse_from([
+        "ferium",
+        "--output",
+        "json",
+        "profile",
+        "export",
+        "--file",
+        "pack.mrpack",
+    ])
+    .unwrap();
+    assert_eq!(cli.output_format, OutputFormat::Json);
+    assert_matches!(
+        cli.subcommand,
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Export {
+                file: Some(file),
+                ..
+            })
+        } if file == Path::new("pack.mrpack")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_without_lockfile() {
+    let (args, output_dir) = get_args_with_profile(
+        SubCommands::Profile {
+            subcommand: Some(ProfileSubCommands::Export {
+                format: ExportFormat::Mrpack,
+                file: None,
+                pack_version: "1.0.0".to_owned(),
+                force: false,
+            }),
+        },
+        local_profile(Environment::Both, "Sodium.jar"),
+    );
+    assert_matches!(actual_main(args).await, Err(_));
+    // Exporting doesn't resolve and lock the profile's mods
+    assert!(directory_contents(&output_dir).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_packwiz_to_non_empty_directory() {
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::Update,
+        local_profile(Environment::Both, "Sodium.jar"),
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    let pack_dir = output_dir.parent().unwrap().join("pack");
+    create_dir_all(pack_dir.join("mods")).unwrap();
+    write(pack_dir.join("mods").join("lithium.pw.toml"), "").unwrap();
+    args.subcommand = SubCommands::Profile {
+        subcommand: Some(ProfileSubCommands::Export {
+            format: ExportFormat::Packwiz,
+            file: Some(pack_dir.clone()),
+            pack_version: "1.0.0".to_owned(),
+            force: false,
+        }),
+    };
+    assert_matches!(actual_main(args).await, Err(_));
+    assert!(pack_dir.join("mods").join("lithium.pw.toml").is_file());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_import_packwiz() {
+    let mut profile = local_profile(Environment::Both, "Sodium.jar");
+    profile.mods = vec![Mod::new(
+        "Sodium".to_owned(),
+        ModIdentifier::ModrinthProject("AANobbMI".to_owned(), None),
+        vec![],
+        false,
+    )];
+    let (mut args, output_dir) = get_args_with_profile(SubCommands::Update, profile);
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    let pack_dir = output_dir.parent().unwrap().join("pack");
+    args.subcommand = SubCommands::Profile {
+        subcommand: Some(ProfileSubCommands::Export {
+            format: ExportFormat::Packwiz,
+            file: Some(pack_dir.clone()),
+            pack_version: "1.0.0".to_owned(),
+            force: false,
+        }),
+    };
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    args.subcommand = SubCommands::Profile {
+        subcommand: Some(ProfileSubCommands::Import {
+            source: pack_dir.display().to_string(),
+            name: Some("Imported".to_owned()),
+            output_dir: Some(output_dir.parent().unwrap().join("imported")),
+        }),
+    };
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    let config = read_config(args.config_file.unwrap()).unwrap();
+    let imported = config
+        .profiles
+        .iter()
+        .find(|profile| profile.name == "Imported")
+        .unwrap();
+    assert_matches!(
+        imported.mods.as_slice(),
+        [Mod {
+            identifier: ModIdentifier::ModrinthProject(id, Some(_)),
+            ..
+        }] if id == "AANobbMI"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_not_a_modpack() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Import {
+                    source: "./tests/configs/empty.json".to_owned(),
+                    name: None,
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                })
+            },
+            Some("empty"),
+        ))
+        .await,
+        Err(_),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_packwiz() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Import {
+                    source: "./tests/packwiz".to_owned(),
+                    name: None,
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                })
+            },
+            Some("empty"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_packwiz_outside_instance() {
+    let instance_dir = current_dir()
+        .unwrap()
+        .join("tests")
+        .join("mods")
+        .join(format!("{:X}", rand::random::<u32>()));
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Import {
+                    source: "./tests/packwiz_escape".to_owned(),
+                    name: None,
+                    output_dir: Some(instance_dir.join("mods")),
+                })
+            },
+            Some("empty"),
+        ))
+        .await,
+        Err(_),
+    );
+    assert!(!instance_dir.join("tests").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn import_modpack_outside_instance() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Import {
+                    source: "./tests/escape.mrpack".to_owned(),
+                    name: None,
+                    output_dir: Some(current_dir().unwrap().join("tests").join("mods")),
+                })
+            },
+            Some("empty"),
+        ))
+        .await,
+        Err(err) if err.to_string().contains("outside the instance"),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_empty_profile() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: false,
+                markdown: false
+            },
+            Some("empty_profile"),
+        ))
+        .await,
+        Err(_),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: false,
+                markdown: false
+            },
+            Some("one_profile_full"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_dependencies() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: false,
+                markdown: false
+            },
+            Some("one_profile_dependencies"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_project_types() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: false,
+                markdown: false
+            },
+            Some("one_profile_packs"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_verbose() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: true,
+                markdown: false
+            },
+            Some("one_profile_full"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_markdown() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::List {
+                verbose: true,
+                markdown: true
+            },
+            Some("one_profile_full"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_profiles() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profiles,
+            Some("two_profiles_one_empty"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_modpacks() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Modpacks,
+            Some("two_modpacks_mdactive"),
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[test]
+fn filters_json() {
+    assert_eq!(
+        [
+            Filter::ModLoaderAny(vec![ModLoader::Fabric, ModLoader::Quilt]),
+            Filter::GameVersionMinor(vec!["1.21.4".to_owned()]),
+            Filter::ReleaseChannel(ReleaseChannel::Beta),
+            Filter::Filename("sodium".to_owned()),
+        ]
+        .iter()
+        .map(output::filter)
+        .collect::<Vec<_>>(),
+        [
+            serde_json::json!({ "type": "mod_loader_any", "mod_loaders": ["fabric", "quilt"] }),
+            serde_json::json!({ "type": "game_version_minor", "game_versions": ["1.21.4"] }),
+            serde_json::json!({ "type": "release_channel", "release_channel": "beta" }),
+            serde_json::json!({ "type": "filename", "regex": "sodium" }),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(
+                SubCommands::List {
+                    verbose: false,
+                    markdown: false
+                },
+                Some("one_profile_dependencies"),
+            )
+        })
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_markdown_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(
+                SubCommands::List {
+                    verbose: true,
+                    markdown: true
+                },
+                Some("one_profile_full"),
+            )
+        })
+        .await,
+        Err(_),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_profiles_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(SubCommands::Profiles, Some("two_profiles_one_empty"))
+        })
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_modpacks_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(SubCommands::Modpacks, Some("two_modpacks_mdactive"))
+        })
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Upgrade {
+                offline: false,
+                dry_run: false,
+                changelogs: false,
+                yes: true,
+            },
+            Some("one_profile_full")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_dry_run() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Upgrade {
+                offline: false,
+                dry_run: true,
+                changelogs: false,
+                yes: false,
+            },
+            Some("one_profile_full")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_dry_run_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(
+                SubCommands::Upgrade {
+                    offline: false,
+                    dry_run: true,
+                    changelogs: false,
+                    yes: false,
+                },
+                Some("one_profile_full")
+            )
+        })
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update() {
+    assert_matches!(
+        actual_main(get_args(SubCommands::Update, Some("one_profile_full"))).await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_ignoring_conflicts() {
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::Update,
+        local_profile(Environment::Both, "Starlight.jar"),
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    // Declare that the locked file is incompatible with Sodium
+    let mut lockfile = read_lockfile(&output_dir).unwrap().unwrap();
+    lockfile.mods[0].conflicts = vec![ModIdentifier::ModrinthProject("AANobbMI".to_owned(), None)];
+    write_lockfile(&output_dir, &lockfile).unwrap();
+
+    args.subcommand = SubCommands::Add {
+        identifiers: vec!["AANobbMI".to_owned()],
+        force: false,
+        environment: None,
+        optional_dependencies: None,
+        ignore_conflicts: true,
+        hash: None,
+        filters: FilterArguments::default(),
+    };
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    let config = read_config(args.config_file.as_ref().unwrap()).unwrap();
+    assert!(config.profiles[0].mods[1].ignore_conflicts);
+
+    args.subcommand = SubCommands::Upgrade {
+        offline: false,
+        dry_run: false,
+        changelogs: false,
+        yes: true,
+    };
+    assert_matches!(actual_main(args).await, Ok(()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_offline() {
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::Update,
+        local_profile(Environment::Both, "Sodium.jar"),
+    );
+    write(output_dir.join("Stale.jar"), "").unwrap();
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args).await, Ok(()));
+    assert!(output_dir.join("Sodium.jar").is_file());
+    assert!(output_dir.join(".old").join("Stale.jar").is_file());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_offline_with_unused_mod() {
+    let mut profile = local_profile(Environment::Server, "Starlight.jar");
+    let mut client_mod = local_profile(Environment::Server, "Sodium.jar")
+        .mods
+        .remove(0);
+    client_mod.environment = Some(Environment::Client);
+    profile.mods.push(client_mod);
+    let (mut args, output_dir) = get_args_with_profile(SubCommands::Update, profile);
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args).await, Ok(()));
+    assert!(output_dir.join("Starlight.jar").is_file());
+    assert!(!output_dir.join("Sodium.jar").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_keeps_user_resource_packs() {
+    let mut profile = local_profile(Environment::Both, "Starlight.jar");
+    profile.mods[0].project_type = ProjectType::ResourcePack;
+    let (mut args, output_dir) = get_args_with_profile(SubCommands::Update, profile);
+    let resource_packs = ProjectType::ResourcePack.install_dir(&output_dir);
+    create_dir_all(&resource_packs).unwrap();
+    write(resource_packs.join("User Pack.zip"), "").unwrap();
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    assert!(resource_packs.join("Starlight.jar").is_file());
+
+    // Replace the pack, so that the file installed before has to be moved out
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods[0] = local_profile(Environment::Both, "Sodium.jar")
+        .mods
+        .remove(0);
+    config.profiles[0].mods[0].project_type = ProjectType::ResourcePack;
+    write_config(&config_file, &config).unwrap();
+    args.subcommand = SubCommands::Update;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args).await, Ok(()));
+
+    assert_eq!(
+        directory_contents(&resource_packs),
+        [
+            resource_packs.join(".old"),
+            resource_packs.join(".old").join("Starlight.jar"),
+            resource_packs.join("Sodium.jar"),
+            resource_packs.join("User Pack.zip"),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_maven_artifact_without_https() {
+    let mut profile = local_profile(Environment::Both, "Sodium.jar");
+    // A repository URL that `ferium add` rejects, but that can be written into the config by hand
+    profile.mods.push(Mod::new(
+        "fabric-api".to_owned(),
+        ModIdentifier::MavenArtifact(
+            MavenArtifact {
+                repository: "http://maven.fabricmc.net".parse().unwrap(),
+                group: "net.fabricmc.fabric-api".to_owned(),
+                artifact: "fabric-api".to_owned(),
+                classifier: None,
+            },
+            None,
+        ),
+        vec![],
+        false,
+    ));
+    let (args, output_dir) = get_args_with_profile(SubCommands::Update, profile);
+    assert_matches!(actual_main(args).await, Err(_));
+    // The artifact fails to resolve without stopping the other mods from being locked
+    assert_matches!(
+        read_lockfile(&output_dir).unwrap().unwrap().mods.as_slice(),
+        [LockedMod { filename, .. }] if filename == "Sodium.jar"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn forge_repository_without_https() {
+    let mut profile = local_profile(Environment::Both, "Sodium.jar");
+    // The scheme is missing, so this parses as a URL with the scheme `codeberg.org` that has no path
+    profile.mods.push(Mod::new(
+        "sodium-extra".to_owned(),
+        ModIdentifier::ForgeRepository(
+            ForgeRepository {
+                forge: Forge::Forgejo,
+                instance: "codeberg.org:443".parse().unwrap(),
+                path: "owner/sodium-extra".to_owned(),
+            },
+            None,
+        ),
+        vec![],
+        false,
+    ));
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::List {
+            verbose: true,
+            markdown: false,
+        },
+        profile,
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = SubCommands::Update;
+    assert_matches!(actual_main(args).await, Err(_));
+    assert_matches!(
+        read_lockfile(&output_dir).unwrap().unwrap().mods.as_slice(),
+        [LockedMod { filename, .. }] if filename == "Sodium.jar"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_datapack() {
+    let mut profile = local_profile(Environment::Both, "Starlight.jar");
+    profile.mods[0].project_type = ProjectType::Datapack;
+    let (mut args, output_dir) = get_args_with_profile(SubCommands::Update, profile);
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args).await, Ok(()));
+
+    let datapacks = output_dir.parent().unwrap().join("datapacks");
+    assert_eq!(ProjectType::Datapack.install_dir(&output_dir), datapacks);
+    assert!(datapacks.join("Starlight.jar").is_file());
+    assert!(!output_dir.join("Starlight.jar").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_offline_missing_file() {
+    let mut profile = local_profile(Environment::Both, "Sodium.jar");
+    profile.mods = vec![Mod::new(
+        "Incendium".to_owned(),
+        ModIdentifier::CurseForgeProject(591_388, None),
+        vec![],
+        false,
+    )];
+    let (args, output_dir) = get_args_with_profile(UPGRADE_OFFLINE, profile.clone());
+    // A locked file that was never downloaded, so it isn't in the cache
+    write_lockfile(
+        &output_dir,
+        &Lockfile {
+            environment: profile.environment,
+            filters: profile.filters,
+            mods: vec![LockedMod {
+                identifier: ModIdentifier::CurseForgeProject(591_388, Some(1)),
+                project_type: ProjectType::Mod,
+                filename: "incendium-missing.jar".to_owned(),
+                download_url: "https://edge.forgecdn.net/files/0/1/incendium-missing.jar"
+                    .parse()
+                    .unwrap(),
+                length: 1,
+                hashes: Hashes::from_hex(&"0".repeat(40)).unwrap(),
+                filters: vec![],
+                override_filters: false,
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                dependency_of: vec![],
+            }],
+            unused: vec![],
+            replaced: vec![],
+        },
+    )
+    .unwrap();
+    write(output_dir.join("Stale.jar"), "").unwrap();
+    let contents = directory_contents(&output_dir);
+
+    assert_matches!(actual_main(args).await, Err(_));
+    assert_eq!(directory_contents(&output_dir), contents);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn outdated() {
+    assert_matches!(
+        actual_main(get_args(SubCommands::Outdated, Some("one_profile_full"))).await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn outdated_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(SubCommands::Outdated, Some("one_profile_full"))
+        })
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changelog() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Changelog { mod_name: None },
+            Some("one_profile_full")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changelog_not_present() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Changelog {
+                mod_name: Some("Not A Mod".to_owned())
+            },
+            Some("one_profile_full")
+        ))
+        .await,
+        Err(_),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rollback_list() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Rollback {
+                to: None,
+                list: true
+            },
+            Some("one_profile_full")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rollback_to_missing() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Rollback {
+                to: Some("not-a-snapshot".to_owned()),
+                list: false
+            },
+            Some("one_profile_full")
+        ))
+        .await,
+        Err(_),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rollback_and_undo() {
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::Update,
+        local_profile(Environment::Both, "Sodium.jar"),
+    );
+    write(output_dir.join("Stale.jar"), "").unwrap();
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    args.subcommand = SubCommands::Rollback {
+        to: None,
+        list: false,
+    };
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    assert!(output_dir.join("Stale.jar").is_file());
+    assert!(!output_dir.join("Sodium.jar").exists());
+    // The file that was removed is in the snapshot taken before rolling back, instead of the `.old` folder
+    assert!(!output_dir.join(".old").join("Sodium.jar").exists());
+
+    // Undo the rollback by restoring the snapshot it took
+    let saved = snapshot::list(&output_dir).unwrap().pop().unwrap();
+    assert!(saved.rolled_back_to().is_some());
+    args.subcommand = SubCommands::Rollback {
+        to: Some(saved.id),
+        list: false,
+    };
+    assert_matches!(actual_main(args).await, Ok(()));
+    assert!(output_dir.join("Sodium.jar").is_file());
+    // Ferium didn't install the stale file, so restoring a snapshot without it doesn't delete it
+    assert!(output_dir.join("Stale.jar").is_file());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rollback_twice() {
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::Update,
+        local_profile(Environment::Both, "Sodium.jar"),
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    let config_file = args.config_file.clone().unwrap();
+    let mut config = read_config(&config_file).unwrap();
+    config.profiles[0].mods = local_profile(Environment::Both, "Starlight.jar").mods;
+    write_config(&config_file, &config).unwrap();
+    args.subcommand = SubCommands::Update;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    // A file the user added after the snapshots were taken
+    write(output_dir.join("Dropped.jar"), "").unwrap();
+
+    args.subcommand = SubCommands::Rollback {
+        to: None,
+        list: false,
+    };
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    assert!(output_dir.join("Sodium.jar").is_file());
+    assert!(!output_dir.join("Starlight.jar").exists());
+
+    // Rolling back again goes back to before Sodium was installed, instead of undoing the last rollback
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    assert!(!output_dir.join("Sodium.jar").exists());
+    assert!(!output_dir.join("Starlight.jar").exists());
+    assert!(output_dir.join("Dropped.jar").is_file());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rollback_json() {
+    let (mut args, output_dir) = get_args_with_profile(
+        SubCommands::Update,
+        local_profile(Environment::Both, "Sodium.jar"),
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+    args.subcommand = UPGRADE_OFFLINE;
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    assert_matches!(
+        actual_main(Ferium {
+            subcommand: SubCommands::Rollback {
+                to: None,
+                list: false,
+            },
+            output_format: OutputFormat::Json,
+            ..args
+        })
+        .await,
+        Ok(()),
+    );
+    assert!(!output_dir.join("Sodium.jar").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_restarts_on_wrong_range() {
+    const CONTENTS: &str = "the new file";
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/File.jar", listener.local_addr().unwrap());
+    // A server that ignores the requested range, and sends the whole file as a partial response
+    thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let mut request = [0; 4096];
+            let len = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..len]).to_lowercase();
+            let status = if request.contains("range: bytes=") {
+                format!(
+                    "206 Partial Content\r\ncontent-range: bytes 0-11/{}",
+                    CONTENTS.len()
+                )
+            } else {
+                "200 OK\r\netag: \"new\"".to_owned()
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{CONTENTS}",
+                CONTENTS.len()
+            );
+        }
+    });
+
+    let output_dir = current_dir()
+        .unwrap()
+        .join("tests")
+        .join("mods")
+        .join(format!("{:X}", rand::random::<u32>()));
+    create_dir_all(&output_dir).unwrap();
+    let out_file_path = output_dir.join("File.jar");
+    // A partial download of an older version of the file
+    write(out_file_path.with_extension("part"), "old ").unwrap();
+    write(validator_path(&out_file_path), "\"old\"").unwrap();
+
+    let download_data = DownloadData {
+        identifier: None,
+        download_url: url.parse().unwrap(),
+        output: "File.jar".into(),
+        length: CONTENTS.len(),
+        hashes: Hashes::default(),
+        channel: None,
+        dependencies: Vec::new(),
+        optional_dependencies: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    assert_matches!(
+        download_data
+            .download(reqwest::Client::new(), &output_dir, |_| ())
+            .await,
+        Ok(_)
+    );
+    assert_eq!(read_to_string(&out_file_path).unwrap(), CONTENTS);
+    assert!(!validator_path(&out_file_path).exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cache_info() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Cache { subcommand: None },
+            Some("empty")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_md_modpacks() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Modpack {
+                subcommand: Some(ModpackSubCommands::Upgrade)
+            },
+            Some("two_modpacks_mdactive")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upgrade_cf_modpack() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Modpack {
+                subcommand: Some(ModpackSubCommands::Upgrade)
+            },
+            Some("two_modpacks_cfactive")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn profile_switch() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Profile {
+                subcommand: Some(ProfileSubCommands::Switch {
+                    profile_name: Some("Profile Two".to_owned())
+                })
+            },
+            Some("two_profiles_one_empty")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn modpack_switch() {
     assert_matches!(
         actual_main(get_args(
             SubCommands::Modpack {
@@ -510,6 +1817,28 @@ async fn remove_fail() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn remove_with_dependencies() {
+    let args = get_args(
+        SubCommands::Remove {
+            mod_names: vec!["sodium".to_owned()],
+        },
+        Some("one_profile_dependencies"),
+    );
+    assert_matches!(actual_main(args.clone()).await, Ok(()));
+
+    // Fabric API was only needed by Sodium, but Indium is still needed by Starlight
+    let config = read_config(args.config_file.unwrap()).unwrap();
+    assert_eq!(
+        config.profiles[0]
+            .mods
+            .iter()
+            .map(|mod_| mod_.name.as_str())
+            .collect::<Vec<_>>(),
+        ["Indium", "Starlight (Fabric)"]
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn remove_name() {
     assert_matches!(
@@ -601,3 +1930,38 @@ async fn delete_modpack() {
         Ok(()),
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search() {
+    assert_matches!(
+        actual_main(get_args(
+            SubCommands::Search {
+                query: "sodium".to_owned(),
+                add: false,
+                platform: Platform::default(),
+            },
+            Some("one_profile_full")
+        ))
+        .await,
+        Ok(()),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_add_json() {
+    assert_matches!(
+        actual_main(Ferium {
+            output_format: OutputFormat::Json,
+            ..get_args(
+                SubCommands::Search {
+                    query: "sodium".to_owned(),
+                    add: true,
+                    platform: Platform::default(),
+                },
+                Some("one_profile_full"),
+            )
+        })
+        .await,
+        Err(_),
+    );
+}